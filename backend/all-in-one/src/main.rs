@@ -0,0 +1,210 @@
+//! The indexer and the server in one process, for hobbyist operators and local
+//! development: blocks are processed in process and handed to the boards over
+//! channels instead of the Valkey queue, and the boards persist to an embedded store
+//! in `DATA_DIR`. Serves the same HTTP/WS API as the server.
+
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use indexer::processor::{self, BlockRef, BoardRoute, Discontinuity, IndexMode, ProcessorStats};
+use indexer::{config as indexer_config, metrics};
+use server::consumer::{BlockEvents, EventSource};
+use server::storage::{BoardStore, EmbeddedStore};
+use server::{api, app, config};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tower_http::cors::CorsLayer;
+
+/// Blocks a board's consumer may trail the processor by before it waits.
+const BOARD_CHANNEL_CAPACITY: usize = 100;
+
+async fn shutdown_signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .expect("failed to register SIGTERM handler");
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT, shutting down..."),
+        _ = sigterm.recv() => tracing::info!("Received SIGTERM, shutting down..."),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let _ = dotenvy::dotenv();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env()
+                .add_directive("all_in_one=info".parse().unwrap())
+                .add_directive("server=info".parse().unwrap())
+                .add_directive("indexer=info".parse().unwrap())
+                .add_directive("neardata-fetcher=info".parse().unwrap()),
+        )
+        .init();
+
+    let config = config::Config::from_env();
+    let chain_id = indexer_config::chain_id();
+    let routes = indexer_config::routes(&config.keyspace, chain_id);
+    let index_mode = indexer_config::index_mode();
+    let relayers = indexer_config::relayers();
+
+    let data_dir = std::env::var("DATA_DIR").unwrap_or_else(|_| "data".into());
+    let store: Arc<dyn BoardStore> = Arc::new(EmbeddedStore::open(&data_dir)?);
+
+    // Every hosted board gets a channel, so boards without contracts keep a live
+    // (idle) consumer like they would with the indexer
+    let mut boards = HashMap::new();
+    let mut senders = HashMap::new();
+    for board_config in &config.boards {
+        let (tx, rx) = mpsc::channel(BOARD_CHANNEL_CAPACITY);
+        let source = EventSource::Channel(rx);
+        let handle = app::start_board_with(&config, store.clone(), source, board_config).await?;
+        boards.insert(board_config.id.clone(), handle);
+        senders.insert(board_config.id.clone(), tx);
+    }
+    let route_senders = routes
+        .iter()
+        .map(|route| {
+            senders.get(route.keys.board()).cloned().ok_or_else(|| {
+                anyhow::anyhow!("board {} receives draws but is not hosted", route.keys.board())
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Resume after the least advanced board, falling back to START_BLOCK_HEIGHT env var
+    let mut last_processed: Option<BlockRef> = None;
+    for route in &routes {
+        if let Some((height, hash)) = store.last_processed_block(&route.keys).await? {
+            if last_processed.as_ref().is_none_or(|l| height < l.height) {
+                last_processed = Some(BlockRef { height, hash });
+            }
+        }
+    }
+    let start_block = last_processed.as_ref().map(|b| b.height + 1).or_else(|| {
+        std::env::var("START_BLOCK_HEIGHT")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+    });
+
+    tracing::info!(
+        "Starting all-in-one {} board from block {:?} ({:?} mode, data in {}, relayers: {:?})",
+        chain_id,
+        start_block,
+        index_mode,
+        data_dir,
+        relayers
+    );
+    for route in &routes {
+        tracing::info!("Board {}: contracts {:?}", route.keys.board(), route.contract_ids);
+    }
+
+    // Optional Prometheus listener for the indexer metrics, e.g. METRICS_ADDR=0.0.0.0:9100
+    if let Ok(addr) = std::env::var("METRICS_ADDR") {
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(&addr).await {
+                tracing::error!("Metrics listener on {} failed: {}", addr, e);
+            }
+        });
+    }
+
+    let is_running = Arc::new(AtomicBool::new(true));
+    let (blocks_tx, blocks_rx) = mpsc::channel(100);
+    let fetcher_handle =
+        indexer_config::start_blocks(chain_id, start_block, blocks_tx, is_running.clone())?;
+
+    let processor_running = is_running.clone();
+    tokio::spawn(async move {
+        let result = process_blocks(
+            blocks_rx,
+            processor_running,
+            &routes,
+            &route_senders,
+            index_mode,
+            &relayers,
+            last_processed,
+        )
+        .await;
+        // On a discontinuity keep serving the board as indexed so far; the halt shows
+        // in metrics and health until an operator looks at the chain data
+        if let Err(d) = result {
+            tracing::error!("Stopped indexing: chain discontinuity: {}", d);
+        }
+    });
+
+    let state = api::AppState {
+        boards: Arc::new(boards),
+        valkey: None,
+        health: config.health,
+    };
+
+    let app = api::router(state).layer(CorsLayer::permissive());
+
+    let listener = tokio::net::TcpListener::bind(&config.listen_addr).await?;
+    tracing::info!("Server listening on {}", config.listen_addr);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    is_running.store(false, Ordering::SeqCst);
+    fetcher_handle.abort();
+    // Held until here so idle boards' consumers are not closed early
+    drop(senders);
+
+    tracing::info!("All-in-one stopped.");
+    Ok(())
+}
+
+/// Hand every block after `last` to each routed board, with the events of its
+/// contracts, until the channel closes or `is_running` is cleared. A board records
+/// the block as processed once it has applied them.
+///
+/// Stops at the first block that does not continue the chain from the previous one.
+async fn process_blocks(
+    mut blocks_rx: mpsc::Receiver<BlockWithTxHashes>,
+    is_running: Arc<AtomicBool>,
+    routes: &[BoardRoute],
+    senders: &[mpsc::Sender<BlockEvents>],
+    mode: IndexMode,
+    relayers: &[String],
+    mut last: Option<BlockRef>,
+) -> Result<(), Discontinuity> {
+    let mut stats = ProcessorStats::default();
+
+    while is_running.load(Ordering::SeqCst) {
+        let Some(block) = blocks_rx.recv().await else {
+            break;
+        };
+
+        let buffered = blocks_rx.len();
+        let prepared = processor::prepare_block(
+            &block,
+            last.as_ref(),
+            routes,
+            mode,
+            relayers,
+            &mut stats,
+            buffered,
+        )?;
+        for (route, tx) in senders.iter().enumerate() {
+            let events = prepared
+                .events
+                .iter()
+                .filter(|(r, _)| *r == route)
+                .map(|(_, event)| event.clone())
+                .collect();
+            let block_events = BlockEvents {
+                block_height: prepared.block.height,
+                block_hash: prepared.hash().to_string(),
+                heartbeat: prepared.heartbeat,
+                events,
+            };
+            if tx.send(block_events).await.is_err() {
+                // The board's consumer is gone, which only happens on shutdown
+                return Ok(());
+            }
+        }
+        processor::finish_block(&prepared, &stats);
+        last = Some(prepared.block);
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use crate::pricing::Pricing;
+use crate::region::{REGION_OPEN_THRESHOLD, REGION_SIZE};
+use crate::valkey::is_valid_board_id;
+use serde::Deserialize;
+
+/// How long a drawn pixel can be overwritten before it becomes permanent (one hour).
+pub const DEFAULT_OWNERSHIP_DURATION_MS: u64 = 3_600_000;
+
+/// Per-board drawing rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct BoardRules {
+    /// Age after which a pixel is permanent.
+    pub ownership_duration_ms: u64,
+    /// Drawn pixels a region needs before its cardinal neighbors open.
+    pub region_open_threshold: i64,
+    /// If set, only regions with `|rx|` and `|ry|` at most this far from (0,0) ever open.
+    pub max_region_radius: Option<i32>,
+    /// Fixed canvas size. A bounded board spans pixels `[0, width) x [0, height)`; every
+    /// region covering it is open from the start of each season and no others ever open.
+    pub bounds: Option<BoardBounds>,
+    /// When seasons end. Without a schedule the board has a single endless season.
+    pub seasons: Option<SeasonSchedule>,
+    /// Prices of this board's contract; free unless set.
+    pub pricing: Pricing,
+}
+
+/// Size of a bounded board in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct BoardBounds {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoardBounds {
+    /// Inclusive (rx, ry) range of the regions covering the board.
+    pub fn region_range(&self) -> ((i32, i32), (i32, i32)) {
+        let last = |size: u32| (size.div_ceil(REGION_SIZE as u32) as i32 - 1).max(0);
+        ((0, 0), (last(self.width), last(self.height)))
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+    }
+}
+
+/// Season end points, by block height and/or block timestamp; a season ends at
+/// whichever bound is reached first. Season `n` ends at `end + n * length` for each
+/// bound, so a bound without a length only ends season 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct SeasonSchedule {
+    pub end_block_height: Option<u64>,
+    pub end_timestamp_ms: Option<u64>,
+    pub length_blocks: Option<u64>,
+    pub length_ms: Option<u64>,
+}
+
+impl SeasonSchedule {
+    /// Whether `season` has ended by the given block.
+    pub fn has_ended(&self, season: u32, block_height: u64, block_timestamp_ms: u64) -> bool {
+        let ended = |at: u64, end: Option<u64>, length: Option<u64>| {
+            let end = match (end, length) {
+                (Some(end), _) if season == 0 => end,
+                (Some(end), Some(length)) => {
+                    end.saturating_add(length.saturating_mul(season as u64))
+                }
+                _ => return false,
+            };
+            at >= end
+        };
+        ended(block_height, self.end_block_height, self.length_blocks)
+            || ended(block_timestamp_ms, self.end_timestamp_ms, self.length_ms)
+    }
+}
+
+impl Default for BoardRules {
+    fn default() -> Self {
+        Self {
+            ownership_duration_ms: DEFAULT_OWNERSHIP_DURATION_MS,
+            region_open_threshold: REGION_OPEN_THRESHOLD,
+            max_region_radius: None,
+            bounds: None,
+            seasons: None,
+            pricing: Pricing::default(),
+        }
+    }
+}
+
+impl BoardRules {
+    /// Whether region (rx, ry) lies within the board's size limits.
+    pub fn region_in_bounds(&self, rx: i32, ry: i32) -> bool {
+        let in_radius = self
+            .max_region_radius
+            .is_none_or(|r| rx.unsigned_abs() <= r as u32 && ry.unsigned_abs() <= r as u32);
+        let in_bounds = self.bounds.is_none_or(|b| {
+            let ((min_x, min_y), (max_x, max_y)) = b.region_range();
+            (min_x..=max_x).contains(&rx) && (min_y..=max_y).contains(&ry)
+        });
+        in_radius && in_bounds
+    }
+
+    /// Whether pixel (x, y) may be drawn on a bounded board (always true if unbounded).
+    pub fn pixel_in_bounds(&self, x: i32, y: i32) -> bool {
+        self.bounds.is_none_or(|b| b.contains(x, y))
+    }
+
+    /// Regions open at the start of every season: all of a bounded board, else (0,0).
+    pub fn initial_regions(&self) -> Vec<(i32, i32)> {
+        match self.bounds {
+            Some(bounds) => {
+                let ((min_x, min_y), (max_x, max_y)) = bounds.region_range();
+                (min_y..=max_y)
+                    .flat_map(|ry| (min_x..=max_x).map(move |rx| (rx, ry)))
+                    .collect()
+            }
+            None => vec![(0, 0)],
+        }
+    }
+}
+
+/// One board: the contracts whose draws feed it and the rules it is drawn under.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BoardConfig {
+    pub id: String,
+    /// Contract accounts routed to this board by the indexer.
+    #[serde(default)]
+    pub contract_ids: Vec<String>,
+    #[serde(default)]
+    pub rules: BoardRules,
+}
+
+/// Load the board list shared by the indexer and server from a JSON file:
+/// `[{"id": "default", "contract_ids": ["berryfast.near"]}, {"id": "halloween", ...}]`.
+pub fn load_boards(path: &str) -> Result<Vec<BoardConfig>, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let boards: Vec<BoardConfig> =
+        serde_json::from_str(&json).map_err(|e| format!("{path}: {e}"))?;
+    validate_boards(&boards)?;
+    Ok(boards)
+}
+
+/// Check board ids are valid and unique, rules are sane, and no contract feeds two boards.
+pub fn validate_boards(boards: &[BoardConfig]) -> Result<(), String> {
+    if boards.is_empty() {
+        return Err("no boards configured".into());
+    }
+    for (i, board) in boards.iter().enumerate() {
+        if !is_valid_board_id(&board.id) {
+            return Err(format!("invalid board id: {}", board.id));
+        }
+        if boards[..i].iter().any(|b| b.id == board.id) {
+            return Err(format!("duplicate board id: {}", board.id));
+        }
+        let rules = &board.rules;
+        let bad_bounds = rules.bounds.is_some_and(|b| b.width == 0 || b.height == 0);
+        let bad_radius = rules.max_region_radius.is_some_and(|r| r < 0);
+        let bad_seasons = rules
+            .seasons
+            .is_some_and(|s| s.length_blocks == Some(0) || s.length_ms == Some(0));
+        if rules.region_open_threshold <= 0 || bad_radius || bad_bounds || bad_seasons {
+            return Err(format!("invalid rules for board {}", board.id));
+        }
+        for contract in &board.contract_ids {
+            if let Some(other) = boards[..i].iter().find(|b| b.contract_ids.contains(contract)) {
+                return Err(format!(
+                    "contract {contract} feeds both {} and {}",
+                    other.id, board.id
+                ));
+            }
+        }
+    }
+    Ok(())
+}
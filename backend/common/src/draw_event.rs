@@ -22,6 +22,48 @@ pub struct DrawEvent {
     pub block_height: u64,
     pub block_timestamp_ms: u64,
     pub pixels: Vec<DrawPixel>,
+    /// Attached deposit in yoctoNEAR. Missing on events queued before paid drawing.
+    #[serde(default, with = "u128_dec_format")]
+    pub deposit: u128,
+    /// Position among the block's events queued for the same board. Missing on events
+    /// queued before the indexer numbered them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_index: Option<u32>,
+}
+
+impl DrawEvent {
+    /// `(block_height, event_index)`, which increases along a board's queue, so a
+    /// consumer can recognize an event it already applied. `None` for unnumbered events.
+    pub fn position(&self) -> Option<(u64, u32)> {
+        Some((self.block_height, self.event_index?))
+    }
+}
+
+/// Prefix of NEP-297 event logs emitted by the contract.
+pub const EVENT_LOG_PREFIX: &str = "EVENT_JSON:";
+
+/// NEP-297 `standard` of the contract's events.
+pub const DRAW_EVENT_STANDARD: &str = "berrydraw";
+
+/// NEP-297 `event` name logged by a successful `draw` call.
+pub const DRAW_EVENT_NAME: &str = "draw";
+
+/// A NEP-297 event log emitted by the contract (after the `EVENT_JSON:` prefix).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrawEventLog {
+    pub standard: String,
+    pub version: String,
+    pub event: String,
+    pub data: Vec<DrawEventLogData>,
+}
+
+/// One entry of a `draw` event's `data` array.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrawEventLogData {
+    pub account_id: String,
+    #[serde(default, with = "u128_dec_format")]
+    pub deposit: u128,
+    pub pixels: Vec<DrawPixel>,
 }
 
 impl DrawPixel {
@@ -36,3 +78,18 @@ impl DrawPixel {
         Some((r, g, b))
     }
 }
+
+/// Serialize a u128 yoctoNEAR amount as a decimal string, as NEAR's JSON does.
+pub mod u128_dec_format {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
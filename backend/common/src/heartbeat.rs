@@ -0,0 +1,65 @@
+use crate::valkey::Keyspace;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+
+/// The indexer's progress after its latest block, stored as a hash at
+/// `Keyspace::indexer_heartbeat` so the server can tell how far behind the chain it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexerHeartbeat {
+    pub block_height: u64,
+    pub block_timestamp_ms: u64,
+    /// Wall clock when the indexer finished the block.
+    pub updated_ms: u64,
+    /// Blocks fetched but not yet processed, i.e. how far the processor trails the fetcher.
+    pub buffered_blocks: u64,
+}
+
+impl IndexerHeartbeat {
+    /// How far the block trailed the wall clock when it was processed.
+    pub fn lag_ms(&self) -> u64 {
+        self.updated_ms.saturating_sub(self.block_timestamp_ms)
+    }
+
+    /// Hash fields to store.
+    pub fn fields(&self) -> [(&'static str, u64); 4] {
+        [
+            ("block_height", self.block_height),
+            ("block_timestamp_ms", self.block_timestamp_ms),
+            ("updated_ms", self.updated_ms),
+            ("buffered_blocks", self.buffered_blocks),
+        ]
+    }
+
+    /// Parse a stored hash; `None` if no heartbeat was ever written.
+    pub fn from_fields(fields: &HashMap<String, u64>) -> Option<Self> {
+        let field = |name: &str| fields.get(name).copied().unwrap_or(0);
+        Some(Self {
+            updated_ms: *fields.get("updated_ms")?,
+            block_height: field("block_height"),
+            block_timestamp_ms: field("block_timestamp_ms"),
+            buffered_blocks: field("buffered_blocks"),
+        })
+    }
+
+    pub async fn read(
+        con: &mut redis::aio::MultiplexedConnection,
+        keys: &Keyspace,
+    ) -> redis::RedisResult<Option<Self>> {
+        let fields: HashMap<String, u64> = con.hgetall(keys.indexer_heartbeat()).await?;
+        Ok(Self::from_fields(&fields))
+    }
+
+    /// The WebSocket `indexer_status` message, with `behind_ms` measured at `now_ms`
+    /// (so it keeps growing if the indexer stops).
+    pub fn status_message(&self, now_ms: u64) -> serde_json::Value {
+        serde_json::json!({
+            "type": "indexer_status",
+            "block_height": self.block_height,
+            "block_timestamp_ms": self.block_timestamp_ms,
+            "updated_ms": self.updated_ms,
+            "lag_ms": self.lag_ms(),
+            "buffered_blocks": self.buffered_blocks,
+            "behind_ms": now_ms.saturating_sub(self.block_timestamp_ms),
+        })
+    }
+}
@@ -1,6 +1,13 @@
+pub mod boards;
 pub mod draw_event;
+pub mod heartbeat;
+pub mod owner_ids;
+pub mod pricing;
 pub mod region;
 pub mod valkey;
 
+pub use boards::{BoardConfig, BoardRules};
 pub use draw_event::*;
+pub use heartbeat::IndexerHeartbeat;
+pub use pricing::Pricing;
 pub use region::*;
@@ -0,0 +1,170 @@
+use crate::valkey::Keyspace;
+use redis::AsyncCommands;
+use std::collections::{BTreeMap, HashMap};
+
+/// Atomically resolve an account to its owner id, allocating a new one if needed.
+///
+/// KEYS[1] = account_to_id, KEYS[2] = id_to_account, KEYS[3] = owner_id_counter.
+/// ARGV[1] = account_id, ARGV[2] = largest owner id that may be handed out.
+///
+/// The counter is seeded from the highest existing id on first use, so boards
+/// created with the old `HLEN + 1` scheme continue from where they left off.
+/// HSETNX on the reverse mapping guards against ids claimed outside this script.
+const ALLOCATE_SCRIPT: &str = r#"
+local id = redis.call('HGET', KEYS[1], ARGV[1])
+if id then
+    return tonumber(id)
+end
+if redis.call('EXISTS', KEYS[3]) == 0 then
+    local max = 0
+    for _, k in ipairs(redis.call('HKEYS', KEYS[2])) do
+        local n = tonumber(k)
+        if n and n > max then
+            max = n
+        end
+    end
+    redis.call('SET', KEYS[3], max)
+end
+while true do
+    local new_id = redis.call('INCR', KEYS[3])
+    if new_id > tonumber(ARGV[2]) then
+        redis.call('DECR', KEYS[3])
+        return redis.error_reply('OWNER_IDS_EXHAUSTED owner id space exhausted')
+    end
+    if redis.call('HSETNX', KEYS[2], new_id, ARGV[1]) == 1 then
+        redis.call('HSET', KEYS[1], ARGV[1], new_id)
+        return new_id
+    end
+end
+"#;
+
+/// Error code of the reply `resolve_or_allocate` fails with when no ids are left.
+pub const EXHAUSTED_ERROR_CODE: &str = "OWNER_IDS_EXHAUSTED";
+
+/// Whether `err` is `resolve_or_allocate` running out of ids, as opposed to Valkey
+/// failing.
+pub fn is_exhausted(err: &redis::RedisError) -> bool {
+    err.code() == Some(EXHAUSTED_ERROR_CODE)
+}
+
+/// Resolve an account_id to a u32 owner index, creating a new one if needed.
+/// IDs start at 1; 0 is reserved as the "undrawn" sentinel.
+///
+/// Allocation fails with an error once the next id would exceed `max_owner_id`,
+/// so ids never wrap inside a narrower pixel encoding; see `is_exhausted`.
+pub async fn resolve_or_allocate(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keyspace,
+    account_id: &str,
+    max_owner_id: u32,
+) -> redis::RedisResult<u32> {
+    redis::Script::new(ALLOCATE_SCRIPT)
+        .key(keys.account_to_id())
+        .key(keys.id_to_account())
+        .key(keys.owner_id_counter())
+        .arg(account_id)
+        .arg(max_owner_id)
+        .invoke_async(con)
+        .await
+}
+
+/// Result of scanning the owner id mappings for inconsistencies.
+#[derive(Debug, Default)]
+pub struct ConsistencyReport {
+    /// Accounts that shared an id with another account: (account, old id, new id).
+    /// The new id is 0 when running without `repair`.
+    pub reassigned: Vec<(String, u32, u32)>,
+    /// Ids whose reverse (id → account) mapping was missing and was restored.
+    pub restored_reverse: Vec<(u32, String)>,
+    /// Accounts whose forward (account → id) mapping was missing and was restored.
+    pub restored_forward: Vec<(String, u32)>,
+    /// Whether the allocation counter was behind the highest id in use.
+    pub counter_bumped: bool,
+}
+
+impl ConsistencyReport {
+    pub fn is_clean(&self) -> bool {
+        self.reassigned.is_empty()
+            && self.restored_reverse.is_empty()
+            && self.restored_forward.is_empty()
+            && !self.counter_bumped
+    }
+}
+
+/// Scan `ACCOUNT_TO_ID` / `ID_TO_ACCOUNT` for collisions and dangling entries.
+///
+/// With `repair`, colliding accounts keep their id only if the reverse mapping names
+/// them (that is the account existing pixels display as); every other account on the
+/// same id gets a fresh one. Pixels already written under a shared id stay attributed
+/// to the reverse-mapped account, since the blobs cannot tell the two apart.
+///
+/// Must not run concurrently with draws from a colliding account.
+pub async fn check_and_repair(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keyspace,
+    repair: bool,
+    max_owner_id: u32,
+) -> redis::RedisResult<ConsistencyReport> {
+    let forward: HashMap<String, u32> = con.hgetall(keys.account_to_id()).await?;
+    let reverse: HashMap<u32, String> = con.hgetall(keys.id_to_account()).await?;
+    let mut report = ConsistencyReport::default();
+
+    // id → accounts claiming it in the forward mapping (sorted for determinism)
+    let mut claimants: BTreeMap<u32, Vec<&String>> = BTreeMap::new();
+    for (account, id) in &forward {
+        claimants.entry(*id).or_default().push(account);
+    }
+
+    for (id, accounts) in claimants.iter_mut() {
+        accounts.sort();
+        let keeper: Option<String> = match reverse.get(id) {
+            Some(name) if accounts.contains(&name) => Some(name.clone()),
+            // Reverse mapping names an account that does not claim this id;
+            // every claimant needs a new id.
+            Some(_) => None,
+            None => {
+                let keeper = accounts[0].clone();
+                if repair {
+                    let _: () = con.hset_nx(keys.id_to_account(), *id, &keeper).await?;
+                }
+                report.restored_reverse.push((*id, keeper.clone()));
+                Some(keeper)
+            }
+        };
+
+        for account in accounts.iter().filter(|a| keeper.as_ref() != Some(**a)) {
+            let new_id = if repair {
+                let _: () = con.hdel(keys.account_to_id(), account.as_str()).await?;
+                resolve_or_allocate(con, keys, account, max_owner_id).await?
+            } else {
+                0
+            };
+            report.reassigned.push(((*account).clone(), *id, new_id));
+        }
+    }
+
+    for (id, account) in &reverse {
+        if !forward.contains_key(account) {
+            if repair {
+                let _: () = con.hset_nx(keys.account_to_id(), account, *id).await?;
+            }
+            report.restored_forward.push((account.clone(), *id));
+        }
+    }
+
+    let max_id = forward
+        .values()
+        .chain(reverse.keys())
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let counter: Option<u32> = con.get(keys.owner_id_counter()).await?;
+    if counter.is_some_and(|c| c < max_id) {
+        if repair {
+            let _: () = con.set(keys.owner_id_counter(), max_id).await?;
+        }
+        report.counter_bumped = true;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,91 @@
+/// Price per pixel in yoctoNEAR of a contract built without `PIXEL_PRICE_YOCTO`
+/// (0.0001 NEAR). The contract and the frontend default to the same prices.
+pub const DEFAULT_PIXEL_PRICE: u128 = 100_000_000_000_000_000_000;
+/// Overwrite price in yoctoNEAR of a contract built without `OVERWRITE_PRICE_YOCTO`
+/// (0.0002 NEAR).
+pub const DEFAULT_OVERWRITE_PRICE: u128 = 200_000_000_000_000_000_000;
+
+use serde::Deserialize;
+
+/// Per-pixel draw prices in yoctoNEAR, mirroring the contract's build-time prices.
+///
+/// The contract can only check `pixel_price * pixel_count`; whether a pixel is still
+/// claimed is known off-chain, so the overwrite surcharge is enforced when applying.
+/// Pricing is opt-in: the default is free. A priced board must name the block its
+/// prices start at, since events indexed before then carry no deposit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "PricingConfig")]
+pub struct Pricing {
+    /// Price of drawing on an empty pixel.
+    pub pixel_price: u128,
+    /// Price of overwriting a pixel that is still within its ownership window.
+    pub overwrite_price: u128,
+    /// Events from earlier blocks are free, so replaying history stays deterministic.
+    pub start_block_height: u64,
+}
+
+/// `Pricing` as written in a board's rules, with prices as decimal strings:
+/// `{"pixel_price": "100000000000000000000", ..., "start_block_height": 140000000}`.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PricingConfig {
+    #[serde(with = "crate::draw_event::u128_dec_format")]
+    pixel_price: u128,
+    #[serde(with = "crate::draw_event::u128_dec_format")]
+    overwrite_price: u128,
+    start_block_height: Option<u64>,
+}
+
+impl TryFrom<PricingConfig> for Pricing {
+    type Error = String;
+
+    fn try_from(config: PricingConfig) -> Result<Self, String> {
+        Pricing::new(config.pixel_price, config.overwrite_price, config.start_block_height)
+    }
+}
+
+impl Pricing {
+    /// Prices charged from `start_block_height`, which is required unless both are 0.
+    pub fn new(
+        pixel_price: u128,
+        overwrite_price: u128,
+        start_block_height: Option<u64>,
+    ) -> Result<Self, String> {
+        let free = pixel_price == 0 && overwrite_price == 0;
+        match start_block_height {
+            None if !free => Err("prices are set without a pricing start block".into()),
+            start => Ok(Self {
+                pixel_price,
+                overwrite_price,
+                start_block_height: start.unwrap_or(0),
+            }),
+        }
+    }
+
+    /// Whether draws at `block_height` are charged at all.
+    pub fn applies_to(&self, block_height: u64) -> bool {
+        block_height >= self.start_block_height
+            && (self.pixel_price > 0 || self.overwrite_price > 0)
+    }
+
+    /// Price of a single pixel; `overwrite` when the target is still claimed.
+    pub fn cost(&self, overwrite: bool) -> u128 {
+        if overwrite {
+            self.overwrite_price
+        } else {
+            self.pixel_price
+        }
+    }
+
+    /// Charge one pixel against the draw's remaining deposit. Returns false, leaving
+    /// `remaining` untouched, if the deposit cannot cover it.
+    pub fn try_charge(&self, remaining: &mut u128, overwrite: bool) -> bool {
+        match remaining.checked_sub(self.cost(overwrite)) {
+            Some(left) => {
+                *remaining = left;
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -8,6 +8,21 @@ pub const PIXEL_SIZE: usize = 6;
 /// Total region blob size: 128 * 128 * 6 = 98,304 bytes.
 pub const REGION_BLOB_SIZE: usize = (REGION_SIZE as usize) * (REGION_SIZE as usize) * PIXEL_SIZE;
 
+/// Per-pixel binary size in the wide format: 3 (RGB) + 4 (owner_id u32) = 7 bytes.
+pub const PIXEL_SIZE_WIDE: usize = 7;
+
+/// Total wide region blob size: 128 * 128 * 7 = 114,688 bytes.
+pub const REGION_BLOB_SIZE_WIDE: usize =
+    (REGION_SIZE as usize) * (REGION_SIZE as usize) * PIXEL_SIZE_WIDE;
+
+/// Largest owner id the 24-bit format can store. 0xFFFFFF is reserved as
+/// `OVERFLOW_OWNER_ID`, so the 24-bit space holds 16,777,214 accounts.
+pub const MAX_OWNER_ID_24: u32 = 0xFF_FFFE;
+
+/// Owner id written into 24-bit blobs for pixels whose real owner does not fit.
+/// Clients that see it need the wide format to resolve the owner.
+pub const OVERFLOW_OWNER_ID: u32 = 0xFF_FFFF;
+
 /// Number of drawn pixels required to open a region's cardinal neighbors (~20%).
 pub const REGION_OPEN_THRESHOLD: i64 = (REGION_SIZE as i64 * REGION_SIZE as i64) / 5;
 
@@ -20,6 +35,289 @@ pub struct Pixel {
     pub owner_id: u32,
 }
 
+/// Pixel layout of a region blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionEncoding {
+    /// 3 RGB + 3 owner_id LE (the original headerless layout).
+    Owner24,
+    /// 3 RGB + 4 owner_id LE.
+    Owner32,
+}
+
+/// A pixel's owner does not fit in the target encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnerOverflow {
+    pub owner_id: u32,
+    pub max_owner_id: u32,
+}
+
+impl std::fmt::Display for OwnerOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "owner id {} exceeds the maximum {} for this region encoding",
+            self.owner_id, self.max_owner_id
+        )
+    }
+}
+
+impl std::error::Error for OwnerOverflow {}
+
+impl RegionEncoding {
+    /// Parse an encoding name ("owner24" / "owner32").
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "owner24" => Some(Self::Owner24),
+            "owner32" => Some(Self::Owner32),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Owner24 => "owner24",
+            Self::Owner32 => "owner32",
+        }
+    }
+
+    pub fn pixel_size(self) -> usize {
+        match self {
+            Self::Owner24 => PIXEL_SIZE,
+            Self::Owner32 => PIXEL_SIZE_WIDE,
+        }
+    }
+
+    pub fn blob_size(self) -> usize {
+        match self {
+            Self::Owner24 => REGION_BLOB_SIZE,
+            Self::Owner32 => REGION_BLOB_SIZE_WIDE,
+        }
+    }
+
+    /// Largest owner id a pixel in this encoding can hold.
+    pub fn max_owner_id(self) -> u32 {
+        match self {
+            Self::Owner24 => MAX_OWNER_ID_24,
+            Self::Owner32 => u32::MAX,
+        }
+    }
+
+    /// Detect the encoding of a stored blob from its length.
+    pub fn detect(blob: &[u8]) -> Option<Self> {
+        match blob.len() {
+            REGION_BLOB_SIZE => Some(Self::Owner24),
+            REGION_BLOB_SIZE_WIDE => Some(Self::Owner32),
+            _ => None,
+        }
+    }
+
+    /// Byte offset into a region blob for a local (lx, ly) coordinate.
+    pub fn pixel_offset(self, lx: usize, ly: usize) -> usize {
+        (ly * REGION_SIZE as usize + lx) * self.pixel_size()
+    }
+
+    /// Encode a pixel, failing instead of truncating an owner id that does not fit.
+    pub fn encode(self, pixel: &Pixel, buf: &mut [u8]) -> Result<(), OwnerOverflow> {
+        if pixel.owner_id > self.max_owner_id() {
+            return Err(OwnerOverflow {
+                owner_id: pixel.owner_id,
+                max_owner_id: self.max_owner_id(),
+            });
+        }
+        match self {
+            Self::Owner24 => pixel.encode(buf),
+            Self::Owner32 => pixel.encode_wide(buf),
+        }
+        Ok(())
+    }
+
+    pub fn decode(self, buf: &[u8]) -> Pixel {
+        match self {
+            Self::Owner24 => Pixel::decode(buf),
+            Self::Owner32 => Pixel::decode_wide(buf),
+        }
+    }
+}
+
+/// Re-encode a region blob from one pixel layout to another.
+///
+/// Widening always succeeds. Narrowing fails on the first owner that does not fit;
+/// use `to_legacy_blob` to serve narrowed blobs to old clients instead.
+pub fn convert_region_blob(
+    blob: &[u8],
+    from: RegionEncoding,
+    to: RegionEncoding,
+) -> Result<Vec<u8>, OwnerOverflow> {
+    if from == to {
+        return Ok(blob.to_vec());
+    }
+    let mut out = vec![0u8; to.blob_size()];
+    for (src, dst) in blob
+        .chunks_exact(from.pixel_size())
+        .zip(out.chunks_exact_mut(to.pixel_size()))
+    {
+        to.encode(&from.decode(src), dst)?;
+    }
+    Ok(out)
+}
+
+/// Produce the 24-bit legacy layout of a blob, replacing owners that do not fit
+/// with `OVERFLOW_OWNER_ID`. Colors are preserved exactly.
+pub fn to_legacy_blob(blob: &[u8], from: RegionEncoding) -> Vec<u8> {
+    if from == RegionEncoding::Owner24 {
+        return blob.to_vec();
+    }
+    let mut out = vec![0u8; REGION_BLOB_SIZE];
+    for (src, dst) in blob
+        .chunks_exact(from.pixel_size())
+        .zip(out.chunks_exact_mut(PIXEL_SIZE))
+    {
+        let mut pixel = from.decode(src);
+        if pixel.owner_id > MAX_OWNER_ID_24 {
+            pixel.owner_id = OVERFLOW_OWNER_ID;
+        }
+        pixel.encode(dst);
+    }
+    out
+}
+
+/// Magic bytes at the start of every versioned region container.
+pub const REGION_MAGIC: [u8; 4] = *b"BDBR";
+
+/// Current region container version. Version 0 denotes the headerless layouts.
+pub const REGION_FORMAT_VERSION: u8 = 1;
+
+/// Size of the v1 container header:
+/// magic (4) + version (1) + encoding (1) + width (2 LE) + height (2 LE) + crc32 (4 LE) + reserved (2).
+pub const REGION_HEADER_SIZE: usize = 16;
+
+/// Why a stored region blob could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionFormatError {
+    /// Neither a known headerless size nor a container.
+    UnknownLayout { len: usize },
+    UnsupportedVersion(u8),
+    UnknownEncoding(u8),
+    DimensionMismatch { width: u16, height: u16 },
+    /// Payload length does not match the header's encoding and dimensions.
+    LengthMismatch { expected: usize, actual: usize },
+    ChecksumMismatch { expected: u32, actual: u32 },
+}
+
+impl std::fmt::Display for RegionFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownLayout { len } => write!(f, "unrecognized region blob of {len} bytes"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported region format version {v}"),
+            Self::UnknownEncoding(e) => write!(f, "unknown region encoding tag {e}"),
+            Self::DimensionMismatch { width, height } => {
+                write!(f, "region is {width}x{height}, expected {REGION_SIZE}x{REGION_SIZE}")
+            }
+            Self::LengthMismatch { expected, actual } => {
+                write!(f, "region payload is {actual} bytes, expected {expected}")
+            }
+            Self::ChecksumMismatch { expected, actual } => {
+                write!(f, "region checksum {actual:08x} does not match header {expected:08x}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegionFormatError {}
+
+/// A region blob decoded from any supported on-disk version.
+#[derive(Debug, Clone)]
+pub struct DecodedRegion {
+    /// Container version the blob was stored in (0 = headerless).
+    pub version: u8,
+    pub encoding: RegionEncoding,
+    /// Raw pixel array in `encoding`, without any header.
+    pub pixels: Vec<u8>,
+}
+
+impl RegionEncoding {
+    /// Tag stored in the container header.
+    pub fn tag(self) -> u8 {
+        match self {
+            Self::Owner24 => 0,
+            Self::Owner32 => 1,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Self::Owner24),
+            1 => Some(Self::Owner32),
+            _ => None,
+        }
+    }
+}
+
+/// Wrap a raw pixel array in the current versioned container.
+pub fn encode_region_container(pixels: &[u8], encoding: RegionEncoding) -> Vec<u8> {
+    debug_assert_eq!(pixels.len(), encoding.blob_size());
+    let mut out = Vec::with_capacity(REGION_HEADER_SIZE + pixels.len());
+    out.extend_from_slice(&REGION_MAGIC);
+    out.push(REGION_FORMAT_VERSION);
+    out.push(encoding.tag());
+    out.extend_from_slice(&(REGION_SIZE as u16).to_le_bytes());
+    out.extend_from_slice(&(REGION_SIZE as u16).to_le_bytes());
+    out.extend_from_slice(&crc32fast::hash(pixels).to_le_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(pixels);
+    out
+}
+
+/// Decode a stored region blob of any supported version.
+///
+/// Headerless blobs (version 0) are recognized by their exact length, which can never
+/// equal a container's length since containers always carry a header.
+pub fn decode_region_blob(blob: &[u8]) -> Result<DecodedRegion, RegionFormatError> {
+    if let Some(encoding) = RegionEncoding::detect(blob) {
+        return Ok(DecodedRegion {
+            version: 0,
+            encoding,
+            pixels: blob.to_vec(),
+        });
+    }
+    if blob.len() < REGION_HEADER_SIZE || blob[0..4] != REGION_MAGIC {
+        return Err(RegionFormatError::UnknownLayout { len: blob.len() });
+    }
+    match blob[4] {
+        1 => decode_region_v1(blob),
+        v => Err(RegionFormatError::UnsupportedVersion(v)),
+    }
+}
+
+fn decode_region_v1(blob: &[u8]) -> Result<DecodedRegion, RegionFormatError> {
+    let encoding =
+        RegionEncoding::from_tag(blob[5]).ok_or(RegionFormatError::UnknownEncoding(blob[5]))?;
+    let width = u16::from_le_bytes([blob[6], blob[7]]);
+    let height = u16::from_le_bytes([blob[8], blob[9]]);
+    if width as i32 != REGION_SIZE || height as i32 != REGION_SIZE {
+        return Err(RegionFormatError::DimensionMismatch { width, height });
+    }
+    let expected = u32::from_le_bytes([blob[10], blob[11], blob[12], blob[13]]);
+
+    let pixels = &blob[REGION_HEADER_SIZE..];
+    if pixels.len() != encoding.blob_size() {
+        return Err(RegionFormatError::LengthMismatch {
+            expected: encoding.blob_size(),
+            actual: pixels.len(),
+        });
+    }
+    let actual = crc32fast::hash(pixels);
+    if actual != expected {
+        return Err(RegionFormatError::ChecksumMismatch { expected, actual });
+    }
+
+    Ok(DecodedRegion {
+        version: 1,
+        encoding,
+        pixels: pixels.to_vec(),
+    })
+}
+
 /// Compute which region a world-space pixel coordinate falls in.
 pub fn region_coords(x: i32, y: i32) -> (i32, i32) {
     (x.div_euclid(REGION_SIZE), y.div_euclid(REGION_SIZE))
@@ -40,8 +338,16 @@ pub fn pixel_offset(lx: usize, ly: usize) -> usize {
 
 impl Pixel {
     /// Encode a pixel into the 6-byte binary format (3 RGB + 3 owner_id LE).
+    ///
+    /// Panics if `owner_id` does not fit in 24 bits rather than silently wrapping;
+    /// use `RegionEncoding::encode` to handle overflow as an error.
     pub fn encode(&self, buf: &mut [u8]) {
         debug_assert!(buf.len() >= PIXEL_SIZE);
+        assert!(
+            self.owner_id <= OVERFLOW_OWNER_ID,
+            "owner id {} does not fit in a 24-bit pixel",
+            self.owner_id
+        );
         let ob = self.owner_id.to_le_bytes();
         buf[0] = self.r;
         buf[1] = self.g;
@@ -62,8 +368,47 @@ impl Pixel {
         }
     }
 
+    /// Encode a pixel into the 7-byte wide format (3 RGB + 4 owner_id LE).
+    pub fn encode_wide(&self, buf: &mut [u8]) {
+        debug_assert!(buf.len() >= PIXEL_SIZE_WIDE);
+        buf[0] = self.r;
+        buf[1] = self.g;
+        buf[2] = self.b;
+        buf[3..7].copy_from_slice(&self.owner_id.to_le_bytes());
+    }
+
+    /// Decode a pixel from the 7-byte wide format.
+    pub fn decode_wide(buf: &[u8]) -> Self {
+        debug_assert!(buf.len() >= PIXEL_SIZE_WIDE);
+        Self {
+            r: buf[0],
+            g: buf[1],
+            b: buf[2],
+            owner_id: u32::from_le_bytes([buf[3], buf[4], buf[5], buf[6]]),
+        }
+    }
+
     /// Whether this pixel has ever been drawn on (owner_id == 0 means undrawn).
     pub fn is_empty(&self) -> bool {
         self.owner_id == 0
     }
 }
+
+/// Average color of all drawn pixels in a region blob, or `None` if nothing is drawn.
+pub fn average_color(blob: &[u8], encoding: RegionEncoding) -> Option<(u8, u8, u8)> {
+    let (mut r, mut g, mut b, mut n) = (0u64, 0u64, 0u64, 0u64);
+    for chunk in blob.chunks_exact(encoding.pixel_size()) {
+        let pixel = encoding.decode(chunk);
+        if pixel.is_empty() {
+            continue;
+        }
+        r += pixel.r as u64;
+        g += pixel.g as u64;
+        b += pixel.b as u64;
+        n += 1;
+    }
+    if n == 0 {
+        return None;
+    }
+    Some(((r / n) as u8, (g / n) as u8, (b / n) as u8))
+}
@@ -1,41 +1,256 @@
-/// Valkey key for the draw event queue (indexer LPUSH, server RPOP).
-pub const DRAW_QUEUE: &str = "draw_queue";
+/// Width of one "pixels drawn" leaderboard bucket (one hour).
+pub const LEADERBOARD_BUCKET_MS: u64 = 3_600_000;
 
-/// Valkey key for the processing queue (RPOPLPUSH target).
-pub const PROCESSING_QUEUE: &str = "processing_queue";
+/// Hourly buckets of pixels drawn are kept this long (covers the 7-day window).
+pub const LEADERBOARD_BUCKET_TTL_SECS: u64 = 8 * 24 * 3600;
 
-/// Valkey key for the last processed block height.
-pub const LAST_PROCESSED_BLOCK: &str = "last_processed_block";
+/// Id of the board that owns the original, un-namespaced keys.
+pub const DEFAULT_BOARD: &str = "default";
 
-/// Valkey key for account_id -> u32 owner index mapping.
-pub const ACCOUNT_TO_ID: &str = "account_to_id";
+/// Whether `id` can name a board: 1-32 characters of `[a-z0-9_-]`.
+pub fn is_valid_board_id(id: &str) -> bool {
+    (1..=32).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-')
+}
 
-/// Valkey key for u32 owner index -> account_id reverse mapping.
-pub const ID_TO_ACCOUNT: &str = "id_to_account";
+/// Names every Valkey key one board uses.
+///
+/// Keys are namespaced by an optional keyspace prefix, so deployments for different
+/// chains (e.g. testnet and mainnet) can share one Valkey, and by board id, so one
+/// deployment can host several independent boards. The default board in the default
+/// keyspace reads and writes the original bare key names.
+///
+/// Board state (regions, counters, leaderboards) is further namespaced by season;
+/// season 0 uses the board's plain keys. The queues and owner id mappings belong to
+/// the board and are shared by all its seasons.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyspace {
+    name: String,
+    board: String,
+    season: u32,
+    /// Everything put in front of a bare board-level key name, e.g. "testnet:board:team:".
+    prefix: String,
+}
 
-/// Valkey sorted set for recent draw events (for WebSocket catch-up).
-pub const DRAW_EVENTS_ZSET: &str = "draw_events";
+impl Default for Keyspace {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
 
-/// Hash: owner_id (u32) → pixel count (i64). Tracks how many pixels each account owns.
-pub const ACCOUNT_PIXEL_COUNT: &str = "account_pixel_count";
+impl Keyspace {
+    /// Default board of the keyspace named `name`; keys become "{name}:draw_queue" and
+    /// so on. An empty name is the unprefixed default keyspace.
+    pub fn new(name: &str) -> Self {
+        assert!(
+            !name.contains([':', '*', '?', '[']),
+            "invalid keyspace name: {name}"
+        );
+        let prefix = if name.is_empty() {
+            String::new()
+        } else {
+            format!("{name}:")
+        };
+        Self {
+            name: name.to_string(),
+            board: DEFAULT_BOARD.to_string(),
+            season: 0,
+            prefix,
+        }
+    }
 
-/// Hash: "rx:ry" → pixel count (i64). Tracks drawn pixels per region.
-pub const REGION_PIXEL_COUNT: &str = "region_pixel_count";
+    /// The same keyspace, namespaced to season 0 of `board`. Keys of a non-default
+    /// board become "board:{board}:draw_queue" and so on.
+    pub fn for_board(&self, board: &str) -> Self {
+        assert!(is_valid_board_id(board), "invalid board id: {board}");
+        let mut keys = Self::new(&self.name);
+        if board != DEFAULT_BOARD {
+            keys.prefix.push_str(&format!("board:{board}:"));
+            keys.board = board.to_string();
+        }
+        keys
+    }
 
-/// Set of "rx:ry" strings for regions that are open for drawing.
-pub const OPEN_REGIONS: &str = "open_regions";
+    /// Keyspace named by the `VALKEY_PREFIX` env var (default keyspace if unset).
+    pub fn from_env() -> Self {
+        Self::new(&std::env::var("VALKEY_PREFIX").unwrap_or_default())
+    }
 
-/// Build the Valkey key for a region blob.
-pub fn region_key(rx: i32, ry: i32) -> String {
-    format!("region:{rx}:{ry}")
-}
+    /// The keyspace name, empty for the default keyspace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-/// Build the Valkey key for region metadata.
-pub fn region_meta_key(rx: i32, ry: i32) -> String {
-    format!("region_meta:{rx}:{ry}")
-}
+    /// The board these keys belong to.
+    pub fn board(&self) -> &str {
+        &self.board
+    }
+
+    /// The same board, with state keys of `season`. State keys of seasons after the
+    /// first become "season:{season}:region:0:0" and so on.
+    pub fn for_season(&self, season: u32) -> Self {
+        Self {
+            season,
+            ..self.clone()
+        }
+    }
+
+    /// The season whose state these keys address.
+    pub fn season(&self) -> u32 {
+        self.season
+    }
+
+    /// A board-level key, shared by all seasons.
+    fn key(&self, name: &str) -> String {
+        format!("{}{name}", self.prefix)
+    }
+
+    /// A key holding board state of the current season.
+    fn state_key(&self, name: &str) -> String {
+        match self.season {
+            0 => self.key(name),
+            season => format!("{}season:{season}:{name}", self.prefix),
+        }
+    }
+
+    /// Key for the board's current season number (absent means season 0).
+    pub fn current_season(&self) -> String {
+        self.key("current_season")
+    }
+
+    /// Sorted set of ended season numbers, scored by their end timestamp in ms.
+    pub fn ended_seasons(&self) -> String {
+        self.key("ended_seasons")
+    }
+
+    /// Hash describing how the season ended (end block, timestamp, totals).
+    pub fn season_meta(&self) -> String {
+        self.state_key("season_meta")
+    }
+
+    /// Key for the draw event queue (indexer LPUSH, server RPOP).
+    pub fn draw_queue(&self) -> String {
+        self.key("draw_queue")
+    }
+
+    /// Key for the processing queue (RPOPLPUSH target).
+    pub fn processing_queue(&self) -> String {
+        self.key("processing_queue")
+    }
+
+    /// Key for the last processed block height.
+    pub fn last_processed_block(&self) -> String {
+        self.key("last_processed_block")
+    }
+
+    /// Key for the hash of the last processed block, checked against the next block's
+    /// `prev_hash`.
+    pub fn last_processed_block_hash(&self) -> String {
+        self.key("last_processed_block_hash")
+    }
+
+    /// `"{block_height}:{event_index}"` of the last draw event applied to the board;
+    /// see `DrawEvent::position`.
+    pub fn last_applied_event(&self) -> String {
+        self.key("last_applied_event")
+    }
+
+    /// Hash the indexer refreshes after every block (see `IndexerHeartbeat`).
+    pub fn indexer_heartbeat(&self) -> String {
+        self.key("indexer_heartbeat")
+    }
+
+    /// Key for account_id -> u32 owner index mapping.
+    pub fn account_to_id(&self) -> String {
+        self.key("account_to_id")
+    }
+
+    /// Key for u32 owner index -> account_id reverse mapping.
+    pub fn id_to_account(&self) -> String {
+        self.key("id_to_account")
+    }
+
+    /// Key for the last allocated owner id (INCR counter).
+    pub fn owner_id_counter(&self) -> String {
+        self.key("owner_id_counter")
+    }
+
+    /// Sorted set for recent draw events (for WebSocket catch-up).
+    pub fn draw_events(&self) -> String {
+        self.state_key("draw_events")
+    }
+
+    /// Hash: owner_id (u32) → pixel count (i64). Tracks how many pixels each account owns.
+    pub fn account_pixel_count(&self) -> String {
+        self.state_key("account_pixel_count")
+    }
+
+    /// Sorted set: owner_id (u32) → pixel count. Mirrors `account_pixel_count` for ranked reads.
+    pub fn leaderboard(&self) -> String {
+        self.state_key("leaderboard")
+    }
+
+    /// Hash: "rx:ry" → pixel count (i64). Tracks drawn pixels per region.
+    pub fn region_pixel_count(&self) -> String {
+        self.state_key("region_pixel_count")
+    }
+
+    /// Set of "rx:ry" strings for regions that are open for drawing.
+    pub fn open_regions(&self) -> String {
+        self.state_key("open_regions")
+    }
+
+    /// Key for a region blob.
+    pub fn region(&self, rx: i32, ry: i32) -> String {
+        self.state_key(&format!("region:{rx}:{ry}"))
+    }
+
+    /// SCAN pattern matching every region blob key.
+    pub fn region_pattern(&self) -> String {
+        self.state_key("region:*")
+    }
+
+    /// Key for region metadata.
+    pub fn region_meta(&self, rx: i32, ry: i32) -> String {
+        self.state_key(&format!("region_meta:{rx}:{ry}"))
+    }
+
+    /// Key for the per-region pixel timestamp sorted set.
+    pub fn pixel_ts(&self, rx: i32, ry: i32) -> String {
+        self.state_key(&format!("pixel_ts:{rx}:{ry}"))
+    }
+
+    /// Key for the per-region sorted set of owner_id → owned pixel count.
+    pub fn region_owners(&self, rx: i32, ry: i32) -> String {
+        self.state_key(&format!("region_owners:{rx}:{ry}"))
+    }
+
+    /// Key for account metadata (first/last draw timestamps).
+    pub fn account_meta(&self, owner_id: u32) -> String {
+        self.state_key(&format!("account_meta:{owner_id}"))
+    }
+
+    /// Key for the set of "rx:ry" regions an account has drawn in.
+    pub fn account_regions(&self, owner_id: u32) -> String {
+        self.state_key(&format!("account_regions:{owner_id}"))
+    }
+
+    /// Key for the per-account sorted set of owned pixel timestamps.
+    /// Members are world-space "x,y" strings, scores are block timestamps in ms.
+    pub fn account_pixel_ts(&self, owner_id: u32) -> String {
+        self.state_key(&format!("account_pixel_ts:{owner_id}"))
+    }
+
+    /// Key for the hourly "pixels drawn" leaderboard bucket.
+    /// `hour` is the block timestamp in ms divided by `LEADERBOARD_BUCKET_MS`.
+    pub fn leaderboard_drawn(&self, hour: u64) -> String {
+        self.state_key(&format!("leaderboard_drawn:{hour}"))
+    }
 
-/// Build the Valkey key for per-region pixel timestamp sorted set.
-pub fn pixel_ts_key(rx: i32, ry: i32) -> String {
-    format!("pixel_ts:{rx}:{ry}")
+    /// Key for a cached time-windowed leaderboard (e.g. "24h", "7d").
+    pub fn leaderboard_window(&self, window: &str) -> String {
+        self.state_key(&format!("leaderboard_window:{window}"))
+    }
 }
@@ -0,0 +1,106 @@
+use common::boards::{validate_boards, BoardConfig};
+
+fn parse(json: &str) -> Vec<BoardConfig> {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn rules_default_to_the_main_board() {
+    let boards = parse(r#"[{"id": "default", "contract_ids": ["berryfast.near"]}]"#);
+    assert_eq!(boards[0].rules, common::BoardRules::default());
+    assert!(boards[0].rules.region_in_bounds(1_000, -1_000));
+    validate_boards(&boards).unwrap();
+}
+
+#[test]
+fn partial_rules_override_only_what_they_set() {
+    let boards = parse(
+        r#"[{"id": "halloween", "contract_ids": ["spooky.near"],
+             "rules": {"ownership_duration_ms": 600000, "max_region_radius": 2}}]"#,
+    );
+    let rules = boards[0].rules;
+    assert_eq!(rules.ownership_duration_ms, 600_000);
+    assert_eq!(rules.region_open_threshold, common::REGION_OPEN_THRESHOLD);
+    assert!(rules.region_in_bounds(-2, 2));
+    assert!(!rules.region_in_bounds(3, 0));
+}
+
+#[test]
+fn a_contract_cannot_feed_two_boards() {
+    let boards = parse(
+        r#"[{"id": "default", "contract_ids": ["berryfast.near"]},
+            {"id": "event", "contract_ids": ["event.near", "berryfast.near"]}]"#,
+    );
+    let err = validate_boards(&boards).unwrap_err();
+    assert!(err.contains("berryfast.near"), "{err}");
+
+    let duplicate = parse(r#"[{"id": "a"}, {"id": "a"}]"#);
+    assert!(validate_boards(&duplicate).is_err());
+}
+
+#[test]
+fn a_bounded_board_opens_exactly_the_regions_covering_it() {
+    let boards =
+        parse(r#"[{"id": "poster", "rules": {"bounds": {"width": 100, "height": 64}}}]"#);
+    let rules = boards[0].rules;
+    let size = common::REGION_SIZE;
+
+    let expected_x = (100 + size - 1) / size;
+    let expected_y = (64 + size - 1) / size;
+    assert_eq!(rules.initial_regions().len(), (expected_x * expected_y) as usize);
+    assert!(rules.region_in_bounds(expected_x - 1, expected_y - 1));
+    assert!(!rules.region_in_bounds(expected_x, 0));
+    assert!(!rules.region_in_bounds(-1, 0));
+
+    assert!(rules.pixel_in_bounds(99, 63));
+    assert!(!rules.pixel_in_bounds(100, 0));
+    assert!(!rules.pixel_in_bounds(0, -1));
+    assert_eq!(common::BoardRules::default().initial_regions(), vec![(0, 0)]);
+}
+
+#[test]
+fn seasons_end_at_the_first_bound_reached() {
+    let boards = parse(
+        r#"[{"id": "weekly", "rules": {"seasons": {
+            "end_block_height": 1000, "length_blocks": 500, "end_timestamp_ms": 90000}}}]"#,
+    );
+    let schedule = boards[0].rules.seasons.unwrap();
+
+    assert!(!schedule.has_ended(0, 999, 0));
+    assert!(schedule.has_ended(0, 1000, 0));
+    assert!(schedule.has_ended(0, 10, 90_000));
+    // Later seasons repeat by block length; the timestamp bound has no length
+    assert!(!schedule.has_ended(1, 1499, 1_000_000));
+    assert!(schedule.has_ended(1, 1500, 0));
+    assert!(schedule.has_ended(2, 2000, 0));
+}
+
+#[test]
+fn zero_length_seasons_are_rejected() {
+    let boards =
+        parse(r#"[{"id": "a", "rules": {"seasons": {"end_timestamp_ms": 1, "length_ms": 0}}}]"#);
+    assert!(validate_boards(&boards).is_err());
+}
+
+#[test]
+fn boards_are_free_unless_priced_and_prices_need_a_start_block() {
+    let free = parse(r#"[{"id": "default"}]"#);
+    assert!(!free[0].rules.pricing.applies_to(u64::MAX));
+
+    let priced = parse(
+        r#"[{"id": "default", "rules": {"pricing": {
+             "pixel_price": "100000000000000000000",
+             "overwrite_price": "200000000000000000000",
+             "start_block_height": 140000000}}}]"#,
+    );
+    let pricing = priced[0].rules.pricing;
+    assert_eq!(pricing.pixel_price, common::pricing::DEFAULT_PIXEL_PRICE);
+    assert_eq!(pricing.overwrite_price, common::pricing::DEFAULT_OVERWRITE_PRICE);
+    assert!(!pricing.applies_to(139_999_999));
+    assert!(pricing.applies_to(140_000_000));
+
+    // Without a start block, draws indexed before the upgrade would all be underpaid
+    let unstarted = r#"[{"id": "default", "rules": {"pricing": {"pixel_price": "1"}}}]"#;
+    let err = serde_json::from_str::<Vec<BoardConfig>>(unstarted).unwrap_err();
+    assert!(err.to_string().contains("start block"), "{err}");
+}
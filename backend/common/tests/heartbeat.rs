@@ -0,0 +1,21 @@
+use common::IndexerHeartbeat;
+use std::collections::HashMap;
+
+#[test]
+fn heartbeat_round_trips_through_hash_fields() {
+    let heartbeat = IndexerHeartbeat {
+        block_height: 120,
+        block_timestamp_ms: 10_000,
+        updated_ms: 12_500,
+        buffered_blocks: 3,
+    };
+    let fields: HashMap<String, u64> =
+        heartbeat.fields().iter().map(|(k, v)| (k.to_string(), *v)).collect();
+    assert_eq!(IndexerHeartbeat::from_fields(&fields), Some(heartbeat));
+    assert_eq!(IndexerHeartbeat::from_fields(&HashMap::new()), None);
+
+    assert_eq!(heartbeat.lag_ms(), 2_500);
+    let message = heartbeat.status_message(20_000);
+    assert_eq!(message["type"], "indexer_status");
+    assert_eq!(message["behind_ms"], 10_000);
+}
@@ -0,0 +1,54 @@
+use common::valkey::{is_valid_board_id, Keyspace, DEFAULT_BOARD};
+
+#[test]
+fn default_board_keeps_the_original_key_names() {
+    let keys = Keyspace::default();
+    assert_eq!(keys.board(), DEFAULT_BOARD);
+    assert_eq!(keys.draw_queue(), "draw_queue");
+    assert_eq!(keys.region(-1, 2), "region:-1:2");
+    assert_eq!(keys.region_pattern(), "region:*");
+    assert_eq!(Keyspace::default().for_board(DEFAULT_BOARD), keys);
+}
+
+#[test]
+fn keyspace_and_board_namespace_every_key() {
+    let testnet = Keyspace::new("testnet");
+    assert_eq!(testnet.open_regions(), "testnet:open_regions");
+
+    let team = testnet.for_board("team-a");
+    assert_eq!(team.name(), "testnet");
+    assert_eq!(team.board(), "team-a");
+    assert_eq!(team.open_regions(), "testnet:board:team-a:open_regions");
+    assert_eq!(team.account_meta(7), "testnet:board:team-a:account_meta:7");
+    // Re-scoping replaces the board rather than nesting it
+    assert_eq!(team.for_board("other").draw_queue(), "testnet:board:other:draw_queue");
+
+    // The default board's region scan must not pick up other boards' regions
+    assert!(!Keyspace::default().for_board("team-a").region(0, 0).starts_with("region:"));
+}
+
+#[test]
+fn board_ids_are_restricted() {
+    assert!(is_valid_board_id("season-2_final"));
+    assert!(!is_valid_board_id(""));
+    assert!(!is_valid_board_id("Team"));
+    assert!(!is_valid_board_id("a:b"));
+    assert!(!is_valid_board_id(&"x".repeat(33)));
+}
+
+#[test]
+fn seasons_namespace_state_but_share_queues_and_owner_ids() {
+    let keys = Keyspace::default().for_board("weekly");
+    assert_eq!(keys.for_season(0), keys);
+
+    let season = keys.for_season(2);
+    assert_eq!(season.season(), 2);
+    assert_eq!(season.region(0, 0), "board:weekly:season:2:region:0:0");
+    assert_eq!(season.draw_events(), "board:weekly:season:2:draw_events");
+    assert_eq!(season.draw_queue(), keys.draw_queue());
+    assert_eq!(season.account_to_id(), keys.account_to_id());
+    assert_eq!(season.ended_seasons(), keys.ended_seasons());
+    assert_eq!(season.last_applied_event(), keys.last_applied_event());
+    // A board change starts over at season 0
+    assert_eq!(season.for_board("weekly"), keys);
+}
@@ -0,0 +1,116 @@
+//! Runs against the fake's embedded Lua by default. To run against a real, disposable
+//! Valkey (the selected database is flushed), set
+//! `VALKEY_TEST_URL=redis://127.0.0.1:6379/15`.
+
+use common::owner_ids::{check_and_repair, resolve_or_allocate};
+use common::valkey::Keyspace;
+use fake_valkey::FakeValkey;
+use redis::AsyncCommands;
+use std::collections::{HashMap, HashSet};
+
+/// The fake is returned so it lives as long as the test.
+async fn connect() -> (redis::aio::MultiplexedConnection, Option<FakeValkey>) {
+    let Ok(url) = std::env::var("VALKEY_TEST_URL") else {
+        let valkey = FakeValkey::start().await;
+        return (valkey.connect().await, Some(valkey));
+    };
+    let client = redis::Client::open(url).unwrap();
+    let mut con = client.get_multiplexed_async_connection().await.unwrap();
+    let _: () = redis::cmd("FLUSHDB").query_async(&mut con).await.unwrap();
+    (con, None)
+}
+
+#[tokio::test]
+async fn concurrent_allocation_assigns_unique_ids() {
+    let (con, _valkey) = connect().await;
+    let keys = Keyspace::default();
+
+    // 16 tasks each resolve the same 100 accounts in a different order
+    let tasks: Vec<_> = (0..16)
+        .map(|t| {
+            let mut con = con.clone();
+            let keys = keys.clone();
+            tokio::spawn(async move {
+                let mut ids = HashMap::new();
+                for i in 0..100 {
+                    let account = format!("user{}.near", (i * 7 + t * 13) % 100);
+                    let id = resolve_or_allocate(&mut con, &keys, &account, u32::MAX)
+                        .await
+                        .unwrap();
+                    ids.insert(account, id);
+                }
+                ids
+            })
+        })
+        .collect();
+
+    let results: Vec<HashMap<String, u32>> = futures::future::try_join_all(tasks).await.unwrap();
+
+    // Every task must agree on every account's id
+    let first = &results[0];
+    for r in &results[1..] {
+        assert_eq!(r, first);
+    }
+
+    let ids: HashSet<u32> = first.values().copied().collect();
+    assert_eq!(ids.len(), 100, "ids must be unique");
+    assert_eq!(ids.iter().min(), Some(&1));
+    assert_eq!(ids.iter().max(), Some(&100));
+
+    let report = check_and_repair(&mut con.clone(), &keys, false, u32::MAX).await.unwrap();
+    assert!(report.is_clean(), "{report:?}");
+}
+
+#[tokio::test]
+async fn repair_reassigns_colliding_accounts() {
+    let (mut con, _valkey) = connect().await;
+    let keys = Keyspace::default();
+
+    // Simulate the old HLEN + 1 race: two accounts both got id 1
+    let _: () = redis::pipe()
+        .hset(keys.account_to_id(), "alice.near", 1)
+        .hset(keys.account_to_id(), "bob.near", 1)
+        .hset(keys.id_to_account(), 1, "bob.near")
+        .query_async(&mut con)
+        .await
+        .unwrap();
+
+    let report = check_and_repair(&mut con, &keys, true, u32::MAX).await.unwrap();
+    assert_eq!(report.reassigned, vec![("alice.near".to_string(), 1, 2)]);
+
+    let bob: u32 = con.hget(keys.account_to_id(), "bob.near").await.unwrap();
+    let alice: u32 = con.hget(keys.account_to_id(), "alice.near").await.unwrap();
+    let reverse: String = con.hget(keys.id_to_account(), 2).await.unwrap();
+    assert_eq!((bob, alice, reverse.as_str()), (1, 2, "alice.near"));
+
+    assert!(check_and_repair(&mut con, &keys, false, u32::MAX).await.unwrap().is_clean());
+}
+
+#[tokio::test]
+async fn allocation_stops_at_max_owner_id() {
+    let (mut con, _valkey) = connect().await;
+    let keys = Keyspace::default();
+
+    assert_eq!(resolve_or_allocate(&mut con, &keys, "a.near", 2).await.unwrap(), 1);
+    assert_eq!(resolve_or_allocate(&mut con, &keys, "b.near", 2).await.unwrap(), 2);
+    assert!(resolve_or_allocate(&mut con, &keys, "c.near", 2).await.is_err());
+
+    // Existing accounts still resolve, and a wider limit continues from 3
+    assert_eq!(resolve_or_allocate(&mut con, &keys, "a.near", 2).await.unwrap(), 1);
+    assert_eq!(resolve_or_allocate(&mut con, &keys, "c.near", 3).await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn keyspaces_allocate_independently() {
+    let (mut con, _valkey) = connect().await;
+    let mainnet = Keyspace::default();
+    let testnet = Keyspace::new("testnet");
+
+    assert_eq!(resolve_or_allocate(&mut con, &mainnet, "a.near", u32::MAX).await.unwrap(), 1);
+    assert_eq!(resolve_or_allocate(&mut con, &testnet, "b.testnet", u32::MAX).await.unwrap(), 1);
+    assert_eq!(resolve_or_allocate(&mut con, &testnet, "a.near", u32::MAX).await.unwrap(), 2);
+
+    let testnet_ids: HashMap<String, u32> = con.hgetall("testnet:account_to_id").await.unwrap();
+    assert_eq!(testnet_ids.len(), 2);
+    assert!(check_and_repair(&mut con, &mainnet, false, u32::MAX).await.unwrap().is_clean());
+}
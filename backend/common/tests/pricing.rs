@@ -0,0 +1,49 @@
+use common::pricing::{DEFAULT_OVERWRITE_PRICE, DEFAULT_PIXEL_PRICE};
+use common::Pricing;
+
+const PRICING: Pricing = Pricing {
+    pixel_price: 10,
+    overwrite_price: 25,
+    start_block_height: 100,
+};
+
+#[test]
+fn charges_come_out_of_the_deposit_until_it_runs_short() {
+    let mut remaining = 50;
+    assert!(PRICING.try_charge(&mut remaining, false));
+    assert_eq!(remaining, 40);
+    assert!(PRICING.try_charge(&mut remaining, true));
+    assert_eq!(remaining, 15);
+
+    // Too little for an overwrite, but still enough for an empty pixel
+    assert!(!PRICING.try_charge(&mut remaining, true));
+    assert_eq!(remaining, 15);
+    assert!(PRICING.try_charge(&mut remaining, false));
+    assert_eq!(remaining, 5);
+    assert!(!PRICING.try_charge(&mut remaining, false));
+    assert_eq!(remaining, 5);
+}
+
+#[test]
+fn pricing_applies_from_its_start_block_and_only_when_not_free() {
+    assert!(!PRICING.applies_to(99));
+    assert!(PRICING.applies_to(100));
+    assert!(!Pricing::default().applies_to(100));
+
+    let mut remaining = 0;
+    assert!(Pricing::default().try_charge(&mut remaining, true));
+}
+
+#[test]
+fn default_prices_match_the_contract_defaults() {
+    // 0.0001 and 0.0002 NEAR, as in contract/src/lib.rs and frontend/src/lib/constants.ts
+    assert_eq!(DEFAULT_PIXEL_PRICE, 10u128.pow(20));
+    assert_eq!(DEFAULT_OVERWRITE_PRICE, 2 * 10u128.pow(20));
+}
+
+#[test]
+fn prices_need_a_start_block_unless_free() {
+    assert!(Pricing::new(10, 25, None).is_err());
+    assert_eq!(Pricing::new(0, 0, None), Ok(Pricing::default()));
+    assert_eq!(Pricing::new(10, 25, Some(100)), Ok(PRICING));
+}
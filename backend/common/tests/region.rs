@@ -0,0 +1,158 @@
+use common::region::*;
+
+/// A region with a few distinct pixels set, in `encoding`.
+fn sample_pixels(encoding: RegionEncoding) -> Vec<u8> {
+    let mut pixels = vec![0u8; encoding.blob_size()];
+    for (i, (lx, ly)) in [(0, 0), (5, 9), (127, 127)].into_iter().enumerate() {
+        let pixel = Pixel { r: 255, g: i as u8, b: 7, owner_id: i as u32 + 1 };
+        let offset = encoding.pixel_offset(lx, ly);
+        encoding.encode(&pixel, &mut pixels[offset..]).unwrap();
+    }
+    pixels
+}
+
+#[test]
+fn containers_round_trip_in_both_encodings() {
+    for encoding in [RegionEncoding::Owner24, RegionEncoding::Owner32] {
+        let pixels = sample_pixels(encoding);
+        let blob = encode_region_container(&pixels, encoding);
+        assert_eq!(blob.len(), REGION_HEADER_SIZE + encoding.blob_size());
+        assert_eq!(blob[0..4], REGION_MAGIC);
+
+        let region = decode_region_blob(&blob).unwrap();
+        assert_eq!(region.version, REGION_FORMAT_VERSION);
+        assert_eq!(region.encoding, encoding);
+        assert_eq!(region.pixels, pixels);
+    }
+}
+
+#[test]
+fn headerless_blobs_decode_as_version_zero() {
+    for encoding in [RegionEncoding::Owner24, RegionEncoding::Owner32] {
+        let pixels = sample_pixels(encoding);
+        let region = decode_region_blob(&pixels).unwrap();
+        assert_eq!((region.version, region.encoding), (0, encoding));
+        assert_eq!(region.pixels, pixels);
+    }
+}
+
+#[test]
+fn a_corrupted_payload_fails_its_checksum() {
+    let pixels = sample_pixels(RegionEncoding::Owner24);
+    let mut blob = encode_region_container(&pixels, RegionEncoding::Owner24);
+    blob[REGION_HEADER_SIZE + 1] ^= 0xFF;
+
+    let expected = crc32fast::hash(&pixels);
+    let actual = crc32fast::hash(&blob[REGION_HEADER_SIZE..]);
+    assert_eq!(
+        decode_region_blob(&blob).unwrap_err(),
+        RegionFormatError::ChecksumMismatch { expected, actual }
+    );
+}
+
+#[test]
+fn malformed_headers_are_rejected() {
+    let pixels = sample_pixels(RegionEncoding::Owner24);
+    let blob = encode_region_container(&pixels, RegionEncoding::Owner24);
+    let with = |at: usize, bytes: &[u8]| {
+        let mut blob = blob.clone();
+        blob[at..at + bytes.len()].copy_from_slice(bytes);
+        decode_region_blob(&blob).unwrap_err()
+    };
+
+    assert_eq!(with(4, &[2]), RegionFormatError::UnsupportedVersion(2));
+    assert_eq!(with(4, &[0]), RegionFormatError::UnsupportedVersion(0));
+    assert_eq!(with(5, &[9]), RegionFormatError::UnknownEncoding(9));
+    assert_eq!(
+        with(6, &64u16.to_le_bytes()),
+        RegionFormatError::DimensionMismatch { width: 64, height: 128 }
+    );
+    assert_eq!(
+        with(8, &256u16.to_le_bytes()),
+        RegionFormatError::DimensionMismatch { width: 128, height: 256 }
+    );
+    // An Owner32 tag on an Owner24 payload
+    assert_eq!(
+        with(5, &[RegionEncoding::Owner32.tag()]),
+        RegionFormatError::LengthMismatch {
+            expected: REGION_BLOB_SIZE_WIDE,
+            actual: REGION_BLOB_SIZE,
+        }
+    );
+}
+
+#[test]
+fn unrecognized_blobs_are_an_unknown_layout() {
+    let pixels = sample_pixels(RegionEncoding::Owner24);
+    let blob = encode_region_container(&pixels, RegionEncoding::Owner24);
+
+    assert_eq!(
+        decode_region_blob(&blob[..10]).unwrap_err(),
+        RegionFormatError::UnknownLayout { len: 10 }
+    );
+    let mut unmagic = blob.clone();
+    unmagic[0] = b'X';
+    assert_eq!(
+        decode_region_blob(&unmagic).unwrap_err(),
+        RegionFormatError::UnknownLayout { len: blob.len() }
+    );
+    assert_eq!(
+        decode_region_blob(&pixels[1..]).unwrap_err(),
+        RegionFormatError::UnknownLayout { len: REGION_BLOB_SIZE - 1 }
+    );
+}
+
+#[test]
+fn widening_keeps_every_pixel() {
+    let narrow = sample_pixels(RegionEncoding::Owner24);
+    let wide = convert_region_blob(&narrow, RegionEncoding::Owner24, RegionEncoding::Owner32)
+        .unwrap();
+    assert_eq!(wide, sample_pixels(RegionEncoding::Owner32));
+
+    let back = convert_region_blob(&wide, RegionEncoding::Owner32, RegionEncoding::Owner24)
+        .unwrap();
+    assert_eq!(back, narrow);
+}
+
+#[test]
+fn narrowing_fails_on_an_owner_past_the_24_bit_limit() {
+    let wide = RegionEncoding::Owner32;
+    let mut pixels = vec![0u8; wide.blob_size()];
+    let at_limit = Pixel { r: 1, g: 2, b: 3, owner_id: MAX_OWNER_ID_24 };
+    wide.encode(&at_limit, &mut pixels[wide.pixel_offset(1, 0)..]).unwrap();
+
+    // The largest 24-bit id still fits
+    let narrow = convert_region_blob(&pixels, wide, RegionEncoding::Owner24).unwrap();
+    let offset = RegionEncoding::Owner24.pixel_offset(1, 0);
+    let pixel = RegionEncoding::Owner24.decode(&narrow[offset..]);
+    assert_eq!(pixel.owner_id, MAX_OWNER_ID_24);
+
+    let past = Pixel { owner_id: MAX_OWNER_ID_24 + 1, ..at_limit };
+    wide.encode(&past, &mut pixels[wide.pixel_offset(2, 0)..]).unwrap();
+    assert_eq!(
+        convert_region_blob(&pixels, wide, RegionEncoding::Owner24).unwrap_err(),
+        OwnerOverflow { owner_id: MAX_OWNER_ID_24 + 1, max_owner_id: MAX_OWNER_ID_24 }
+    );
+    assert!(RegionEncoding::Owner24.encode(&past, &mut [0u8; PIXEL_SIZE]).is_err());
+}
+
+#[test]
+fn legacy_blobs_show_overflowing_owners_as_the_overflow_id() {
+    let wide = RegionEncoding::Owner32;
+    let mut pixels = vec![0u8; wide.blob_size()];
+    let owners = [(0, MAX_OWNER_ID_24), (1, MAX_OWNER_ID_24 + 1), (2, u32::MAX), (3, 42)];
+    for (lx, owner_id) in owners {
+        let pixel = Pixel { r: 10, g: lx as u8, b: 30, owner_id };
+        wide.encode(&pixel, &mut pixels[wide.pixel_offset(lx, 0)..]).unwrap();
+    }
+
+    let legacy = to_legacy_blob(&pixels, wide);
+    assert_eq!(legacy.len(), REGION_BLOB_SIZE);
+    let narrow = RegionEncoding::Owner24;
+    let expected = [MAX_OWNER_ID_24, OVERFLOW_OWNER_ID, OVERFLOW_OWNER_ID, 42];
+    for (lx, owner_id) in expected.into_iter().enumerate() {
+        let pixel = narrow.decode(&legacy[narrow.pixel_offset(lx, 0)..]);
+        assert_eq!((pixel.r, pixel.g, pixel.b), (10, lx as u8, 30));
+        assert_eq!(pixel.owner_id, owner_id);
+    }
+}
@@ -0,0 +1,795 @@
+//! An in-process stand-in for Valkey that speaks RESP2 over TCP and fails on cue,
+//! for tests of the server and indexer.
+//!
+//! It implements just the commands they send, keeps everything in one process-wide
+//! map and runs scripts in an embedded Lua interpreter, as Valkey does. Expiry is
+//! ignored.
+//! Transactions are all-or-nothing: a faulted `EXEC`, or a connection dropped
+//! before it, writes nothing.
+
+use mlua::{Lua, Value as LuaValue, Variadic};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+pub struct FakeValkey {
+    addr: std::net::SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeValkey {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+        let accept_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, accept_state.clone()));
+            }
+        });
+        Self { addr, state }
+    }
+
+    pub fn url(&self) -> String {
+        format!("redis://{}", self.addr)
+    }
+
+    pub async fn connect(&self) -> redis::aio::MultiplexedConnection {
+        let client = redis::Client::open(self.url()).unwrap();
+        client.get_multiplexed_async_connection().await.unwrap()
+    }
+
+    /// Fail the next `times` calls of `command` (e.g. "EXEC", "SISMEMBER"). A command
+    /// failing inside MULTI aborts the whole transaction.
+    pub fn fail_next(&self, command: &str, times: usize) {
+        let mut state = self.state.lock().unwrap();
+        *state.faults.entry(command.to_ascii_uppercase()).or_insert(0) += times;
+    }
+
+    /// Drop the connection that next sends `command`, without running it or replying,
+    /// as if the client had crashed just before it. An open MULTI on that connection
+    /// is discarded.
+    pub fn disconnect_on(&self, command: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.disconnects.insert(command.to_ascii_uppercase());
+    }
+
+    /// While down, every command fails.
+    pub fn set_down(&self, down: bool) {
+        self.state.lock().unwrap().down = down;
+    }
+
+    /// How many times `command` was received, including failed calls.
+    pub fn calls(&self, command: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state.calls.get(&command.to_ascii_uppercase()).copied().unwrap_or(0)
+    }
+}
+
+enum Value {
+    Str(Vec<u8>),
+    Hash(HashMap<Vec<u8>, Vec<u8>>),
+    Set(HashSet<Vec<u8>>),
+    ZSet(HashMap<Vec<u8>, f64>),
+    List(VecDeque<Vec<u8>>),
+}
+
+impl Value {
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::Str(_) => false,
+            Value::Hash(h) => h.is_empty(),
+            Value::Set(s) => s.is_empty(),
+            Value::ZSet(z) => z.is_empty(),
+            Value::List(l) => l.is_empty(),
+        }
+    }
+}
+
+enum Reply {
+    Status(String),
+    Error(String),
+    Int(i64),
+    Bulk(Vec<u8>),
+    Nil,
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Reply::Status(s) => out.extend_from_slice(format!("+{s}\r\n").as_bytes()),
+            Reply::Error(e) => out.extend_from_slice(format!("-{e}\r\n").as_bytes()),
+            Reply::Int(i) => out.extend_from_slice(format!(":{i}\r\n").as_bytes()),
+            Reply::Bulk(b) => {
+                out.extend_from_slice(format!("${}\r\n", b.len()).as_bytes());
+                out.extend_from_slice(b);
+                out.extend_from_slice(b"\r\n");
+            }
+            Reply::Nil => out.extend_from_slice(b"$-1\r\n"),
+            Reply::Array(items) => {
+                out.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+                for item in items {
+                    item.write(out);
+                }
+            }
+        }
+    }
+}
+
+type Command = Vec<Vec<u8>>;
+type CmdResult = Result<Reply, String>;
+
+const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// A connection's open MULTI block.
+struct Transaction {
+    queued: Vec<Command>,
+    aborted: bool,
+}
+
+#[derive(Default)]
+struct State {
+    data: HashMap<Vec<u8>, Value>,
+    /// Loaded scripts by SHA1.
+    scripts: HashMap<String, String>,
+    faults: HashMap<String, usize>,
+    disconnects: HashSet<String>,
+    calls: HashMap<String, usize>,
+    down: bool,
+}
+
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+    let mut transaction: Option<Transaction> = None;
+    while let Some(command) = read_command(&mut read).await {
+        let Some(reply) = state.lock().unwrap().handle(&mut transaction, command) else {
+            break;
+        };
+        let mut out = Vec::new();
+        reply.write(&mut out);
+        if write.write_all(&out).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn read_command<R: AsyncBufReadExt + Unpin>(read: &mut R) -> Option<Command> {
+    let mut line = String::new();
+    if read.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        line.clear();
+        read.read_line(&mut line).await.ok()?;
+        let len: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; len + 2];
+        read.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        args.push(arg);
+    }
+    Some(args)
+}
+
+fn parse<T: std::str::FromStr>(arg: &[u8]) -> Result<T, String> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| "ERR value is not a valid number".to_string())
+}
+
+/// A ZRANGEBYSCORE-style bound: `-inf`, `+inf`, `n` or exclusive `(n`.
+fn parse_bound(arg: &[u8]) -> Result<(f64, bool), String> {
+    let (arg, exclusive) = match arg.strip_prefix(b"(") {
+        Some(rest) => (rest, true),
+        None => (arg, false),
+    };
+    let score = match arg {
+        b"-inf" => f64::NEG_INFINITY,
+        b"+inf" | b"inf" => f64::INFINITY,
+        _ => parse(arg)?,
+    };
+    Ok((score, exclusive))
+}
+
+fn in_range(score: f64, (min, min_ex): (f64, bool), (max, max_ex): (f64, bool)) -> bool {
+    (if min_ex { score > min } else { score >= min })
+        && (if max_ex { score < max } else { score <= max })
+}
+
+fn format_score(score: f64) -> Vec<u8> {
+    if score.fract() == 0.0 && score.abs() < 1e15 {
+        (score as i64).to_string().into_bytes()
+    } else {
+        score.to_string().into_bytes()
+    }
+}
+
+/// Members in ascending (score, member) order.
+fn sorted(zset: &HashMap<Vec<u8>, f64>) -> Vec<(Vec<u8>, f64)> {
+    let mut entries: Vec<_> = zset.iter().map(|(m, s)| (m.clone(), *s)).collect();
+    entries.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    entries
+}
+
+/// Resolve LRANGE/ZRANGE-style inclusive indexes, which may count from the end.
+fn index_range(len: usize, start: i64, stop: i64) -> std::ops::Range<usize> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+    if start > stop {
+        return 0..0;
+    }
+    start as usize..stop as usize + 1
+}
+
+fn scored(entries: Vec<(Vec<u8>, f64)>, with_scores: bool) -> Reply {
+    let mut items = Vec::new();
+    for (member, score) in entries {
+        items.push(Reply::Bulk(member));
+        if with_scores {
+            items.push(Reply::Bulk(format_score(score)));
+        }
+    }
+    Reply::Array(items)
+}
+
+/// Glob match supporting `*` and `?`, which is all the callers' patterns use.
+fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((b'*', rest)) => (0..=s.len()).any(|i| glob_match(rest, &s[i..])),
+        Some((b'?', rest)) => !s.is_empty() && glob_match(rest, &s[1..]),
+        Some((c, rest)) => s.first() == Some(c) && glob_match(rest, &s[1..]),
+    }
+}
+
+fn has_flag(args: &[Vec<u8>], flag: &str) -> bool {
+    args.iter().any(|a| a.eq_ignore_ascii_case(flag.as_bytes()))
+}
+
+impl State {
+    /// The reply to `command`, or `None` to drop the connection.
+    fn handle(
+        &mut self,
+        transaction: &mut Option<Transaction>,
+        command: Command,
+    ) -> Option<Reply> {
+        let Some(name) = command.first() else {
+            return Some(Reply::Error("ERR empty command".into()));
+        };
+        let name = String::from_utf8_lossy(name).to_ascii_uppercase();
+        *self.calls.entry(name.clone()).or_insert(0) += 1;
+        if self.disconnects.remove(&name) {
+            return None;
+        }
+        Some(self.reply(transaction, name, command))
+    }
+
+    fn reply(
+        &mut self,
+        transaction: &mut Option<Transaction>,
+        name: String,
+        command: Command,
+    ) -> Reply {
+
+        if self.down || self.take_fault(&name) {
+            if let Some(t) = transaction.as_mut() {
+                t.aborted = true;
+            }
+            if name == "EXEC" {
+                *transaction = None;
+                return Reply::Error("EXECABORT Transaction discarded (injected fault)".into());
+            }
+            return Reply::Error(format!("ERR injected fault in {name}"));
+        }
+
+        match name.as_str() {
+            "MULTI" => {
+                *transaction = Some(Transaction {
+                    queued: Vec::new(),
+                    aborted: false,
+                });
+                Reply::Status("OK".into())
+            }
+            "DISCARD" => {
+                *transaction = None;
+                Reply::Status("OK".into())
+            }
+            "EXEC" => match transaction.take() {
+                None => Reply::Error("ERR EXEC without MULTI".into()),
+                Some(t) if t.aborted => Reply::Error(
+                    "EXECABORT Transaction discarded because of previous errors.".into(),
+                ),
+                Some(t) => Reply::Array(t.queued.into_iter().map(|c| self.run(c)).collect()),
+            },
+            _ => match transaction.as_mut() {
+                Some(t) => {
+                    t.queued.push(command);
+                    Reply::Status("QUEUED".into())
+                }
+                None => self.run(command),
+            },
+        }
+    }
+
+    fn take_fault(&mut self, name: &str) -> bool {
+        match self.faults.get_mut(name) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn run(&mut self, command: Command) -> Reply {
+        let name = String::from_utf8_lossy(&command[0]).to_ascii_uppercase();
+        let reply = self.execute(&name, &command[1..]).unwrap_or_else(Reply::Error);
+        // Like Valkey, a container left empty no longer exists
+        self.data.retain(|_, v| !v.is_empty());
+        reply
+    }
+
+    fn string(&mut self, key: &[u8]) -> Result<Option<&mut Vec<u8>>, String> {
+        match self.data.get_mut(key) {
+            None => Ok(None),
+            Some(Value::Str(s)) => Ok(Some(s)),
+            Some(_) => Err(WRONGTYPE.into()),
+        }
+    }
+
+    fn hash(&mut self, key: &[u8]) -> Result<&mut HashMap<Vec<u8>, Vec<u8>>, String> {
+        match self.data.entry(key.to_vec()).or_insert_with(|| Value::Hash(HashMap::new())) {
+            Value::Hash(h) => Ok(h),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    fn set(&mut self, key: &[u8]) -> Result<&mut HashSet<Vec<u8>>, String> {
+        match self.data.entry(key.to_vec()).or_insert_with(|| Value::Set(HashSet::new())) {
+            Value::Set(s) => Ok(s),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    fn zset(&mut self, key: &[u8]) -> Result<&mut HashMap<Vec<u8>, f64>, String> {
+        match self.data.entry(key.to_vec()).or_insert_with(|| Value::ZSet(HashMap::new())) {
+            Value::ZSet(z) => Ok(z),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    fn list(&mut self, key: &[u8]) -> Result<&mut VecDeque<Vec<u8>>, String> {
+        match self.data.entry(key.to_vec()).or_insert_with(|| Value::List(VecDeque::new())) {
+            Value::List(l) => Ok(l),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    fn incr_by(&mut self, key: &[u8], by: i64) -> Result<i64, String> {
+        let value = match self.string(key)? {
+            Some(s) => parse::<i64>(s)? + by,
+            None => by,
+        };
+        self.data.insert(key.to_vec(), Value::Str(value.to_string().into_bytes()));
+        Ok(value)
+    }
+
+    fn execute(&mut self, name: &str, args: &[Vec<u8>]) -> CmdResult {
+        let arg = |i: usize| -> Result<&[u8], String> {
+            args.get(i)
+                .map(Vec::as_slice)
+                .ok_or_else(|| format!("ERR wrong number of arguments for '{name}'"))
+        };
+        Ok(match name {
+            "PING" => Reply::Status("PONG".into()),
+            "CLIENT" | "SELECT" => Reply::Status("OK".into()),
+
+            "GET" => match self.string(arg(0)?)? {
+                Some(s) => Reply::Bulk(s.clone()),
+                None => Reply::Nil,
+            },
+            "SET" => {
+                self.data.insert(arg(0)?.to_vec(), Value::Str(arg(1)?.to_vec()));
+                Reply::Status("OK".into())
+            }
+            "DEL" => {
+                Reply::Int(args.iter().filter(|k| self.data.remove(*k).is_some()).count() as i64)
+            }
+            "EXISTS" => {
+                Reply::Int(args.iter().filter(|k| self.data.contains_key(*k)).count() as i64)
+            }
+            "EXPIRE" | "PEXPIREAT" => Reply::Int(self.data.contains_key(arg(0)?) as i64),
+            "SCAN" => {
+                // Everything in one batch; the returned cursor is always 0
+                let pattern = match args.iter().position(|a| a.eq_ignore_ascii_case(b"MATCH")) {
+                    Some(i) => arg(i + 1)?,
+                    None => b"*",
+                };
+                let mut keys: Vec<Vec<u8>> =
+                    self.data.keys().filter(|k| glob_match(pattern, k)).cloned().collect();
+                keys.sort();
+                Reply::Array(vec![
+                    Reply::Bulk(b"0".to_vec()),
+                    Reply::Array(keys.into_iter().map(Reply::Bulk).collect()),
+                ])
+            }
+            "INCR" => Reply::Int(self.incr_by(arg(0)?, 1)?),
+            "DECR" => Reply::Int(self.incr_by(arg(0)?, -1)?),
+
+            "HGET" => match self.hash(arg(0)?)?.get(arg(1)?) {
+                Some(v) => Reply::Bulk(v.clone()),
+                None => Reply::Nil,
+            },
+            "HSET" => {
+                let hash = self.hash(arg(0)?)?;
+                let added = args[1..]
+                    .chunks(2)
+                    .filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none())
+                    .count();
+                Reply::Int(added as i64)
+            }
+            "HMSET" => {
+                let hash = self.hash(arg(0)?)?;
+                for pair in args[1..].chunks(2) {
+                    hash.insert(pair[0].clone(), pair[1].clone());
+                }
+                Reply::Status("OK".into())
+            }
+            "HSETNX" => {
+                let (field, value) = (arg(1)?.to_vec(), arg(2)?.to_vec());
+                let hash = self.hash(arg(0)?)?;
+                let added = !hash.contains_key(&field);
+                if added {
+                    hash.insert(field, value);
+                }
+                Reply::Int(added as i64)
+            }
+            "HDEL" => {
+                let hash = self.hash(arg(0)?)?;
+                Reply::Int(args[1..].iter().filter(|f| hash.remove(*f).is_some()).count() as i64)
+            }
+            "HGETALL" => Reply::Array(
+                self.hash(arg(0)?)?
+                    .iter()
+                    .flat_map(|(f, v)| [Reply::Bulk(f.clone()), Reply::Bulk(v.clone())])
+                    .collect(),
+            ),
+            "HKEYS" => Reply::Array(self.hash(arg(0)?)?.keys().cloned().map(Reply::Bulk).collect()),
+            "HMGET" => {
+                let hash = self.hash(arg(0)?)?;
+                Reply::Array(
+                    args[1..]
+                        .iter()
+                        .map(|f| hash.get(f).cloned().map_or(Reply::Nil, Reply::Bulk))
+                        .collect(),
+                )
+            }
+            "HINCRBY" => {
+                let by: i64 = parse(arg(2)?)?;
+                let field = arg(1)?.to_vec();
+                let hash = self.hash(arg(0)?)?;
+                let value = match hash.get(&field) {
+                    Some(v) => parse::<i64>(v)? + by,
+                    None => by,
+                };
+                hash.insert(field, value.to_string().into_bytes());
+                Reply::Int(value)
+            }
+
+            "SADD" => {
+                let set = self.set(arg(0)?)?;
+                Reply::Int(args[1..].iter().filter(|m| set.insert(m.to_vec())).count() as i64)
+            }
+            "SREM" => {
+                let set = self.set(arg(0)?)?;
+                Reply::Int(args[1..].iter().filter(|m| set.remove(*m)).count() as i64)
+            }
+            "SISMEMBER" => Reply::Int(self.set(arg(0)?)?.contains(arg(1)?) as i64),
+            "SMEMBERS" => {
+                Reply::Array(self.set(arg(0)?)?.iter().cloned().map(Reply::Bulk).collect())
+            }
+            "SCARD" => Reply::Int(self.set(arg(0)?)?.len() as i64),
+
+            "ZADD" => {
+                let zset = self.zset(arg(0)?)?;
+                let mut added = 0;
+                for pair in args[1..].chunks(2) {
+                    let score: f64 = parse(&pair[0])?;
+                    if zset.insert(pair[1].clone(), score).is_none() {
+                        added += 1;
+                    }
+                }
+                Reply::Int(added)
+            }
+            "ZINCRBY" => {
+                let by: f64 = parse(arg(1)?)?;
+                let member = arg(2)?.to_vec();
+                let score = self.zset(arg(0)?)?.entry(member).or_insert(0.0);
+                *score += by;
+                Reply::Bulk(format_score(*score))
+            }
+            "ZSCORE" => match self.zset(arg(0)?)?.get(arg(1)?) {
+                Some(score) => Reply::Bulk(format_score(*score)),
+                None => Reply::Nil,
+            },
+            "ZREM" => {
+                let zset = self.zset(arg(0)?)?;
+                Reply::Int(args[1..].iter().filter(|m| zset.remove(*m).is_some()).count() as i64)
+            }
+            "ZCARD" => Reply::Int(self.zset(arg(0)?)?.len() as i64),
+            "ZCOUNT" => {
+                let (min, max) = (parse_bound(arg(1)?)?, parse_bound(arg(2)?)?);
+                let zset = self.zset(arg(0)?)?;
+                Reply::Int(zset.values().filter(|s| in_range(**s, min, max)).count() as i64)
+            }
+            "ZRANGE" | "ZREVRANGE" => {
+                let (start, stop): (i64, i64) = (parse(arg(1)?)?, parse(arg(2)?)?);
+                let mut entries = sorted(self.zset(arg(0)?)?);
+                if name == "ZREVRANGE" {
+                    entries.reverse();
+                }
+                let range = index_range(entries.len(), start, stop);
+                scored(entries[range].to_vec(), has_flag(&args[3..], "WITHSCORES"))
+            }
+            "ZRANGEBYSCORE" => {
+                let (min, max) = (parse_bound(arg(1)?)?, parse_bound(arg(2)?)?);
+                let entries = sorted(self.zset(arg(0)?)?)
+                    .into_iter()
+                    .filter(|(_, s)| in_range(*s, min, max))
+                    .collect();
+                scored(entries, has_flag(&args[3..], "WITHSCORES"))
+            }
+            "ZREMRANGEBYSCORE" => {
+                let (min, max) = (parse_bound(arg(1)?)?, parse_bound(arg(2)?)?);
+                let zset = self.zset(arg(0)?)?;
+                let before = zset.len();
+                zset.retain(|_, s| !in_range(*s, min, max));
+                Reply::Int((before - zset.len()) as i64)
+            }
+            "ZREVRANK" => {
+                let member = arg(1)?;
+                let entries = sorted(self.zset(arg(0)?)?);
+                match entries.iter().rev().position(|(m, _)| m == member) {
+                    Some(rank) => Reply::Int(rank as i64),
+                    None => Reply::Nil,
+                }
+            }
+            "ZUNIONSTORE" => {
+                let count: usize = parse(arg(1)?)?;
+                let mut union: HashMap<Vec<u8>, f64> = HashMap::new();
+                for key in args.get(2..2 + count).ok_or("ERR syntax error")? {
+                    for (member, score) in self.zset(key)?.iter() {
+                        *union.entry(member.clone()).or_insert(0.0) += score;
+                    }
+                }
+                let len = union.len();
+                self.data.insert(arg(0)?.to_vec(), Value::ZSet(union));
+                Reply::Int(len as i64)
+            }
+
+            "LPUSH" => {
+                let list = self.list(arg(0)?)?;
+                for value in &args[1..] {
+                    list.push_front(value.clone());
+                }
+                Reply::Int(list.len() as i64)
+            }
+            "RPOPLPUSH" => match self.list(arg(0)?)?.pop_back() {
+                Some(value) => {
+                    self.list(arg(1)?)?.push_front(value.clone());
+                    Reply::Bulk(value)
+                }
+                None => Reply::Nil,
+            },
+            "LREM" => {
+                let count: i64 = parse(arg(1)?)?;
+                let value = arg(2)?;
+                let list = self.list(arg(0)?)?;
+                let mut removed = 0;
+                // Only the count > 0 form (from the head) is used
+                while count == 0 || removed < count {
+                    match list.iter().position(|v| v == value) {
+                        Some(i) => {
+                            list.remove(i);
+                            removed += 1;
+                        }
+                        None => break,
+                    }
+                }
+                Reply::Int(removed)
+            }
+            "LMOVE" => {
+                let (from_left, to_left) = (
+                    arg(2)?.eq_ignore_ascii_case(b"LEFT"),
+                    arg(3)?.eq_ignore_ascii_case(b"LEFT"),
+                );
+                let source = self.list(arg(0)?)?;
+                let value = if from_left { source.pop_front() } else { source.pop_back() };
+                match value {
+                    Some(value) => {
+                        let destination = self.list(arg(1)?)?;
+                        if to_left {
+                            destination.push_front(value.clone());
+                        } else {
+                            destination.push_back(value.clone());
+                        }
+                        Reply::Bulk(value)
+                    }
+                    None => Reply::Nil,
+                }
+            }
+            "LLEN" => Reply::Int(self.list(arg(0)?)?.len() as i64),
+            "LRANGE" => {
+                let (start, stop): (i64, i64) = (parse(arg(1)?)?, parse(arg(2)?)?);
+                let list = self.list(arg(0)?)?;
+                let range = index_range(list.len(), start, stop);
+                Reply::Array(list.range(range).cloned().map(Reply::Bulk).collect())
+            }
+
+            "SCRIPT" if arg(0)?.eq_ignore_ascii_case(b"LOAD") => {
+                let body = String::from_utf8_lossy(arg(1)?).into_owned();
+                let sha = sha1_smol::Sha1::from(&body).digest().to_string();
+                self.scripts.insert(sha.clone(), body);
+                Reply::Bulk(sha.into_bytes())
+            }
+            "EVAL" | "EVALSHA" => {
+                let body = if name == "EVAL" {
+                    String::from_utf8_lossy(arg(0)?).into_owned()
+                } else {
+                    let sha = String::from_utf8_lossy(arg(0)?).into_owned();
+                    self.scripts
+                        .get(&sha)
+                        .cloned()
+                        .ok_or("NOSCRIPT No matching script. Please use EVAL.")?
+                };
+                let count: usize = parse(arg(1)?)?;
+                let keys = args.get(2..2 + count).ok_or("ERR syntax error")?;
+                self.eval(&body, keys, &args[2 + count..])?
+            }
+
+            _ => return Err(format!("ERR unknown command '{name}'")),
+        })
+    }
+
+    /// Run a script in a fresh Lua interpreter whose `redis.call` runs commands on this
+    /// state. Replies convert to and from Lua values as in Valkey.
+    fn eval(&mut self, body: &str, keys: &[Vec<u8>], argv: &[Vec<u8>]) -> CmdResult {
+        let lua = Lua::new();
+        let state = RefCell::new(self);
+        let result = lua.scope(|scope| {
+            let globals = lua.globals();
+            globals.set("KEYS", strings_table(&lua, keys)?)?;
+            globals.set("ARGV", strings_table(&lua, argv)?)?;
+
+            let redis = lua.create_table()?;
+            let call = scope.create_function(|lua, args: Variadic<LuaValue>| {
+                match state.borrow_mut().run(script_command(args)?) {
+                    Reply::Error(e) => Err(mlua::Error::external(ReplyError(e))),
+                    reply => reply_to_lua(lua, reply),
+                }
+            })?;
+            let pcall = scope.create_function(|lua, args: Variadic<LuaValue>| {
+                reply_to_lua(lua, state.borrow_mut().run(script_command(args)?))
+            })?;
+            redis.set("call", call)?;
+            redis.set("pcall", pcall)?;
+            redis.set(
+                "error_reply",
+                lua.create_function(|lua, e: String| lua.create_table_from([("err", e)]))?,
+            )?;
+            redis.set(
+                "status_reply",
+                lua.create_function(|lua, s: String| lua.create_table_from([("ok", s)]))?,
+            )?;
+            globals.set("redis", redis)?;
+
+            let value: LuaValue = lua.load(body).set_name("user_script").eval()?;
+            Ok(reply_from_lua(value))
+        });
+        result.map_err(|e| script_error(&e))
+    }
+}
+
+/// A command's error reply, raised by `redis.call` so the script fails with it.
+#[derive(Debug)]
+struct ReplyError(String);
+
+impl std::fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ReplyError {}
+
+/// The error reply of a failed script: a command's own error passes through as is.
+fn script_error(e: &mlua::Error) -> String {
+    match e {
+        mlua::Error::CallbackError { cause, .. } => script_error(cause),
+        mlua::Error::ExternalError(cause) => match cause.downcast_ref::<ReplyError>() {
+            Some(ReplyError(reply)) => reply.clone(),
+            None => format!("ERR {cause}"),
+        },
+        e => format!("ERR {e}"),
+    }
+}
+
+fn strings_table<'lua>(lua: &'lua Lua, items: &[Vec<u8>]) -> mlua::Result<mlua::Table<'lua>> {
+    let strings = items
+        .iter()
+        .map(|item| lua.create_string(item))
+        .collect::<mlua::Result<Vec<_>>>()?;
+    lua.create_sequence_from(strings)
+}
+
+/// The command a script passes to `redis.call`: strings and numbers only.
+fn script_command(args: Variadic<LuaValue>) -> mlua::Result<Command> {
+    let command: Command = args
+        .iter()
+        .map(|arg| match arg {
+            LuaValue::String(s) => Ok(s.as_bytes().to_vec()),
+            LuaValue::Integer(i) => Ok(i.to_string().into_bytes()),
+            LuaValue::Number(n) => Ok(format_score(*n)),
+            _ => Err(mlua::Error::runtime(
+                "Lua redis lib command arguments must be strings or integers",
+            )),
+        })
+        .collect::<mlua::Result<_>>()?;
+    if command.is_empty() {
+        return Err(mlua::Error::runtime(
+            "Please specify at least one argument for this redis lib call",
+        ));
+    }
+    Ok(command)
+}
+
+fn reply_to_lua(lua: &Lua, reply: Reply) -> mlua::Result<LuaValue<'_>> {
+    Ok(match reply {
+        Reply::Status(s) => LuaValue::Table(lua.create_table_from([("ok", s)])?),
+        Reply::Error(e) => LuaValue::Table(lua.create_table_from([("err", e)])?),
+        Reply::Int(i) => LuaValue::Integer(i),
+        Reply::Bulk(b) => LuaValue::String(lua.create_string(b)?),
+        Reply::Nil => LuaValue::Boolean(false),
+        Reply::Array(items) => {
+            let items = items
+                .into_iter()
+                .map(|item| reply_to_lua(lua, item))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            LuaValue::Table(lua.create_sequence_from(items)?)
+        }
+    })
+}
+
+fn reply_from_lua(value: LuaValue) -> Reply {
+    match value {
+        LuaValue::Integer(i) => Reply::Int(i),
+        LuaValue::Number(n) => Reply::Int(n as i64),
+        LuaValue::String(s) => Reply::Bulk(s.as_bytes().to_vec()),
+        LuaValue::Boolean(true) => Reply::Int(1),
+        LuaValue::Table(table) => {
+            if let Ok(Some(e)) = table.get::<_, Option<String>>("err") {
+                return Reply::Error(e);
+            }
+            if let Ok(Some(s)) = table.get::<_, Option<String>>("ok") {
+                return Reply::Status(s);
+            }
+            // An array up to its first nil
+            Reply::Array(
+                table
+                    .sequence_values::<LuaValue>()
+                    .map_while(Result::ok)
+                    .map(reply_from_lua)
+                    .collect(),
+            )
+        }
+        _ => Reply::Nil,
+    }
+}
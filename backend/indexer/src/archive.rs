@@ -0,0 +1,85 @@
+//! Offline block source: neardata `BlockWithTxHashes` JSON read from local files
+//! instead of fetched, for development and tests without network access.
+//!
+//! An archive is either a directory of `*.json` / `*.json.gz` files or a `.tar.gz`
+//! (`.tgz`) tarball of them, one block per file. Blocks are fed in height order
+//! whatever the file names, so an archive must fit in memory.
+
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Load every block in the archive at `path` from `start_block` on, sorted by height.
+pub fn load_blocks(path: &Path, start_block: Option<u64>) -> io::Result<Vec<BlockWithTxHashes>> {
+    let mut blocks = Vec::new();
+    if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<_>>()?;
+        files.sort();
+        for file in files {
+            let name = file.to_string_lossy();
+            if is_block_file(&name) {
+                let block = parse_block(&name, std::fs::File::open(&file)?)?;
+                blocks.push(block);
+            }
+        }
+    } else if is_tarball(&path.to_string_lossy()) {
+        let tar = flate2::read::GzDecoder::new(std::fs::File::open(path)?);
+        for entry in tar::Archive::new(tar).entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if entry.header().entry_type().is_file() && is_block_file(&name) {
+                blocks.push(parse_block(&name, entry)?);
+            }
+        }
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: not a directory or .tar.gz archive", path.display()),
+        ));
+    }
+
+    blocks.retain(|b| start_block.is_none_or(|start| b.block.header.height >= start));
+    blocks.sort_by_key(|b| b.block.header.height);
+    Ok(blocks)
+}
+
+/// Feed the archive's blocks to `blocks_tx` in place of `start_fetcher`. The channel
+/// closes after the last block, which stops `process_blocks`.
+pub async fn start_archive_reader(
+    blocks: Vec<BlockWithTxHashes>,
+    blocks_tx: mpsc::Sender<BlockWithTxHashes>,
+    is_running: Arc<AtomicBool>,
+) {
+    let total = blocks.len();
+    for block in blocks {
+        if !is_running.load(Ordering::SeqCst) || blocks_tx.send(block).await.is_err() {
+            return;
+        }
+    }
+    tracing::info!("Archive exhausted after {} blocks", total);
+}
+
+fn is_block_file(name: &str) -> bool {
+    name.ends_with(".json") || name.ends_with(".json.gz")
+}
+
+fn is_tarball(name: &str) -> bool {
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+fn parse_block(name: &str, reader: impl Read) -> io::Result<BlockWithTxHashes> {
+    let invalid = |e: serde_json::Error| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{name}: {e}"))
+    };
+    if name.ends_with(".gz") {
+        serde_json::from_reader(io::BufReader::new(flate2::read::GzDecoder::new(reader)))
+            .map_err(invalid)
+    } else {
+        serde_json::from_reader(io::BufReader::new(reader)).map_err(invalid)
+    }
+}
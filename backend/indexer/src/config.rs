@@ -0,0 +1,121 @@
+//! Settings read from the environment, shared by the indexer and all-in-one binaries.
+
+use crate::archive;
+use crate::processor::{BoardRoute, IndexMode};
+use common::valkey::{is_valid_board_id, Keyspace, DEFAULT_BOARD};
+use fastnear_neardata_fetcher::{start_fetcher, FetcherConfigBuilder};
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_primitives::types::Finality;
+use fastnear_primitives::types::ChainId;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+pub fn chain_id() -> ChainId {
+    std::env::var("CHAIN_ID")
+        .map(|s| ChainId::try_from(s).unwrap_or_else(|e| panic!("invalid CHAIN_ID: {e}")))
+        .unwrap_or(ChainId::Mainnet)
+}
+
+/// The boards fed by the indexer: one per `BOARDS_CONFIG` board that has contracts
+/// (boards without any are server-only), or else the `BOARD` receiving the
+/// `CONTRACT_IDS` draws.
+pub fn routes(keyspace: &Keyspace, chain_id: ChainId) -> Vec<BoardRoute> {
+    let routes: Vec<BoardRoute> = match std::env::var("BOARDS_CONFIG") {
+        Ok(path) => common::boards::load_boards(&path)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .into_iter()
+            .filter(|b| !b.contract_ids.is_empty())
+            .map(|b| BoardRoute {
+                keys: keyspace.for_board(&b.id),
+                contract_ids: b.contract_ids,
+            })
+            .collect(),
+        Err(_) => {
+            // CONTRACT_IDS is a comma-separated list; CONTRACT_ID is the older single-contract form
+            let contract_ids =
+                std::env::var("CONTRACT_IDS").or_else(|_| std::env::var("CONTRACT_ID"));
+            let contract_ids: Vec<String> = match contract_ids {
+                Ok(s) => split_list(&s),
+                Err(_) if chain_id == ChainId::Mainnet => vec!["berryfast.near".into()],
+                Err(_) => panic!("CONTRACT_IDS is required on {chain_id}"),
+            };
+            assert!(!contract_ids.is_empty(), "CONTRACT_IDS is empty");
+            // Board whose queue receives the events (the default board owns the original keys)
+            let board = std::env::var("BOARD").unwrap_or_else(|_| DEFAULT_BOARD.into());
+            assert!(is_valid_board_id(&board), "invalid BOARD: {board}");
+            vec![BoardRoute {
+                keys: keyspace.for_board(&board),
+                contract_ids,
+            }]
+        }
+    };
+    assert!(!routes.is_empty(), "no board in BOARDS_CONFIG has contract_ids");
+    routes
+}
+
+pub fn index_mode() -> IndexMode {
+    std::env::var("INDEX_MODE")
+        .ok()
+        .map(|s| IndexMode::from_name(&s).unwrap_or_else(|| panic!("invalid INDEX_MODE: {s}")))
+        .unwrap_or(IndexMode::Events)
+}
+
+/// Relay contracts whose forwarded draws are credited to the transaction signer.
+pub fn relayers() -> Vec<String> {
+    std::env::var("RELAYER_ACCOUNT_IDS")
+        .map(|s| split_list(&s))
+        .unwrap_or_default()
+}
+
+/// Start sending blocks from `start_block` on: read from the `BLOCK_ARCHIVE` file or
+/// directory when set, else fetched from neardata.
+pub fn start_blocks(
+    chain_id: ChainId,
+    start_block: Option<u64>,
+    blocks_tx: mpsc::Sender<BlockWithTxHashes>,
+    is_running: Arc<AtomicBool>,
+) -> anyhow::Result<JoinHandle<()>> {
+    if let Ok(path) = std::env::var("BLOCK_ARCHIVE") {
+        // Offline: blocks from a local directory or .tar.gz instead of neardata
+        let blocks = archive::load_blocks(Path::new(&path), start_block)?;
+        tracing::info!("Reading {} blocks from archive {}", blocks.len(), path);
+        return Ok(tokio::spawn(archive::start_archive_reader(
+            blocks, blocks_tx, is_running,
+        )));
+    }
+
+    let num_threads: u64 = std::env::var("FETCHER_THREADS")
+        .map(|s| s.parse().unwrap_or_else(|_| panic!("invalid FETCHER_THREADS: {s}")))
+        .unwrap_or(4);
+    // Only final blocks: they are never reorganized, so events pushed from them stand
+    let mut builder = FetcherConfigBuilder::new()
+        .num_threads(num_threads)
+        .chain_id(chain_id)
+        .finality(Finality::Final);
+
+    if let Some(height) = start_block {
+        builder = builder.start_block_height(height);
+    }
+
+    if let Ok(token) = std::env::var("AUTH_BEARER_TOKEN") {
+        builder = builder.auth_bearer_token(token);
+    }
+
+    let config = builder.build();
+
+    Ok(tokio::spawn(async move {
+        start_fetcher(config, blocks_tx, is_running).await;
+    }))
+}
+
+/// Split a comma-separated env value, dropping empty entries.
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,4 @@
+pub mod archive;
+pub mod config;
+pub mod metrics;
+pub mod processor;
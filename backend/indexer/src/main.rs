@@ -1,8 +1,8 @@
-mod processor;
-
-use fastnear_neardata_fetcher::{FetcherConfigBuilder, start_fetcher};
-use redis::AsyncCommands;
-use std::sync::atomic::AtomicBool;
+use common::valkey::Keyspace;
+use fastnear_primitives::types::ChainId;
+use indexer::{config, metrics};
+use indexer::processor::{self, ProcessError};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
@@ -18,54 +18,82 @@ async fn main() -> anyhow::Result<()> {
         )
         .init();
 
-    let contract_account = std::env::var("CONTRACT_ID").unwrap_or_else(|_| "berryfast.near".into());
+    let chain_id = config::chain_id();
+    let keyspace = Keyspace::from_env();
+    let routes = config::routes(&keyspace, chain_id);
+    if chain_id != ChainId::Mainnet && keyspace.name().is_empty() {
+        tracing::warn!(
+            "Indexing {} into the unprefixed keyspace; set VALKEY_PREFIX to share Valkey with a mainnet board",
+            chain_id
+        );
+    }
+    let index_mode = config::index_mode();
+    let relayers = config::relayers();
     let valkey_url = std::env::var("VALKEY_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".into());
     let client = redis::Client::open(valkey_url)?;
     let mut con = client.get_multiplexed_async_connection().await?;
 
-    // Read last processed block height, falling back to START_BLOCK_HEIGHT env var
-    let start_block: Option<u64> = con.get(common::valkey::LAST_PROCESSED_BLOCK).await?;
-    let start_block = start_block.map(|h| h + 1).or_else(|| {
+    // Resume after the least advanced board, falling back to START_BLOCK_HEIGHT env var
+    let last_processed = processor::last_processed(&mut con, &routes).await?;
+    let start_block = last_processed.as_ref().map(|b| b.height + 1).or_else(|| {
         std::env::var("START_BLOCK_HEIGHT")
             .ok()
             .and_then(|s| s.parse::<u64>().ok())
     });
 
     tracing::info!(
-        "Starting indexer from block {:?} for contract {}",
+        "Starting {} indexer from block {:?} ({:?} mode, keyspace {:?}, relayers: {:?})",
+        chain_id,
         start_block,
-        &contract_account
+        index_mode,
+        keyspace.name(),
+        relayers
     );
 
+    for route in &routes {
+        tracing::info!("Board {}: contracts {:?}", route.keys.board(), route.contract_ids);
+    }
+
     let is_running = Arc::new(AtomicBool::new(true));
     signal_hook::flag::register_conditional_default(signal_hook::consts::SIGINT, is_running.clone())?;
     signal_hook::flag::register_conditional_default(signal_hook::consts::SIGTERM, is_running.clone())?;
 
-    let (blocks_tx, blocks_rx) = mpsc::channel(100);
-
-    let mut builder = FetcherConfigBuilder::new()
-        .num_threads(4)
-        .chain_id(fastnear_primitives::types::ChainId::Mainnet);
-
-    if let Some(height) = start_block {
-        builder = builder.start_block_height(height);
+    // Optional Prometheus listener, e.g. METRICS_ADDR=0.0.0.0:9100
+    if let Ok(addr) = std::env::var("METRICS_ADDR") {
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(&addr).await {
+                tracing::error!("Metrics listener on {} failed: {}", addr, e);
+            }
+        });
     }
 
-    if let Ok(token) = std::env::var("AUTH_BEARER_TOKEN") {
-        builder = builder.auth_bearer_token(token);
-    }
-
-    let config = builder.build();
+    let (blocks_tx, blocks_rx) = mpsc::channel(100);
 
-    let fetcher_running = is_running.clone();
-    let fetcher_handle = tokio::spawn(async move {
-        start_fetcher(config, blocks_tx, fetcher_running).await;
-    });
+    let fetcher_handle =
+        config::start_blocks(chain_id, start_block, blocks_tx, is_running.clone())?;
 
-    processor::process_blocks(blocks_rx, con, is_running.clone(), &contract_account).await;
+    let result = processor::process_blocks(
+        blocks_rx,
+        con,
+        is_running.clone(),
+        &routes,
+        index_mode,
+        &relayers,
+        last_processed,
+    )
+    .await;
 
     fetcher_handle.abort();
+    if let Err(ProcessError::Discontinuity(_)) = &result {
+        // Stay up so the halt stays visible in metrics and health until an operator
+        // looks at the chain data; a restart would only hit the same block again.
+        while is_running.load(Ordering::SeqCst) {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+    }
+    result?;
 
     tracing::info!("Indexer stopped.");
     Ok(())
 }
+
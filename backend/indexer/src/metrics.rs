@@ -0,0 +1,171 @@
+//! Prometheus metrics, served in the text exposition format at `/metrics` on
+//! `METRICS_ADDR` when it is set.
+//!
+//! Metric names are relied on by dashboards and alerts; treat them as a stable
+//! interface.
+
+use crate::processor::ProcessorStats;
+use prometheus::core::Collector;
+use prometheus::{Encoder, Gauge, IntCounter, IntGauge, Registry, TextEncoder};
+use std::sync::LazyLock;
+
+pub struct Metrics {
+    registry: Registry,
+    /// `indexer_blocks_total`: blocks processed; its rate is the indexing speed.
+    pub blocks: IntCounter,
+    /// `indexer_last_block_height`: height of the last processed block.
+    pub last_block_height: IntGauge,
+    /// `indexer_block_lag_seconds`: wall-clock time minus the last processed block's
+    /// timestamp, i.e. how far the indexer trails the chain head.
+    pub block_lag_seconds: Gauge,
+    /// `indexer_draw_receipts_total`: receipts addressed to an indexed contract.
+    pub draw_receipts: IntCounter,
+    /// `indexer_failed_receipts_total`: contract receipts skipped because they failed.
+    pub failed_receipts: IntCounter,
+    /// `indexer_draw_events_total`: draw events extracted and queued.
+    pub draw_events: IntCounter,
+    /// `indexer_relayed_events_total`: events credited to a relayed call's signer.
+    pub relayed_events: IntCounter,
+    /// `indexer_parse_failures_total`: event logs or draw args that could not be parsed.
+    pub parse_failures: IntCounter,
+    /// `indexer_commit_failures_total`: failed attempts to commit a block's events and
+    /// progress to Valkey. Each is retried; the block is not marked processed until
+    /// one succeeds.
+    pub commit_failures: IntCounter,
+    /// `indexer_lpush_failures_total`: the former name of `indexer_commit_failures_total`
+    /// from when events were pushed one by one, kept in step with it so existing
+    /// dashboards and alerts still work. Deprecated.
+    pub lpush_failures: IntCounter,
+    /// `indexer_halted`: 1 once a block failed to continue the chain and indexing
+    /// stopped; clears only on restart.
+    pub halted: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let counter = |name: &str, help: &str| IntCounter::new(name, help).unwrap();
+        Self {
+            registry: Registry::new(),
+            blocks: counter("indexer_blocks_total", "Blocks processed"),
+            last_block_height: IntGauge::new(
+                "indexer_last_block_height",
+                "Height of the last processed block",
+            )
+            .unwrap(),
+            block_lag_seconds: Gauge::new(
+                "indexer_block_lag_seconds",
+                "Seconds between the last processed block's timestamp and now",
+            )
+            .unwrap(),
+            draw_receipts: counter("indexer_draw_receipts_total", "Receipts to indexed contracts"),
+            failed_receipts: counter(
+                "indexer_failed_receipts_total",
+                "Failed contract receipts skipped",
+            ),
+            draw_events: counter("indexer_draw_events_total", "Draw events queued"),
+            relayed_events: counter(
+                "indexer_relayed_events_total",
+                "Draw events credited to a relayed call's signer",
+            ),
+            parse_failures: counter(
+                "indexer_parse_failures_total",
+                "Event logs or draw args that could not be parsed",
+            ),
+            commit_failures: counter(
+                "indexer_commit_failures_total",
+                "Failed attempts to commit a block to Valkey",
+            ),
+            lpush_failures: counter(
+                "indexer_lpush_failures_total",
+                "Deprecated: same as indexer_commit_failures_total",
+            ),
+            halted: IntGauge::new(
+                "indexer_halted",
+                "1 if indexing halted on a chain discontinuity",
+            )
+            .unwrap(),
+        }
+        .registered()
+    }
+
+    fn registered(self) -> Self {
+        let collectors: [Box<dyn Collector>; 11] = [
+            Box::new(self.blocks.clone()),
+            Box::new(self.last_block_height.clone()),
+            Box::new(self.block_lag_seconds.clone()),
+            Box::new(self.draw_receipts.clone()),
+            Box::new(self.failed_receipts.clone()),
+            Box::new(self.draw_events.clone()),
+            Box::new(self.relayed_events.clone()),
+            Box::new(self.parse_failures.clone()),
+            Box::new(self.commit_failures.clone()),
+            Box::new(self.lpush_failures.clone()),
+            Box::new(self.halted.clone()),
+        ];
+        for collector in collectors {
+            self.registry.register(collector).expect("metric names are unique");
+        }
+        self
+    }
+
+    /// Add the counts accumulated in `after` since `before`.
+    pub fn record_stats(&self, before: &ProcessorStats, after: &ProcessorStats) {
+        self.blocks.inc_by(after.blocks - before.blocks);
+        self.draw_receipts.inc_by(after.contract_receipts - before.contract_receipts);
+        self.failed_receipts
+            .inc_by(after.failed_receipts_skipped - before.failed_receipts_skipped);
+        self.draw_events.inc_by(after.events - before.events);
+        self.relayed_events.inc_by(after.relayed_events - before.relayed_events);
+        self.parse_failures.inc_by(after.parse_failures - before.parse_failures);
+    }
+
+    /// Record the last processed block's position relative to the wall clock.
+    pub fn record_block(&self, block_height: u64, block_timestamp_ms: u64) {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        self.last_block_height.set(block_height as i64);
+        self.block_lag_seconds
+            .set(now_ms.saturating_sub(block_timestamp_ms) as f64 / 1000.0);
+    }
+
+    /// Count a failed attempt to commit a block, under both its current and former name.
+    pub fn record_commit_failure(&self) {
+        self.commit_failures.inc();
+        self.lpush_failures.inc();
+    }
+
+    /// Render every metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .expect("text encoding never fails");
+        String::from_utf8(buf).expect("metrics are UTF-8")
+    }
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The process-wide metrics.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Serve `/metrics` on `addr` until the process exits.
+pub async fn serve(addr: &str) -> anyhow::Result<()> {
+    let app = axum::Router::new().route(
+        "/metrics",
+        axum::routing::get(|| async {
+            (
+                [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                metrics().render(),
+            )
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Metrics listening on {}", addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
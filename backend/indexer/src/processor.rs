@@ -1,20 +1,443 @@
-use common::valkey;
-use common::DrawArgs;
-use common::DrawEvent;
+use crate::metrics::metrics;
+use common::valkey::Keyspace;
+use common::{
+    DrawArgs, DrawEvent, DrawEventLog, IndexerHeartbeat, DRAW_EVENT_NAME, DRAW_EVENT_STANDARD,
+    EVENT_LOG_PREFIX,
+};
 use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
-use fastnear_primitives::near_primitives::views::{ActionView, ReceiptEnumView};
-use redis::AsyncCommands;
+use fastnear_primitives::near_primitives::views::{
+    ActionView, ExecutionStatusView, ReceiptEnumView,
+};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
+/// Where draw events are read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexMode {
+    /// NEP-297 `EVENT_JSON:` logs of successful `draw` receipts (default).
+    Events,
+    /// Raw `draw` FunctionCall args. Needed for blocks produced before the contract
+    /// started emitting events.
+    Args,
+}
+
+impl IndexMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "events" => Some(Self::Events),
+            "args" => Some(Self::Args),
+            _ => None,
+        }
+    }
+}
+
+/// Running counters kept by the processor.
+#[derive(Debug, Default, Clone)]
+pub struct ProcessorStats {
+    pub blocks: u64,
+    /// Receipts addressed to the contract, successful or not.
+    pub contract_receipts: u64,
+    /// Contract receipts dropped because their execution outcome failed.
+    pub failed_receipts_skipped: u64,
+    /// Event logs or draw args that could not be parsed.
+    pub parse_failures: u64,
+    pub events: u64,
+    /// Events credited to a relayed call's original signer instead of the relayer.
+    pub relayed_events: u64,
+}
+
+/// A board fed by the indexer: the contracts whose draws it receives, and its keys.
+#[derive(Debug, Clone)]
+pub struct BoardRoute {
+    pub contract_ids: Vec<String>,
+    pub keys: Keyspace,
+}
+
+/// Extract the draw events of a block sent to any of `contract_ids`, in receipt
+/// execution order. See `route_events`.
+pub fn extract_events(
+    block: &BlockWithTxHashes,
+    contract_ids: &[String],
+    mode: IndexMode,
+    relayers: &[String],
+    stats: &mut ProcessorStats,
+) -> Vec<DrawEvent> {
+    let route = BoardRoute {
+        contract_ids: contract_ids.to_vec(),
+        keys: Keyspace::default(),
+    };
+    route_events(block, std::slice::from_ref(&route), mode, relayers, stats)
+        .into_iter()
+        .map(|(_, event)| event)
+        .collect()
+}
+
+/// Extract the draw events of a block, in receipt execution order, each paired with
+/// the index of the route whose contract received it. Each board's events are
+/// numbered from 0 in `event_index`.
+///
+/// Receipts whose execution failed (contract panic, out of gas, ...) are dropped in
+/// every mode: the contract rejected them, so their pixels must not reach the board.
+///
+/// Pixels are credited to the account that signed for them:
+/// - NEP-366 delegate actions need no special handling. The runtime executes the
+///   inner `draw` as a receipt whose predecessor is the delegate action's sender; the
+///   relayer only shows up as the receipt's signer.
+/// - Calls forwarded by one of the trusted `relayers` (a relay contract calling
+///   `draw` on a user's behalf) are credited to the transaction signer instead of
+///   the relayer.
+pub fn route_events(
+    block: &BlockWithTxHashes,
+    routes: &[BoardRoute],
+    mode: IndexMode,
+    relayers: &[String],
+    stats: &mut ProcessorStats,
+) -> Vec<(usize, DrawEvent)> {
+    let block_height = block.block.header.height;
+    let block_timestamp = block.block.header.timestamp_nanosec;
+    let block_timestamp_ms = block_timestamp / 1_000_000; // Convert to milliseconds
+
+    let mut events = Vec::new();
+    let mut skipped = 0u64;
+
+    // Iterate through shards and receipt execution outcomes (maintains ordering)
+    for shard in &block.shards {
+        for outcome in &shard.receipt_execution_outcomes {
+            let receipt = &outcome.receipt;
+
+            // Filter: only receipts to our contracts
+            let receiver_id = receipt.receiver_id.as_str();
+            let Some(route) = routes
+                .iter()
+                .position(|r| r.contract_ids.iter().any(|c| c == receiver_id))
+            else {
+                continue;
+            };
+            stats.contract_receipts += 1;
+
+            // Logs emitted before a failure survive in the outcome, so check both modes
+            if !is_success(&outcome.execution_outcome.outcome.status) {
+                skipped += 1;
+                continue;
+            }
+
+            let predecessor_id = receipt.predecessor_id.as_str();
+            let author = relayed_signer(&receipt.receipt, predecessor_id, relayers)
+                .unwrap_or(predecessor_id);
+
+            let receipt_events = match mode {
+                IndexMode::Events => events_from_logs(
+                    &outcome.execution_outcome.outcome.logs,
+                    predecessor_id,
+                    author,
+                    block_height,
+                    block_timestamp_ms,
+                    stats,
+                ),
+                IndexMode::Args => events_from_args(
+                    &receipt.receipt,
+                    author,
+                    block_height,
+                    block_timestamp_ms,
+                    stats,
+                ),
+            };
+            if author != predecessor_id {
+                stats.relayed_events += receipt_events.len() as u64;
+            }
+            events.extend(receipt_events.into_iter().map(|e| (route, e)));
+        }
+    }
+
+    // Number each board's events so its consumer can skip ones it already applied
+    let mut next_index = vec![0u32; routes.len()];
+    for (route, event) in &mut events {
+        event.event_index = Some(next_index[*route]);
+        next_index[*route] += 1;
+    }
+
+    if skipped > 0 {
+        tracing::info!(
+            "Block {}: skipped {} failed contract receipts",
+            block_height,
+            skipped
+        );
+    }
+    stats.failed_receipts_skipped += skipped;
+    stats.events += events.len() as u64;
+    stats.blocks += 1;
+
+    events
+}
+
+/// Backoff between attempts to commit a block: doubles from the initial delay up to
+/// the maximum.
+const COMMIT_RETRY_INITIAL_MS: u64 = 100;
+const COMMIT_RETRY_MAX_MS: u64 = 5_000;
+
+/// A processed block, which the next block must build on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockRef {
+    pub height: u64,
+    /// Missing for progress recorded before block hashes were.
+    pub hash: Option<String>,
+}
+
+impl BlockRef {
+    pub fn of(block: &BlockWithTxHashes) -> Self {
+        Self {
+            height: block.block.header.height,
+            hash: Some(block.block.header.hash.to_string()),
+        }
+    }
+}
+
+/// A block that does not continue the chain from the last processed block.
+///
+/// Only final blocks are indexed and those are never reorganized, so this means the
+/// block source skipped, repeated or forked blocks: events built on it cannot be
+/// trusted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discontinuity {
+    /// The block is at or below the last processed height.
+    NotAfter { last: u64, height: u64 },
+    /// The block's parent is not the last processed block: blocks in between are missing.
+    Skipped { last: u64, prev_height: u64, height: u64 },
+    /// The block's parent has the last processed height but a different hash.
+    HashMismatch {
+        height: u64,
+        prev_hash: String,
+        last_hash: String,
+    },
+}
+
+impl std::fmt::Display for Discontinuity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAfter { last, height } => {
+                write!(f, "block {height} does not follow the last processed block {last}")
+            }
+            Self::Skipped { last, prev_height, height } => write!(
+                f,
+                "block {height} builds on block {prev_height}, skipping ahead of the last \
+                 processed block {last}"
+            ),
+            Self::HashMismatch { height, prev_hash, last_hash } => write!(
+                f,
+                "block {height} builds on {prev_hash}, not the last processed block {last_hash}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Discontinuity {}
+
+/// Check that `block` directly follows `last`. Heights the chain skipped are fine as
+/// long as the block's parent is `last`; the hash is checked when `last` has one.
+pub fn check_continuity(last: &BlockRef, block: &BlockWithTxHashes) -> Result<(), Discontinuity> {
+    let header = &block.block.header;
+    if header.height <= last.height {
+        return Err(Discontinuity::NotAfter {
+            last: last.height,
+            height: header.height,
+        });
+    }
+    if let Some(prev_height) = header.prev_height {
+        if prev_height != last.height {
+            return Err(Discontinuity::Skipped {
+                last: last.height,
+                prev_height,
+                height: header.height,
+            });
+        }
+    }
+    let prev_hash = header.prev_hash.to_string();
+    match &last.hash {
+        Some(last_hash) if *last_hash != prev_hash => Err(Discontinuity::HashMismatch {
+            height: header.height,
+            prev_hash,
+            last_hash: last_hash.clone(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The least advanced board's last processed block, which indexing resumes after, or
+/// `None` if no board has processed a block yet. Boards with no progress yet join from
+/// wherever the others resume.
+pub async fn last_processed(
+    con: &mut redis::aio::MultiplexedConnection,
+    routes: &[BoardRoute],
+) -> redis::RedisResult<Option<BlockRef>> {
+    let mut last: Option<BlockRef> = None;
+    for route in routes {
+        let (height, hash): (Option<u64>, Option<String>) = redis::pipe()
+            .get(route.keys.last_processed_block())
+            .get(route.keys.last_processed_block_hash())
+            .query_async(con)
+            .await?;
+        if let Some(height) = height {
+            if last.as_ref().is_none_or(|l| height < l.height) {
+                last = Some(BlockRef { height, hash });
+            }
+        }
+    }
+    Ok(last)
+}
+
+/// Why `process_blocks` stopped early.
+#[derive(Debug)]
+pub enum ProcessError {
+    /// The Valkey connection was lost. The block being committed is left for the next
+    /// run.
+    Valkey(redis::RedisError),
+    /// A block did not continue the chain; nothing of it was committed.
+    Discontinuity(Discontinuity),
+}
+
+impl std::fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Valkey(e) => write!(f, "valkey: {e}"),
+            Self::Discontinuity(d) => write!(f, "chain discontinuity: {d}"),
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {}
+
+/// Queue a block's events and mark it (height and hash) processed on every board, in
+/// one MULTI/EXEC.
+///
+/// Either the whole block lands or nothing does, so a crash or failure at any point
+/// leaves the block to be processed again on restart without duplicating its events.
+pub async fn commit_block(
+    con: &mut redis::aio::MultiplexedConnection,
+    routes: &[BoardRoute],
+    events: &[(usize, DrawEvent)],
+    block_hash: &str,
+    heartbeat: &IndexerHeartbeat,
+) -> redis::RedisResult<()> {
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for (i, route) in routes.iter().enumerate() {
+        let queued: Vec<String> = events
+            .iter()
+            .filter(|(r, _)| *r == i)
+            .map(|(_, event)| serde_json::to_string(event).unwrap())
+            .collect();
+        if !queued.is_empty() {
+            pipe.lpush(route.keys.draw_queue(), queued).ignore();
+        }
+        pipe.set(route.keys.last_processed_block(), heartbeat.block_height)
+            .ignore()
+            .set(route.keys.last_processed_block_hash(), block_hash)
+            .ignore()
+            .hset_multiple(route.keys.indexer_heartbeat(), &heartbeat.fields())
+            .ignore();
+    }
+    pipe.query_async(con).await
+}
+
+/// A block's events, ready to be handed to the boards, and the progress to record
+/// with them.
+#[derive(Debug, Clone)]
+pub struct PreparedBlock {
+    pub block: BlockRef,
+    pub events: Vec<(usize, DrawEvent)>,
+    pub heartbeat: IndexerHeartbeat,
+}
+
+impl PreparedBlock {
+    pub fn hash(&self) -> &str {
+        self.block.hash.as_deref().unwrap_or_default()
+    }
+}
+
+/// Check that `block` continues from `last` and extract its events, recording the
+/// block in `stats` and the metrics. `buffered_blocks` are fetched blocks still
+/// waiting behind it.
+///
+/// A discontinuity marks the indexer halted.
+pub fn prepare_block(
+    block: &BlockWithTxHashes,
+    last: Option<&BlockRef>,
+    routes: &[BoardRoute],
+    mode: IndexMode,
+    relayers: &[String],
+    stats: &mut ProcessorStats,
+    buffered_blocks: usize,
+) -> Result<PreparedBlock, Discontinuity> {
+    if let Some(last) = last {
+        if let Err(d) = check_continuity(last, block) {
+            metrics().halted.set(1);
+            tracing::error!("Chain discontinuity, halting: {}", d);
+            return Err(d);
+        }
+    }
+    let before = stats.clone();
+    let events = route_events(block, routes, mode, relayers, stats);
+    metrics().record_stats(&before, stats);
+
+    let heartbeat = IndexerHeartbeat {
+        block_height: block.block.header.height,
+        block_timestamp_ms: block.block.header.timestamp_nanosec / 1_000_000,
+        updated_ms: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        buffered_blocks: buffered_blocks as u64,
+    };
+    Ok(PreparedBlock {
+        block: BlockRef::of(block),
+        events,
+        heartbeat,
+    })
+}
+
+/// Log and record the metrics of a block whose events were handed over.
+pub fn finish_block(block: &PreparedBlock, stats: &ProcessorStats) {
+    let block_height = block.block.height;
+    if !block.events.is_empty() {
+        tracing::info!(
+            "Block {}: pushed {} draw events ({} total pixels)",
+            block_height,
+            block.events.len(),
+            block.events.iter().map(|(_, e)| e.pixels.len()).sum::<usize>()
+        );
+    }
+    metrics().record_block(block_height, block.heartbeat.block_timestamp_ms);
+
+    if stats.blocks.is_multiple_of(1000) {
+        tracing::info!(
+            "Processed {} blocks (latest: {}), {} contract receipts, {} failed skipped, {} parse failures, {} relayed events",
+            stats.blocks,
+            block_height,
+            stats.contract_receipts,
+            stats.failed_receipts_skipped,
+            stats.parse_failures,
+            stats.relayed_events
+        );
+    }
+}
+
+/// Process blocks after `last` until the channel closes or `is_running` is cleared.
+///
+/// Stops at the first block that does not continue the chain from the previous one,
+/// or when the Valkey connection is lost.
+#[allow(clippy::too_many_arguments)]
 pub async fn process_blocks(
     mut blocks_rx: mpsc::Receiver<BlockWithTxHashes>,
     mut con: redis::aio::MultiplexedConnection,
     is_running: Arc<AtomicBool>,
-    contract_account: &str,
-) {
-    let mut blocks_processed: u64 = 0;
+    routes: &[BoardRoute],
+    mode: IndexMode,
+    relayers: &[String],
+    mut last: Option<BlockRef>,
+) -> Result<(), ProcessError> {
+    let mut stats = ProcessorStats::default();
 
     while is_running.load(Ordering::SeqCst) {
         let block = match blocks_rx.recv().await {
@@ -22,113 +445,191 @@ pub async fn process_blocks(
             None => break,
         };
 
-        let block_height = block.block.header.height;
-        let block_timestamp = block.block.header.timestamp_nanosec;
-        let block_timestamp_ms = block_timestamp / 1_000_000; // Convert to milliseconds
+        let buffered = blocks_rx.len();
+        let prepared =
+            prepare_block(&block, last.as_ref(), routes, mode, relayers, &mut stats, buffered)
+                .map_err(ProcessError::Discontinuity)?;
+        let block_height = prepared.block.height;
 
-        let mut events = Vec::new();
+        // Queue the events and update every board's last processed block and heartbeat
+        let mut attempt = 0;
+        while let Err(e) = commit_block(
+            &mut con,
+            routes,
+            &prepared.events,
+            prepared.hash(),
+            &prepared.heartbeat,
+        )
+        .await
+        {
+            metrics().record_commit_failure();
+            if e.is_unrecoverable_error() || !is_running.load(Ordering::SeqCst) {
+                tracing::error!("Failed to commit block {}, stopping: {}", block_height, e);
+                return Err(ProcessError::Valkey(e));
+            }
+            tracing::error!(
+                "Failed to commit block {} (attempt {}), retrying: {}",
+                block_height,
+                attempt + 1,
+                e
+            );
+            let delay_ms = COMMIT_RETRY_INITIAL_MS
+                .saturating_mul(1 << attempt.min(16))
+                .min(COMMIT_RETRY_MAX_MS);
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            attempt += 1;
+        }
+        finish_block(&prepared, &stats);
+        last = Some(prepared.block);
+    }
+    Ok(())
+}
 
-        // Iterate through shards and receipt execution outcomes (maintains ordering)
-        for shard in &block.shards {
-            for outcome in &shard.receipt_execution_outcomes {
-                let receipt = &outcome.receipt;
+/// Whether a receipt's execution outcome succeeded.
+fn is_success(status: &ExecutionStatusView) -> bool {
+    matches!(
+        status,
+        ExecutionStatusView::SuccessValue(_) | ExecutionStatusView::SuccessReceiptId(_)
+    )
+}
 
-                // Filter: only receipts to our contract
-                if receipt.receiver_id.as_str() != contract_account {
-                    continue;
-                }
+/// The original signer of a call forwarded by a trusted relayer, if it was one.
+fn relayed_signer<'a>(
+    receipt: &'a ReceiptEnumView,
+    predecessor_id: &str,
+    relayers: &[String],
+) -> Option<&'a str> {
+    match receipt {
+        ReceiptEnumView::Action { signer_id, .. }
+            if relayers.iter().any(|r| r == predecessor_id) =>
+        {
+            Some(signer_id.as_str())
+        }
+        _ => None,
+    }
+}
 
-                // Extract predecessor_id and actions from the receipt
-                let actions = match &receipt.receipt {
-                    ReceiptEnumView::Action {
-                        actions, ..
-                    } => actions,
-                    _ => continue,
-                };
+/// Parse the contract's `draw` event logs into draw events.
+///
+/// The contract logs its predecessor as `account_id`; that is replaced by `author`
+/// when the call was relayed.
+fn events_from_logs(
+    logs: &[String],
+    predecessor_id: &str,
+    author: &str,
+    block_height: u64,
+    block_timestamp_ms: u64,
+    stats: &mut ProcessorStats,
+) -> Vec<DrawEvent> {
+    let mut events = Vec::new();
 
-                let predecessor_id = receipt.predecessor_id.to_string();
-
-                // Find "draw" function calls
-                for action in actions {
-                    if let ActionView::FunctionCall {
-                        method_name, args, ..
-                    } = action
-                    {
-                        if method_name != "draw" {
-                            continue;
-                        }
-
-                        // args is FunctionArgs which derefs to Vec<u8> (raw JSON bytes)
-                        match serde_json::from_slice::<DrawArgs>(&args) {
-                            Ok(draw_args) => {
-                                // Validate pixels have valid hex colors
-                                let valid_pixels: Vec<_> = draw_args
-                                    .pixels
-                                    .into_iter()
-                                    .filter(|p| p.rgb().is_some())
-                                    .collect();
-
-                                if !valid_pixels.is_empty() {
-                                    events.push(DrawEvent {
-                                        predecessor_id: predecessor_id.clone(),
-                                        block_height,
-                                        block_timestamp_ms,
-                                        pixels: valid_pixels,
-                                    });
-                                }
-                            }
-                            Err(e) => {
-                                tracing::warn!(
-                                    "Failed to parse draw args at block {}: {}",
-                                    block_height,
-                                    e
-                                );
-                            }
-                        }
-                    }
-                }
+    for log in logs {
+        let Some(json) = log.strip_prefix(EVENT_LOG_PREFIX) else {
+            continue;
+        };
+
+        let event_log = match serde_json::from_str::<DrawEventLog>(json) {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::warn!("Failed to parse event log at block {}: {}", block_height, e);
+                stats.parse_failures += 1;
+                continue;
             }
+        };
+        if event_log.standard != DRAW_EVENT_STANDARD || event_log.event != DRAW_EVENT_NAME {
+            continue;
         }
 
-        // Push events to Valkey queue
-        if !events.is_empty() {
-            let serialized: Vec<String> = events
-                .iter()
-                .map(|e| serde_json::to_string(e).unwrap())
+        for data in event_log.data {
+            // The contract already validated colors; filter anyway to match the args path
+            let valid_pixels: Vec<_> = data
+                .pixels
+                .into_iter()
+                .filter(|p| p.rgb().is_some())
                 .collect();
 
-            for event_json in &serialized {
-                let _: () = con
-                    .lpush(valkey::DRAW_QUEUE, event_json)
-                    .await
-                    .unwrap_or_else(|e| {
-                        tracing::error!("Failed to LPUSH draw event: {}", e);
-                    });
+            if !valid_pixels.is_empty() {
+                let account_id = if data.account_id == predecessor_id {
+                    author.to_string()
+                } else {
+                    data.account_id
+                };
+                events.push(DrawEvent {
+                    predecessor_id: account_id,
+                    block_height,
+                    block_timestamp_ms,
+                    pixels: valid_pixels,
+                    deposit: data.deposit,
+                    event_index: None,
+                });
             }
-
-            tracing::info!(
-                "Block {}: pushed {} draw events ({} total pixels)",
-                block_height,
-                events.len(),
-                events.iter().map(|e| e.pixels.len()).sum::<usize>()
-            );
         }
+    }
 
-        // Update last processed block
-        let _: () = con
-            .set(valkey::LAST_PROCESSED_BLOCK, block_height)
-            .await
-            .unwrap_or_else(|e| {
-                tracing::error!("Failed to update last_processed_block: {}", e);
-            });
+    events
+}
 
-        blocks_processed += 1;
-        if blocks_processed % 1000 == 0 {
-            tracing::info!(
-                "Processed {} blocks (latest: {})",
-                blocks_processed,
-                block_height
-            );
+/// Parse `draw` FunctionCall args of a receipt into draw events credited to `author`.
+fn events_from_args(
+    receipt: &ReceiptEnumView,
+    author: &str,
+    block_height: u64,
+    block_timestamp_ms: u64,
+    stats: &mut ProcessorStats,
+) -> Vec<DrawEvent> {
+    let mut events = Vec::new();
+
+    // Extract actions from the receipt
+    let actions = match receipt {
+        ReceiptEnumView::Action { actions, .. } => actions,
+        _ => return events,
+    };
+
+    // Find "draw" function calls
+    for action in actions {
+        if let ActionView::FunctionCall {
+            method_name,
+            args,
+            deposit,
+            ..
+        } = action
+        {
+            if method_name != "draw" {
+                continue;
+            }
+
+            // args is FunctionArgs which derefs to Vec<u8> (raw JSON bytes)
+            match serde_json::from_slice::<DrawArgs>(args) {
+                Ok(draw_args) => {
+                    // Validate pixels have valid hex colors
+                    let valid_pixels: Vec<_> = draw_args
+                        .pixels
+                        .into_iter()
+                        .filter(|p| p.rgb().is_some())
+                        .collect();
+
+                    if !valid_pixels.is_empty() {
+                        events.push(DrawEvent {
+                            predecessor_id: author.to_string(),
+                            block_height,
+                            block_timestamp_ms,
+                            pixels: valid_pixels,
+                            deposit: deposit.as_yoctonear(),
+                            event_index: None,
+                        });
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to parse draw args at block {}: {}",
+                        block_height,
+                        e
+                    );
+                    stats.parse_failures += 1;
+                }
+            }
         }
     }
+
+    events
 }
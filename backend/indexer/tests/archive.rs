@@ -0,0 +1,99 @@
+//! The offline block source: `tests/fixtures/archive` holds the fixture blocks linked
+//! into a chain, as the indexer would fetch them.
+
+use common::valkey::Keyspace;
+use common::DrawEvent;
+use fake_valkey::FakeValkey;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indexer::archive::{load_blocks, start_archive_reader};
+use indexer::processor::{last_processed, process_blocks, BoardRoute, IndexMode};
+use redis::AsyncCommands;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+const HEIGHTS: [u64; 3] = [140_000_000, 140_000_100, 140_000_200];
+
+fn archive_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/archive")
+}
+
+fn heights(path: &Path, start_block: Option<u64>) -> Vec<u64> {
+    let blocks = load_blocks(path, start_block).unwrap();
+    blocks.iter().map(|b| b.block.header.height).collect()
+}
+
+#[test]
+fn a_directory_archive_is_read_in_height_order() {
+    assert_eq!(heights(&archive_dir(), None), HEIGHTS);
+    assert_eq!(heights(&archive_dir(), Some(140_000_001)), HEIGHTS[1..]);
+    assert!(load_blocks(&archive_dir().join("140000000.json"), None).is_err());
+}
+
+#[test]
+fn a_tarball_of_compressed_and_plain_blocks_is_read_in_height_order() {
+    let path = std::env::temp_dir().join(format!("indexer-archive-{}.tar.gz", std::process::id()));
+    let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    // Names that sort out of height order; one block compressed on its own
+    let files = [("c.json", HEIGHTS[0]), ("b.json.gz", HEIGHTS[1]), ("a.json", HEIGHTS[2])];
+    for (name, height) in files {
+        let mut json = std::fs::read(archive_dir().join(format!("{height}.json"))).unwrap();
+        if name.ends_with(".gz") {
+            let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+            gz.write_all(&json).unwrap();
+            json = gz.finish().unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, format!("blocks/{name}"), json.as_slice()).unwrap();
+    }
+    std::fs::write(&path, tar.into_inner().unwrap().finish().unwrap()).unwrap();
+
+    let loaded = heights(&path, None);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, HEIGHTS);
+}
+
+#[tokio::test]
+async fn an_archive_is_indexed_offline_until_it_runs_out() {
+    let routes = vec![BoardRoute {
+        contract_ids: vec!["berryfast.near".into()],
+        keys: Keyspace::default(),
+    }];
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+
+    let is_running = Arc::new(AtomicBool::new(true));
+    let (blocks_tx, blocks_rx) = mpsc::channel(1);
+    let blocks = load_blocks(&archive_dir(), None).unwrap();
+    let reader = tokio::spawn(start_archive_reader(blocks, blocks_tx, is_running.clone()));
+    process_blocks(
+        blocks_rx,
+        valkey.connect().await,
+        is_running,
+        &routes,
+        IndexMode::Events,
+        &[],
+        None,
+    )
+    .await
+    .unwrap();
+    reader.await.unwrap();
+
+    let last = last_processed(&mut con, &routes).await.unwrap().unwrap();
+    assert_eq!(last.height, HEIGHTS[2]);
+    let queued: Vec<String> = con.lrange(routes[0].keys.draw_queue(), 0, -1).await.unwrap();
+    let mut queued: Vec<DrawEvent> =
+        queued.iter().map(|e| serde_json::from_str(e).unwrap()).collect();
+    queued.reverse();
+    let positions: Vec<_> = queued.iter().map(|e| e.position().unwrap()).collect();
+    let (first, second, third) = (HEIGHTS[0], HEIGHTS[1], HEIGHTS[2]);
+    assert_eq!(
+        positions,
+        vec![(first, 0), (second, 0), (third, 0), (third, 1), (third, 2)]
+    );
+}
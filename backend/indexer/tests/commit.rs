@@ -0,0 +1,111 @@
+//! A block's events and progress are committed together: a crash at any step leaves
+//! the block either fully queued or untouched, and reprocessing it after a restart
+//! queues every event exactly once.
+
+use common::valkey::Keyspace;
+use common::{DrawEvent, IndexerHeartbeat};
+use fake_valkey::FakeValkey;
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use indexer::processor::{
+    commit_block, last_processed, route_events, BlockRef, BoardRoute, IndexMode,
+    ProcessorStats,
+};
+use redis::AsyncCommands;
+
+fn load_fixture(name: &str) -> BlockWithTxHashes {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+    serde_json::from_str(&json).unwrap_or_else(|e| panic!("{path}: {e}"))
+}
+
+const HASH: &str = "11111111111111111111111111111111";
+
+fn routes() -> Vec<BoardRoute> {
+    vec![
+        BoardRoute {
+            contract_ids: vec!["berryfast.near".into()],
+            keys: Keyspace::default(),
+        },
+        BoardRoute {
+            contract_ids: vec!["other.near".into()],
+            keys: Keyspace::default().for_board("other"),
+        },
+    ]
+}
+
+fn heartbeat(block: &BlockWithTxHashes) -> IndexerHeartbeat {
+    IndexerHeartbeat {
+        block_height: block.block.header.height,
+        block_timestamp_ms: block.block.header.timestamp_nanosec / 1_000_000,
+        updated_ms: 1,
+        buffered_blocks: 0,
+    }
+}
+
+async fn queued(
+    con: &mut redis::aio::MultiplexedConnection,
+    route: &BoardRoute,
+) -> Vec<DrawEvent> {
+    let events: Vec<String> = con.lrange(route.keys.draw_queue(), 0, -1).await.unwrap();
+    events.iter().map(|e| serde_json::from_str(e).unwrap()).collect()
+}
+
+#[tokio::test]
+async fn a_crash_at_any_step_commits_nothing() {
+    let block = load_fixture("block_mixed_outcomes.json");
+    let routes = routes();
+    let events = route_events(&block, &routes, IndexMode::Args, &[], &mut Default::default());
+    assert_eq!(events.len(), 2);
+
+    for step in ["MULTI", "LPUSH", "SET", "HMSET", "EXEC"] {
+        let valkey = FakeValkey::start().await;
+        let mut con = valkey.connect().await;
+        valkey.disconnect_on(step);
+        let result = commit_block(&mut con, &routes, &events, HASH, &heartbeat(&block)).await;
+        assert!(result.is_err(), "{step}");
+
+        // After the restart nothing of the block is visible, so it is processed again
+        let mut con = valkey.connect().await;
+        assert_eq!(last_processed(&mut con, &routes).await.unwrap(), None, "{step}");
+        for route in &routes {
+            assert!(queued(&mut con, route).await.is_empty(), "{step}");
+            let heartbeat = IndexerHeartbeat::read(&mut con, &route.keys).await.unwrap();
+            assert_eq!(heartbeat, None, "{step}");
+        }
+
+        commit_block(&mut con, &routes, &events, HASH, &heartbeat(&block)).await.unwrap();
+        let height = block.block.header.height;
+        let last = last_processed(&mut con, &routes).await.unwrap();
+        assert_eq!(last, Some(BlockRef { height, hash: Some(HASH.into()) }));
+        for (route, author) in routes.iter().zip(["alice.near", "dave.near"]) {
+            let events = queued(&mut con, route).await;
+            assert_eq!(events.len(), 1, "{step}");
+            assert_eq!(events[0].predecessor_id, author);
+            assert_eq!(events[0].position(), Some((height, 0)));
+        }
+    }
+}
+
+#[tokio::test]
+async fn a_block_is_queued_in_event_order_and_numbered_per_board() {
+    let block = load_fixture("block_relayed_draws.json");
+    let routes = routes();
+    let mut stats = ProcessorStats::default();
+    let events = route_events(&block, &routes, IndexMode::Events, &[], &mut stats);
+
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    commit_block(&mut con, &routes, &events, HASH, &heartbeat(&block)).await.unwrap();
+
+    // The consumer pops from the tail
+    let mut queue = queued(&mut con, &routes[0]).await;
+    queue.reverse();
+    let positions: Vec<_> = queue.iter().map(|e| e.position().unwrap().1).collect();
+    assert_eq!(positions, vec![0, 1, 2]);
+    let authors: Vec<_> = queue.iter().map(|e| e.predecessor_id.as_str()).collect();
+    assert_eq!(authors, vec!["alice.near", "relay.berryfast.near", "proxy.near"]);
+
+    // A board without events in the block still records its progress
+    let last: Option<u64> = con.get(routes[1].keys.last_processed_block()).await.unwrap();
+    assert_eq!(last, Some(block.block.header.height));
+}
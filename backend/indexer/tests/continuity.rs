@@ -0,0 +1,109 @@
+//! Each block must build on the last processed one. Anything else halts the indexer
+//! before the block's events are queued.
+
+use common::valkey::Keyspace;
+use fake_valkey::FakeValkey;
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_primitives::hash::CryptoHash;
+use indexer::processor::{
+    check_continuity, last_processed, process_blocks, BlockRef, BoardRoute, Discontinuity,
+    IndexMode, ProcessError,
+};
+use redis::AsyncCommands;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+fn load_fixture(name: &str) -> BlockWithTxHashes {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+    serde_json::from_str(&json).unwrap_or_else(|e| panic!("{path}: {e}"))
+}
+
+/// A fixture block given its own hash and linked to `parent`.
+fn block(name: &str, hash: u8, parent: &BlockWithTxHashes) -> BlockWithTxHashes {
+    let mut block = load_fixture(name);
+    block.block.header.hash = CryptoHash([hash; 32]);
+    block.block.header.prev_hash = parent.block.header.hash;
+    block.block.header.prev_height = Some(parent.block.header.height);
+    block
+}
+
+fn chain() -> [BlockWithTxHashes; 3] {
+    let mut first = load_fixture("block_mixed_outcomes.json");
+    first.block.header.hash = CryptoHash([1; 32]);
+    let second = block("block_paid_draw.json", 2, &first);
+    let third = block("block_relayed_draws.json", 3, &second);
+    [first, second, third]
+}
+
+#[test]
+fn a_block_must_build_on_the_last_processed_block() {
+    let [first, second, third] = chain();
+    let last = BlockRef::of(&first);
+
+    // Heights the chain skipped are fine when the parent links up
+    assert_eq!(check_continuity(&last, &second), Ok(()));
+
+    assert_eq!(
+        check_continuity(&last, &first),
+        Err(Discontinuity::NotAfter { last: 140_000_000, height: 140_000_000 })
+    );
+    assert_eq!(
+        check_continuity(&last, &third),
+        Err(Discontinuity::Skipped {
+            last: 140_000_000,
+            prev_height: 140_000_100,
+            height: 140_000_200
+        })
+    );
+
+    let mut forked = second;
+    forked.block.header.prev_hash = CryptoHash([9; 32]);
+    assert!(matches!(
+        check_continuity(&last, &forked),
+        Err(Discontinuity::HashMismatch { height: 140_000_100, .. })
+    ));
+
+    // Progress recorded before hashes were can only be checked by height
+    let legacy = BlockRef { height: last.height, hash: None };
+    assert_eq!(check_continuity(&legacy, &forked), Ok(()));
+}
+
+#[tokio::test]
+async fn a_forked_block_halts_indexing_without_queueing_its_events() {
+    let [first, second, mut third] = chain();
+    third.block.header.prev_hash = CryptoHash([9; 32]);
+    let routes = vec![BoardRoute {
+        contract_ids: vec!["berryfast.near".into()],
+        keys: Keyspace::default(),
+    }];
+
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let expected = BlockRef::of(&second);
+    let (blocks_tx, blocks_rx) = mpsc::channel(3);
+    for block in [first, second, third] {
+        blocks_tx.send(block).await.unwrap();
+    }
+    let result = process_blocks(
+        blocks_rx,
+        valkey.connect().await,
+        Arc::new(AtomicBool::new(true)),
+        &routes,
+        IndexMode::Args,
+        &[],
+        None,
+    )
+    .await;
+
+    assert!(matches!(
+        result,
+        Err(ProcessError::Discontinuity(Discontinuity::HashMismatch { height: 140_000_200, .. }))
+    ));
+    let last = last_processed(&mut con, &routes).await.unwrap();
+    assert_eq!(last, Some(expected));
+    // Only the draws of the two good blocks were queued
+    let queued: Vec<String> = con.lrange(routes[0].keys.draw_queue(), 0, -1).await.unwrap();
+    assert!(queued.iter().all(|e| !e.contains("\"block_height\":140000200")));
+}
@@ -0,0 +1,32 @@
+use indexer::metrics::metrics;
+use indexer::processor::ProcessorStats;
+
+#[test]
+fn processor_stats_are_exported_under_stable_names() {
+    let before = ProcessorStats::default();
+    let after = ProcessorStats {
+        blocks: 2,
+        contract_receipts: 3,
+        failed_receipts_skipped: 1,
+        parse_failures: 1,
+        events: 2,
+        relayed_events: 1,
+    };
+    metrics().record_stats(&before, &after);
+    metrics().record_block(120, 0);
+
+    let text = metrics().render();
+    for line in [
+        "indexer_blocks_total 2",
+        "indexer_draw_receipts_total 3",
+        "indexer_failed_receipts_total 1",
+        "indexer_draw_events_total 2",
+        "indexer_relayed_events_total 1",
+        "indexer_parse_failures_total 1",
+        "indexer_commit_failures_total 0",
+        "indexer_lpush_failures_total 0",
+        "indexer_last_block_height 120",
+    ] {
+        assert!(text.lines().any(|l| l == line), "missing {line:?} in\n{text}");
+    }
+}
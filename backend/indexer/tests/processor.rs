@@ -0,0 +1,165 @@
+//! Fixture blocks are in neardata's `BlockWithTxHashes` JSON format, trimmed to the
+//! receipts that matter for each case.
+
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use common::valkey::Keyspace;
+use indexer::processor::{extract_events, route_events, BoardRoute, IndexMode, ProcessorStats};
+
+const CONTRACT: &[&str] = &["berryfast.near"];
+
+fn contracts(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+fn load_fixture(name: &str) -> BlockWithTxHashes {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+    serde_json::from_str(&json).unwrap_or_else(|e| panic!("{path}: {e}"))
+}
+
+#[test]
+fn failed_receipts_are_skipped_in_every_mode() {
+    let block = load_fixture("block_mixed_outcomes.json");
+
+    for mode in [IndexMode::Events, IndexMode::Args] {
+        let mut stats = ProcessorStats::default();
+        let events = extract_events(&block, &contracts(CONTRACT), mode, &[], &mut stats);
+
+        assert_eq!(events.len(), 1, "{mode:?}");
+        assert_eq!(events[0].predecessor_id, "alice.near");
+        assert_eq!(events[0].block_height, 140_000_000);
+        assert_eq!(events[0].block_timestamp_ms, 1_735_689_600_000);
+        let pixels: Vec<_> = events[0]
+            .pixels
+            .iter()
+            .map(|p| (p.x, p.y, p.color.as_str()))
+            .collect();
+        assert_eq!(pixels, vec![(1, 2, "FF5733"), (3, 4, "00FF00")]);
+
+        assert_eq!(stats.contract_receipts, 3, "{mode:?}");
+        assert_eq!(stats.failed_receipts_skipped, 2, "{mode:?}");
+        assert_eq!(stats.parse_failures, 0, "{mode:?}");
+        assert_eq!(stats.blocks, 1);
+    }
+}
+
+#[test]
+fn other_contracts_are_ignored() {
+    let block = load_fixture("block_mixed_outcomes.json");
+    let mut stats = ProcessorStats::default();
+
+    let ids = contracts(&["other.near"]);
+    let events = extract_events(&block, &ids, IndexMode::Args, &[], &mut stats);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].predecessor_id, "dave.near");
+    assert_eq!(stats.contract_receipts, 1);
+    assert_eq!(stats.failed_receipts_skipped, 0);
+}
+
+#[test]
+fn deposit_is_carried_on_draw_events() {
+    let block = load_fixture("block_paid_draw.json");
+
+    for mode in [IndexMode::Events, IndexMode::Args] {
+        let mut stats = ProcessorStats::default();
+        let events = extract_events(&block, &contracts(CONTRACT), mode, &[], &mut stats);
+
+        // The underpaid call failed on-chain and is dropped
+        assert_eq!(events.len(), 1, "{mode:?}");
+        assert_eq!(events[0].predecessor_id, "erin.near");
+        assert_eq!(events[0].deposit, 300_000_000_000_000_000_000, "{mode:?}");
+        assert_eq!(events[0].pixels.len(), 3);
+        assert_eq!(stats.failed_receipts_skipped, 1);
+    }
+}
+
+#[test]
+fn deposit_round_trips_through_the_queue_format() {
+    let block = load_fixture("block_paid_draw.json");
+    let mut stats = ProcessorStats::default();
+    let event =
+        extract_events(&block, &contracts(CONTRACT), IndexMode::Events, &[], &mut stats).remove(0);
+
+    let json = serde_json::to_string(&event).unwrap();
+    assert!(json.contains(r#""deposit":"300000000000000000000""#), "{json}");
+    let parsed: common::DrawEvent = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.deposit, event.deposit);
+
+    // Events queued before paid drawing have no deposit field
+    let legacy = r#"{"predecessor_id":"a.near","block_height":1,"block_timestamp_ms":1,"pixels":[]}"#;
+    assert_eq!(serde_json::from_str::<common::DrawEvent>(legacy).unwrap().deposit, 0);
+}
+
+#[test]
+fn relayed_draws_are_credited_to_the_signer() {
+    let block = load_fixture("block_relayed_draws.json");
+    let relayers = vec!["relay.berryfast.near".to_string()];
+
+    for mode in [IndexMode::Events, IndexMode::Args] {
+        let mut stats = ProcessorStats::default();
+        let events = extract_events(&block, &contracts(CONTRACT), mode, &relayers, &mut stats);
+
+        let authors: Vec<_> = events.iter().map(|e| e.predecessor_id.as_str()).collect();
+        // NEP-366 inner receipt, trusted relay contract, untrusted proxy
+        assert_eq!(authors, vec!["alice.near", "bob.near", "proxy.near"], "{mode:?}");
+        assert_eq!(events[1].pixels[0].color, "00FF00");
+        assert_eq!(stats.relayed_events, 1, "{mode:?}");
+        // The delegate action itself went to alice.near, not the contract
+        assert_eq!(stats.contract_receipts, 3, "{mode:?}");
+    }
+}
+
+#[test]
+fn relayers_are_not_trusted_by_default() {
+    let block = load_fixture("block_relayed_draws.json");
+    let mut stats = ProcessorStats::default();
+
+    let events = extract_events(&block, &contracts(CONTRACT), IndexMode::Events, &[], &mut stats);
+
+    assert_eq!(events[1].predecessor_id, "relay.berryfast.near");
+    assert_eq!(stats.relayed_events, 0);
+}
+
+#[test]
+fn every_configured_contract_is_indexed() {
+    let block = load_fixture("block_mixed_outcomes.json");
+    let mut stats = ProcessorStats::default();
+
+    let ids = contracts(&["berryfast.near", "other.near"]);
+    let events = extract_events(&block, &ids, IndexMode::Args, &[], &mut stats);
+
+    let authors: Vec<_> = events.iter().map(|e| e.predecessor_id.as_str()).collect();
+    assert_eq!(authors, vec!["alice.near", "dave.near"]);
+    assert_eq!(stats.contract_receipts, 4);
+}
+
+#[test]
+fn receipts_are_routed_to_their_contract_board() {
+    let block = load_fixture("block_mixed_outcomes.json");
+    let mut stats = ProcessorStats::default();
+    let routes = vec![
+        BoardRoute {
+            contract_ids: contracts(CONTRACT),
+            keys: Keyspace::default(),
+        },
+        BoardRoute {
+            contract_ids: contracts(&["other.near"]),
+            keys: Keyspace::default().for_board("event"),
+        },
+    ];
+
+    let events = route_events(&block, &routes, IndexMode::Args, &[], &mut stats);
+
+    let routed: Vec<_> = events
+        .iter()
+        .map(|(route, e)| (routes[*route].keys.draw_queue(), e.predecessor_id.as_str()))
+        .collect();
+    assert_eq!(
+        routed,
+        vec![
+            ("draw_queue".to_string(), "alice.near"),
+            ("board:event:draw_queue".to_string(), "dave.near"),
+        ]
+    );
+}
@@ -1,64 +1,220 @@
-use axum::extract::{Path, Query, State, WebSocketUpgrade};
+use axum::extract::{FromRequestParts, Path, Query, WebSocketUpgrade};
 use axum::http::header;
-use axum::response::IntoResponse;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::Router;
-use redis::AsyncCommands;
+use common::valkey::{Keyspace, DEFAULT_BOARD};
+use common::BoardRules;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 
-use crate::board::Board;
+use crate::board::{load_region, Board};
+use crate::consumer::ConsumerStatus;
+use crate::health::{self, HealthThresholds};
+use crate::metrics::metrics;
+use crate::storage::{BoardStore, LeaderboardWindow};
 use crate::ws;
 
+/// Top-level `/api` path segments, which a board id must not shadow.
+pub const RESERVED_BOARD_IDS: &[&str] = &[
+    "region",
+    "regions",
+    "stats",
+    "map",
+    "account",
+    "leaderboard",
+    "open-regions",
+    "rules",
+    "health",
+    "season",
+    "seasons",
+];
+
+/// One hosted board: its keys, region cache and live event channel.
+#[derive(Clone)]
+pub struct BoardHandle {
+    pub keys: Keyspace,
+    pub rules: BoardRules,
+    /// The board's current season, advanced by the consumer.
+    pub season: Arc<AtomicU32>,
+    pub board: Arc<RwLock<Board>>,
+    /// Where the board's state is read from; `board` writes through the same store.
+    pub store: Arc<dyn BoardStore>,
+    pub broadcast_tx: broadcast::Sender<String>,
+    pub consumer: Arc<ConsumerStatus>,
+}
+
 #[derive(Clone)]
 pub struct AppState {
+    /// Hosted boards by id. The un-prefixed routes serve `DEFAULT_BOARD` if hosted.
+    pub boards: Arc<HashMap<String, BoardHandle>>,
+    /// Valkey the boards' indexer queues live in; `None` when every board is fed over
+    /// a channel (see `EventSource`).
+    pub valkey: Option<redis::aio::MultiplexedConnection>,
+    pub health: HealthThresholds,
+}
+
+/// The board a request addresses: the `{board}` path segment, or the default board
+/// for the un-prefixed routes. Unknown boards are rejected with 404.
+///
+/// `keys` address the board's current season, or the `{season}` path segment under
+/// `.../season/{season}/...`; seasons that have not started yet are rejected with 404.
+pub struct BoardCtx {
+    pub keys: Keyspace,
+    pub rules: BoardRules,
     pub board: Arc<RwLock<Board>>,
-    pub valkey: redis::aio::MultiplexedConnection,
+    pub store: Arc<dyn BoardStore>,
     pub broadcast_tx: broadcast::Sender<String>,
+    pub consumer: Arc<ConsumerStatus>,
+    pub valkey: Option<redis::aio::MultiplexedConnection>,
+    pub health: HealthThresholds,
+}
+
+impl FromRequestParts<AppState> for BoardCtx {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let params: Path<HashMap<String, String>> = Path::from_request_parts(parts, state)
+            .await
+            .unwrap_or_else(|_| Path(HashMap::new()));
+        let id = params.get("board").map(String::as_str).unwrap_or(DEFAULT_BOARD);
+        let handle = state.boards.get(id).ok_or(StatusCode::NOT_FOUND)?;
+        let current = handle.season.load(Ordering::Relaxed);
+        let season = match params.get("season") {
+            Some(s) => s
+                .parse()
+                .ok()
+                .filter(|&season| season <= current)
+                .ok_or(StatusCode::NOT_FOUND)?,
+            None => current,
+        };
+        Ok(Self {
+            keys: handle.keys.for_season(season),
+            rules: handle.rules,
+            board: handle.board.clone(),
+            store: handle.store.clone(),
+            broadcast_tx: handle.broadcast_tx.clone(),
+            consumer: handle.consumer.clone(),
+            valkey: state.valkey.clone(),
+            health: state.health,
+        })
+    }
+}
+
+impl BoardCtx {
+    /// Pixels of region (rx, ry) in the addressed season. The live season is served
+    /// from the board's cache; ended seasons are read straight from the store.
+    async fn region(&self, rx: i32, ry: i32) -> crate::Result<Vec<u8>> {
+        let mut board = self.board.write().await;
+        if board.keys() == &self.keys {
+            return board.get_region(rx, ry).await;
+        }
+        let encoding = board.encoding();
+        drop(board);
+        load_region(self.store.as_ref(), &self.keys, encoding, rx, ry).await
+    }
+}
+
+/// Routes served for every board and season: at `/api/...` (default board) and
+/// `/api/{board}/...`, each also under `.../season/{season}/...`.
+fn board_routes() -> Router<AppState> {
+    Router::new()
+        .route("/region/{rx}/{ry}", get(get_region))
+        .route("/region/{rx}/{ry}/meta", get(get_region_meta))
+        .route("/regions", get(get_regions_batch))
+        .route("/stats/accounts", get(get_account_stats))
+        .route("/stats/region/{rx}/{ry}", get(get_region_stats))
+        .route("/region/{rx}/{ry}/timestamps", get(get_region_timestamps))
+        .route("/region/{rx}/{ry}/owners", get(get_region_owners))
+        .route("/map/owners", get(get_owner_map))
+        .route("/account/{owner_id}", get(get_account_by_id))
+        .route("/account/by-name/{account_id}", get(get_account_by_name))
+        .route("/leaderboard", get(get_leaderboard))
+        .route("/leaderboard/rank/{account_id}", get(get_leaderboard_rank))
+        .route("/open-regions", get(get_open_regions))
+        .route("/rules", get(get_rules))
+        .route("/health", get(health::ready))
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready))
 }
 
 pub fn router(state: AppState) -> Router {
     Router::new()
-        .route("/api/region/{rx}/{ry}", get(get_region))
-        .route("/api/region/{rx}/{ry}/meta", get(get_region_meta))
-        .route("/api/regions", get(get_regions_batch))
-        .route("/api/stats/accounts", get(get_account_stats))
-        .route("/api/stats/region/{rx}/{ry}", get(get_region_stats))
-        .route("/api/region/{rx}/{ry}/timestamps", get(get_region_timestamps))
-        .route("/api/account/{owner_id}", get(get_account_by_id))
-        .route("/api/open-regions", get(get_open_regions))
-        .route("/api/health", get(health))
+        .nest("/api", board_routes())
+        .nest("/api/{board}", board_routes())
+        .nest("/api/season/{season}", board_routes())
+        .nest("/api/{board}/season/{season}", board_routes())
+        .route("/api/seasons", get(get_seasons))
+        .route("/api/{board}/seasons", get(get_seasons))
         .route("/ws", get(ws_upgrade))
+        .route("/api/{board}/ws", get(ws_upgrade))
+        .route("/metrics", get(get_metrics))
         .with_state(state)
 }
 
+/// Region coordinates from the path.
+#[derive(Deserialize)]
+struct RegionPath {
+    rx: i32,
+    ry: i32,
+}
+
+#[derive(Deserialize)]
+struct OwnerIdPath {
+    owner_id: u32,
+}
+
+#[derive(Deserialize)]
+struct AccountPath {
+    account_id: String,
+}
+
+#[derive(Deserialize)]
+struct RegionQuery {
+    /// "legacy" (default): headerless 24-bit pixels, as served before containers existed.
+    /// "v1": versioned container in the region's stored encoding.
+    format: Option<String>,
+}
+
 async fn get_region(
-    State(state): State<AppState>,
-    Path((rx, ry)): Path<(i32, i32)>,
-) -> impl IntoResponse {
-    let blob = {
-        let mut board = state.board.write().await;
-        board.get_region(rx, ry).await
+    state: BoardCtx,
+    Path(RegionPath { rx, ry }): Path<RegionPath>,
+    Query(query): Query<RegionQuery>,
+) -> crate::Result<Response> {
+    let format = query.format.as_deref().unwrap_or("legacy");
+    if format != "legacy" && format != "v1" {
+        return Ok(StatusCode::BAD_REQUEST.into_response());
+    }
+
+    let blob = state.region(rx, ry).await?;
+    let blob = match (common::RegionEncoding::detect(&blob), format) {
+        (Some(encoding), "v1") => common::region::encode_region_container(&blob, encoding),
+        // Old clients only understand the 24-bit layout; wide owners become OVERFLOW_OWNER_ID
+        (Some(encoding), _) => common::region::to_legacy_blob(&blob, encoding),
+        (None, _) => blob,
     };
 
     // Get last_updated from metadata
-    let last_updated: Option<u64> = state
-        .valkey
-        .clone()
-        .hget(common::valkey::region_meta_key(rx, ry), "last_updated")
-        .await
-        .unwrap_or(None);
+    let last_updated = state.store.region_updated_ms(&state.keys, rx, ry).await?;
 
     let last_updated_str = last_updated.map(|t| t.to_string()).unwrap_or_default();
 
-    (
+    Ok((
         [
             (header::CONTENT_TYPE, "application/octet-stream".to_string()),
             (
                 header::HeaderName::from_static("x-last-updated"),
                 last_updated_str,
             ),
+            (
+                header::HeaderName::from_static("x-region-format"),
+                format.to_string(),
+            ),
             (
                 header::CACHE_CONTROL,
                 "no-cache, must-revalidate".to_string(),
@@ -66,24 +222,20 @@ async fn get_region(
         ],
         blob,
     )
+        .into_response())
 }
 
 async fn get_region_meta(
-    State(state): State<AppState>,
-    Path((rx, ry)): Path<(i32, i32)>,
-) -> impl IntoResponse {
-    let last_updated: Option<u64> = state
-        .valkey
-        .clone()
-        .hget(common::valkey::region_meta_key(rx, ry), "last_updated")
-        .await
-        .unwrap_or(None);
+    state: BoardCtx,
+    Path(RegionPath { rx, ry }): Path<RegionPath>,
+) -> crate::Result<impl IntoResponse> {
+    let last_updated = state.store.region_updated_ms(&state.keys, rx, ry).await?;
 
-    axum::Json(serde_json::json!({
+    Ok(axum::Json(serde_json::json!({
         "rx": rx,
         "ry": ry,
         "last_updated": last_updated.unwrap_or(0)
-    }))
+    })))
 }
 
 #[derive(Deserialize)]
@@ -92,9 +244,9 @@ struct BatchQuery {
 }
 
 async fn get_regions_batch(
-    State(state): State<AppState>,
+    state: BoardCtx,
     Query(query): Query<BatchQuery>,
-) -> impl IntoResponse {
+) -> crate::Result<impl IntoResponse> {
     let coords: Vec<i32> = query
         .coords
         .split(',')
@@ -102,15 +254,11 @@ async fn get_regions_batch(
         .collect();
 
     let mut results = Vec::new();
-    let mut valkey = state.valkey.clone();
 
     for chunk in coords.chunks(2) {
         if chunk.len() == 2 {
             let (rx, ry) = (chunk[0], chunk[1]);
-            let last_updated: Option<u64> = valkey
-                .hget(common::valkey::region_meta_key(rx, ry), "last_updated")
-                .await
-                .unwrap_or(None);
+            let last_updated = state.store.region_updated_ms(&state.keys, rx, ry).await?;
 
             results.push(serde_json::json!({
                 "rx": rx,
@@ -120,149 +268,294 @@ async fn get_regions_batch(
         }
     }
 
-    axum::Json(results)
+    Ok(axum::Json(results))
 }
 
-async fn health(State(state): State<AppState>) -> impl IntoResponse {
-    let last_block: Option<u64> = state
-        .valkey
-        .clone()
-        .get(common::valkey::LAST_PROCESSED_BLOCK)
-        .await
-        .unwrap_or(None);
+async fn get_account_stats(state: BoardCtx) -> crate::Result<impl IntoResponse> {
+    // Get all owner_id → pixel_count pairs, and the accounts they belong to
+    let counts = state.store.account_pixel_counts(&state.keys).await?;
+    let owner_ids: Vec<u32> = counts.iter().map(|(id, _)| *id).collect();
+    let account_ids = state.store.account_ids(&state.keys, &owner_ids).await?;
 
-    let queue_len: Option<u64> = state
-        .valkey
-        .clone()
-        .llen(common::valkey::DRAW_QUEUE)
-        .await
-        .unwrap_or(None);
+    let results: Vec<serde_json::Value> = counts
+        .into_iter()
+        .zip(account_ids)
+        .filter_map(|((_, count), account_id)| {
+            Some(serde_json::json!({
+                "account_id": account_id?,
+                "pixel_count": count,
+            }))
+        })
+        .collect();
 
-    axum::Json(serde_json::json!({
-        "status": "ok",
-        "last_processed_block": last_block,
-        "queue_length": queue_len.unwrap_or(0)
-    }))
+    Ok(axum::Json(results))
 }
 
-async fn get_account_stats(State(state): State<AppState>) -> impl IntoResponse {
-    let mut valkey = state.valkey.clone();
+/// Default and maximum page size for `/api/leaderboard`.
+const LEADERBOARD_DEFAULT_LIMIT: usize = 50;
+const LEADERBOARD_MAX_LIMIT: usize = 500;
 
-    // Get all owner_id → pixel_count pairs
-    let counts: Vec<(String, i64)> = valkey
-        .hgetall(common::valkey::ACCOUNT_PIXEL_COUNT)
-        .await
-        .unwrap_or_default();
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+    /// Optional time window: "24h" or "7d". Omit for the all-time pixel count board.
+    window: Option<String>,
+}
 
-    // Get all id → account_id mappings
-    let id_to_account: Vec<(String, String)> = valkey
-        .hgetall(common::valkey::ID_TO_ACCOUNT)
-        .await
-        .unwrap_or_default();
+async fn get_leaderboard(
+    state: BoardCtx,
+    Query(query): Query<LeaderboardQuery>,
+) -> crate::Result<Response> {
+    let offset = query.offset.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(LEADERBOARD_DEFAULT_LIMIT)
+        .clamp(1, LEADERBOARD_MAX_LIMIT);
+
+    let Some(window) = LeaderboardWindow::from_name(query.window.as_deref()) else {
+        return Ok(StatusCode::BAD_REQUEST.into_response());
+    };
+    // Every rank on the page must fit in an isize, the range Valkey accepts
+    if offset.checked_add(limit).is_none_or(|end| end > isize::MAX as usize) {
+        return Ok(StatusCode::BAD_REQUEST.into_response());
+    }
 
-    let account_map: std::collections::HashMap<String, String> =
-        id_to_account.into_iter().collect();
+    let (entries, total) = state
+        .store
+        .leaderboard(&state.keys, window, unix_ms(), offset, limit)
+        .await?;
 
-    let results: Vec<serde_json::Value> = counts
-        .into_iter()
-        .filter_map(|(owner_id, count)| {
-            let account_id = account_map.get(&owner_id)?;
+    let owner_ids: Vec<u32> = entries.iter().map(|(id, _)| *id).collect();
+    let account_ids = state.store.account_ids(&state.keys, &owner_ids).await?;
+
+    let results: Vec<serde_json::Value> = entries
+        .iter()
+        .zip(account_ids)
+        .enumerate()
+        .filter_map(|(i, ((owner_id, count), account_id))| {
             Some(serde_json::json!({
-                "account_id": account_id,
+                "rank": offset + i + 1,
+                "account_id": account_id?,
+                "owner_id": owner_id,
                 "pixel_count": count,
             }))
         })
         .collect();
 
-    axum::Json(results)
+    Ok(axum::Json(serde_json::json!({
+        "offset": offset,
+        "limit": limit,
+        "total": total,
+        "entries": results,
+    }))
+    .into_response())
+}
+
+#[derive(Deserialize)]
+struct RankQuery {
+    window: Option<String>,
+}
+
+async fn get_leaderboard_rank(
+    state: BoardCtx,
+    Path(AccountPath { account_id }): Path<AccountPath>,
+    Query(query): Query<RankQuery>,
+) -> crate::Result<Response> {
+    let owner_id = match state.store.owner_id(&state.keys, &account_id).await? {
+        Some(id) => id,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+
+    let Some(window) = LeaderboardWindow::from_name(query.window.as_deref()) else {
+        return Ok(StatusCode::BAD_REQUEST.into_response());
+    };
+
+    let (rank, score) = state
+        .store
+        .leaderboard_rank(&state.keys, window, unix_ms(), owner_id)
+        .await?;
+
+    Ok(axum::Json(serde_json::json!({
+        "account_id": account_id,
+        "owner_id": owner_id,
+        "rank": rank.map(|r| r + 1),
+        "pixel_count": score.unwrap_or(0),
+    }))
+    .into_response())
 }
 
 async fn get_region_stats(
-    State(state): State<AppState>,
-    Path((rx, ry)): Path<(i32, i32)>,
-) -> impl IntoResponse {
-    let count: i64 = state
-        .valkey
-        .clone()
-        .hget(common::valkey::REGION_PIXEL_COUNT, format!("{rx}:{ry}"))
-        .await
-        .unwrap_or(0);
+    state: BoardCtx,
+    Path(RegionPath { rx, ry }): Path<RegionPath>,
+) -> crate::Result<impl IntoResponse> {
+    let count = state.store.region_pixel_count(&state.keys, rx, ry).await?;
 
-    axum::Json(serde_json::json!({ "count": count }))
+    Ok(axum::Json(serde_json::json!({ "count": count })))
 }
 
-async fn get_open_regions(State(state): State<AppState>) -> impl IntoResponse {
-    let members: Vec<String> = state
-        .valkey
-        .clone()
-        .smembers(common::valkey::OPEN_REGIONS)
-        .await
-        .unwrap_or_default();
+/// Default and maximum number of accounts returned by `/api/region/{rx}/{ry}/owners`.
+const REGION_OWNERS_DEFAULT_LIMIT: usize = 10;
+const REGION_OWNERS_MAX_LIMIT: usize = 100;
 
-    let regions: Vec<serde_json::Value> = members
+#[derive(Deserialize)]
+struct RegionOwnersQuery {
+    limit: Option<usize>,
+}
+
+async fn get_region_owners(
+    state: BoardCtx,
+    Path(RegionPath { rx, ry }): Path<RegionPath>,
+    Query(query): Query<RegionOwnersQuery>,
+) -> crate::Result<impl IntoResponse> {
+    let limit = query
+        .limit
+        .unwrap_or(REGION_OWNERS_DEFAULT_LIMIT)
+        .clamp(1, REGION_OWNERS_MAX_LIMIT);
+
+    let entries = state.store.region_owners(&state.keys, (rx, ry), limit).await?;
+    let owner_ids: Vec<u32> = entries.iter().map(|(id, _)| *id).collect();
+    let account_ids = state.store.account_ids(&state.keys, &owner_ids).await?;
+
+    let owners: Vec<serde_json::Value> = entries
         .iter()
-        .filter_map(|s| {
-            let parts: Vec<&str> = s.split(':').collect();
-            if parts.len() == 2 {
-                let rx: i32 = parts[0].parse().ok()?;
-                let ry: i32 = parts[1].parse().ok()?;
-                Some(serde_json::json!({ "rx": rx, "ry": ry }))
-            } else {
-                None
-            }
+        .zip(account_ids)
+        .filter_map(|((owner_id, count), account_id)| {
+            Some(serde_json::json!({
+                "account_id": account_id?,
+                "owner_id": owner_id,
+                "pixel_count": count,
+            }))
         })
         .collect();
 
-    axum::Json(regions)
+    Ok(axum::Json(serde_json::json!({
+        "rx": rx,
+        "ry": ry,
+        "owners": owners,
+    })))
 }
 
-async fn get_region_timestamps(
-    State(state): State<AppState>,
-    Path((rx, ry)): Path<(i32, i32)>,
-) -> impl IntoResponse {
-    let key = common::valkey::pixel_ts_key(rx, ry);
-    let now_ms = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as f64;
-    let one_hour_ago_ms = now_ms - 3_600_000.0;
-
-    // Fetch only fresh entries (< 1 hour old); scores are in milliseconds
-    let entries: Vec<(String, f64)> = redis::cmd("ZRANGEBYSCORE")
-        .arg(&key)
-        .arg(one_hour_ago_ms)
-        .arg("+inf")
-        .arg("WITHSCORES")
-        .query_async(&mut state.valkey.clone())
-        .await
-        .unwrap_or_default();
+/// World-level map of each open region's dominant owner and average color.
+async fn get_owner_map(state: BoardCtx) -> crate::Result<impl IntoResponse> {
+    let coords = state.store.open_regions(&state.keys).await?;
+    let summaries = state.store.region_summaries(&state.keys, &coords).await?;
 
-    // Convert to [[lx, ly, ts_ms], ...] for compact transfer
-    let results: Vec<[u64; 3]> = entries
+    let mut owner_ids: Vec<u32> = summaries
+        .iter()
+        .filter_map(|s| s.top_owner.map(|(id, _)| id))
+        .collect();
+    owner_ids.sort_unstable();
+    owner_ids.dedup();
+    let account_ids = state.store.account_ids(&state.keys, &owner_ids).await?;
+    let account_map: std::collections::HashMap<u32, String> = owner_ids
+        .into_iter()
+        .zip(account_ids)
+        .filter_map(|(id, account)| Some((id, account?)))
+        .collect();
+
+    let results: Vec<serde_json::Value> = coords
+        .iter()
+        .zip(summaries)
+        .map(|((rx, ry), summary)| {
+            let dominant = summary.top_owner;
+            serde_json::json!({
+                "rx": rx,
+                "ry": ry,
+                "owner_id": dominant.map(|(id, _)| id),
+                "account_id": dominant.and_then(|(id, _)| account_map.get(&id)),
+                "pixel_count": dominant.map(|(_, c)| c).unwrap_or(0),
+                "color": summary.color.map(|(r, g, b)| format!("{r:02X}{g:02X}{b:02X}")),
+            })
+        })
+        .collect();
+
+    Ok(axum::Json(results))
+}
+
+async fn get_open_regions(state: BoardCtx) -> crate::Result<impl IntoResponse> {
+    let regions: Vec<serde_json::Value> = state
+        .store
+        .open_regions(&state.keys)
+        .await?
+        .into_iter()
+        .map(|(rx, ry)| serde_json::json!({ "rx": rx, "ry": ry }))
+        .collect();
+
+    Ok(axum::Json(regions))
+}
+
+async fn get_rules(state: BoardCtx) -> impl IntoResponse {
+    axum::Json(serde_json::json!({
+        "board": state.keys.board(),
+        "ownership_duration_ms": state.rules.ownership_duration_ms,
+        "region_open_threshold": state.rules.region_open_threshold,
+        "max_region_radius": state.rules.max_region_radius,
+        "bounds": state.rules.bounds.map(|b| serde_json::json!({
+            "width": b.width,
+            "height": b.height,
+        })),
+        "seasons": state.rules.seasons.map(|s| serde_json::json!({
+            "end_block_height": s.end_block_height,
+            "end_timestamp_ms": s.end_timestamp_ms,
+            "length_blocks": s.length_blocks,
+            "length_ms": s.length_ms,
+        })),
+        "season": state.keys.season(),
+    }))
+}
+
+/// The board's current season and how each ended season finished.
+async fn get_seasons(state: BoardCtx) -> crate::Result<impl IntoResponse> {
+    let seasons: Vec<serde_json::Value> = state
+        .store
+        .ended_seasons(&state.keys)
+        .await?
         .into_iter()
-        .filter_map(|(member, score)| {
-            let (lx_str, ly_str) = member.split_once(',')?;
-            let lx: u64 = lx_str.parse().ok()?;
-            let ly: u64 = ly_str.parse().ok()?;
-            Some([lx, ly, score as u64])
+        .map(|ended| {
+            let summary = ended.summary;
+            serde_json::json!({
+                "season": ended.season,
+                "ended_timestamp_ms": summary.ended_timestamp_ms,
+                "ended_block_height": summary.ended_block_height,
+                "open_regions": summary.open_regions,
+                "top_owner_id": summary.top_owner_id,
+                "top_pixel_count": summary.top_pixel_count,
+            })
         })
         .collect();
 
-    axum::Json(results)
+    Ok(axum::Json(serde_json::json!({
+        "current": state.keys.season(),
+        "ended": seasons,
+    })))
+}
+
+async fn get_region_timestamps(
+    state: BoardCtx,
+    Path(RegionPath { rx, ry }): Path<RegionPath>,
+) -> crate::Result<impl IntoResponse> {
+    let ownership_cutoff_ms = unix_ms().saturating_sub(state.rules.ownership_duration_ms);
+
+    // Fetch only entries still inside the ownership window; scores are in milliseconds
+    let entries = state
+        .store
+        .pixel_timestamps(&state.keys, (rx, ry), ownership_cutoff_ms)
+        .await?;
+
+    // Convert to [[lx, ly, ts_ms], ...] for compact transfer
+    let results: Vec<[u64; 3]> = entries.into_iter().map(|(lx, ly, ts)| [lx, ly, ts]).collect();
+
+    Ok(axum::Json(results))
 }
 
 async fn get_account_by_id(
-    State(state): State<AppState>,
-    Path(owner_id): Path<u32>,
-) -> impl IntoResponse {
-    let account: Option<String> = state
-        .valkey
-        .clone()
-        .hget(common::valkey::ID_TO_ACCOUNT, owner_id)
-        .await
-        .unwrap_or(None);
-
-    match account {
+    state: BoardCtx,
+    Path(OwnerIdPath { owner_id }): Path<OwnerIdPath>,
+) -> crate::Result<Response> {
+    let account = state.store.account_ids(&state.keys, &[owner_id]).await?.pop().flatten();
+
+    Ok(match account {
         Some(id) => (
             [
                 (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
@@ -270,13 +563,61 @@ async fn get_account_by_id(
             id,
         )
             .into_response(),
-        None => axum::http::StatusCode::NOT_FOUND.into_response(),
-    }
+        None => StatusCode::NOT_FOUND.into_response(),
+    })
+}
+
+async fn get_account_by_name(
+    state: BoardCtx,
+    Path(AccountPath { account_id }): Path<AccountPath>,
+) -> crate::Result<Response> {
+    let owner_id = match state.store.owner_id(&state.keys, &account_id).await? {
+        Some(id) => id,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+
+    let ownership_cutoff_ms = unix_ms().saturating_sub(state.rules.ownership_duration_ms);
+    let stats = state
+        .store
+        .account_stats(&state.keys, owner_id, ownership_cutoff_ms)
+        .await?;
+
+    let regions: Vec<serde_json::Value> = stats
+        .regions
+        .iter()
+        .map(|(rx, ry)| serde_json::json!({ "rx": rx, "ry": ry }))
+        .collect();
+
+    Ok(axum::Json(serde_json::json!({
+        "account_id": account_id,
+        "owner_id": owner_id,
+        "pixel_count": stats.pixel_count,
+        "permanent_pixel_count": (stats.pixel_count - stats.claimable_pixel_count).max(0),
+        "claimable_pixel_count": stats.claimable_pixel_count,
+        "first_draw_ms": stats.first_draw_ms,
+        "last_draw_ms": stats.last_draw_ms,
+        "regions": regions,
+    }))
+    .into_response())
+}
+
+async fn get_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
 }
 
 async fn ws_upgrade(
     ws: WebSocketUpgrade,
-    State(state): State<AppState>,
+    state: BoardCtx,
 ) -> impl IntoResponse {
     ws.on_upgrade(move |socket| ws::handle_socket(socket, state))
 }
+
+fn unix_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
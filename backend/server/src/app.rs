@@ -0,0 +1,117 @@
+//! Startup of a hosted board, shared by the server binary and the integration tests.
+
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::api::BoardHandle;
+use crate::board::Board;
+use crate::config::Config;
+use crate::consumer::{self, ConsumerStatus, EventSource};
+use crate::storage::{BoardStore, ValkeyStore};
+use crate::{indexer_status, migrator};
+
+/// Prepare one board's keys in Valkey and start its consumer and migrator.
+pub async fn start_board(
+    config: &Config,
+    valkey_con: redis::aio::MultiplexedConnection,
+    board_config: &common::BoardConfig,
+) -> anyhow::Result<BoardHandle> {
+    let keys = config.keyspace.for_board(&board_config.id);
+
+    // Detect and repair owner id collisions left by the old non-atomic allocator
+    let report = common::owner_ids::check_and_repair(
+        &mut valkey_con.clone(),
+        &keys,
+        true,
+        config.region_encoding.max_owner_id(),
+    )
+    .await?;
+    if !report.is_clean() {
+        tracing::warn!("Repaired owner id mappings of board {}: {:?}", keys.board(), report);
+    }
+
+    let store: Arc<dyn BoardStore> = Arc::new(ValkeyStore::new(valkey_con.clone()));
+    let source = EventSource::Valkey(valkey_con.clone());
+    let handle = start_board_with(config, store, source, board_config).await?;
+
+    // Rewrite headerless region blobs into the versioned container in the background
+    let migrator_valkey = valkey_con.clone();
+    let migrator_keys = handle.board.read().await.keys().clone();
+    tokio::spawn(async move {
+        migrator::run(migrator_valkey, migrator_keys).await;
+    });
+
+    Ok(handle)
+}
+
+/// Start one board on `store`, consuming draw events from `source`.
+pub async fn start_board_with(
+    config: &Config,
+    store: Arc<dyn BoardStore>,
+    source: EventSource,
+    board_config: &common::BoardConfig,
+) -> anyhow::Result<BoardHandle> {
+    let keys = config.keyspace.for_board(&board_config.id);
+    let rules = board_config.rules;
+    let (broadcast_tx, _) = broadcast::channel::<String>(4096);
+    tracing::info!(
+        "Board {}: pixel price {} yocto, overwrite price {} yocto, charged from block {}",
+        keys.board(),
+        rules.pricing.pixel_price,
+        rules.pricing.overwrite_price,
+        rules.pricing.start_block_height
+    );
+
+    let board = Arc::new(tokio::sync::RwLock::new(
+        Board::new(
+            store.clone(),
+            keys.clone(),
+            config.region_encoding,
+            rules,
+        ),
+    ));
+
+    // Resume the current season (opening its initial regions), then fill gaps in it
+    board.write().await.start().await?;
+    board.write().await.backfill_region_owners().await?;
+    board.write().await.backfill_region_colors().await?;
+    board.write().await.backfill_leaderboard().await?;
+    let season = board.read().await.season_handle();
+
+    // Start consumer task
+    let consumer_board = board.clone();
+    let consumer_store = store.clone();
+    let consumer_keys = keys.clone();
+    let consumer_broadcast = broadcast_tx.clone();
+    let consumer = Arc::new(ConsumerStatus::default());
+    let consumer_status = consumer.clone();
+    tokio::spawn(async move {
+        consumer::run(
+            source,
+            consumer_keys,
+            consumer_board,
+            consumer_store,
+            consumer_broadcast,
+            consumer_status,
+        )
+        .await;
+    });
+
+    // Keep WebSocket clients informed of how far the indexer trails the chain
+    let status_store = store.clone();
+    let status_keys = keys.clone();
+    let status_broadcast = broadcast_tx.clone();
+    tokio::spawn(async move {
+        indexer_status::run(status_store, status_keys, status_broadcast).await;
+    });
+
+    Ok(BoardHandle {
+        keys,
+        rules,
+        season,
+        board,
+        store,
+        broadcast_tx,
+        consumer,
+    })
+}
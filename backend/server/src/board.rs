@@ -1,67 +1,204 @@
 use common::region::*;
-use common::valkey;
-use common::DrawEvent;
+use common::valkey::Keyspace;
+use common::{BoardRules, DrawEvent};
 use lru::LruCache;
-use redis::AsyncCommands;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZero;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
-/// One hour in milliseconds.
-const OWNERSHIP_DURATION_MS: u64 = 3_600_000;
+use crate::metrics::metrics;
+use crate::storage::{Batch, BoardStore, ClaimedPixel, LeaderboardWindow, SeasonSummary};
+use crate::{Error, Result};
+
+/// Two hours in milliseconds (for trimming the WS catch-up sorted set).
+const CATCHUP_RETENTION_MS: u64 = 7_200_000;
+
+/// A pixel waiting to be applied to a region: (lx, ly, r, g, b).
+type PendingPixel = (usize, usize, u8, u8, u8);
 
 pub struct Board {
     /// LRU cache of region blobs keyed by (rx, ry).
     cache: LruCache<(i32, i32), Vec<u8>>,
-    valkey: redis::aio::MultiplexedConnection,
+    store: Arc<dyn BoardStore>,
+    /// Keys of the current season.
+    keys: Keyspace,
+    /// Current season number, shared with the API so it can address the live season.
+    season: Arc<AtomicU32>,
+    /// Encoding for new regions; also bounds owner id allocation.
+    encoding: RegionEncoding,
+    rules: BoardRules,
+    /// Whether owner ids running close to the encoding's limit was already logged.
+    warned_owner_id_limit: bool,
 }
 
 impl Board {
-    pub fn new(valkey: redis::aio::MultiplexedConnection) -> Self {
+    pub fn new(
+        store: Arc<dyn BoardStore>,
+        keys: Keyspace,
+        encoding: RegionEncoding,
+        rules: BoardRules,
+    ) -> Self {
         Self {
             cache: LruCache::new(NonZero::new(256).unwrap()),
-            valkey,
+            store,
+            season: Arc::new(AtomicU32::new(keys.season())),
+            keys,
+            encoding,
+            rules,
+            warned_owner_id_limit: false,
         }
     }
 
-    /// Get or load a region blob. Returns a clone of the data.
-    pub async fn get_region(&mut self, rx: i32, ry: i32) -> Vec<u8> {
-        if let Some(blob) = self.cache.get(&(rx, ry)) {
-            return blob.clone();
+    /// Keys of the current season.
+    pub fn keys(&self) -> &Keyspace {
+        &self.keys
+    }
+
+    /// Encoding new regions are created in.
+    pub fn encoding(&self) -> RegionEncoding {
+        self.encoding
+    }
+
+    /// Shared handle to the current season number.
+    pub fn season_handle(&self) -> Arc<AtomicU32> {
+        self.season.clone()
+    }
+
+    /// Resume the board's current season and make sure its initial regions are open.
+    pub async fn start(&mut self) -> Result<()> {
+        let season = self.store.current_season(&self.keys).await?;
+        self.set_season(season.unwrap_or(0));
+        let mut batch = Batch::new();
+        batch.open_regions(&self.keys, &self.rules.initial_regions());
+        self.store.commit(batch).await
+    }
+
+    fn set_season(&mut self, season: u32) {
+        self.keys = self.keys.for_season(season);
+        self.season.store(season, Ordering::Relaxed);
+        self.cache.clear();
+    }
+
+    /// Freeze the current season and start the next one in a fresh namespace.
+    ///
+    /// The ended season's keys are never written again, so they are its final snapshot;
+    /// `season_meta` records when it ended and who led it. The season only advances
+    /// once all of that is written.
+    async fn end_season(&mut self, block_height: u64, block_timestamp_ms: u64) -> Result<()> {
+        let season = self.keys.season();
+        let (top, _) = self
+            .store
+            .leaderboard(&self.keys, LeaderboardWindow::All, block_timestamp_ms, 0, 1)
+            .await?;
+        let (top_owner_id, top_pixel_count) = top.first().copied().unwrap_or((0, 0));
+        let regions = self.store.open_regions(&self.keys).await?.len();
+
+        let summary = SeasonSummary {
+            ended_block_height: block_height,
+            ended_timestamp_ms: block_timestamp_ms,
+            open_regions: regions as u64,
+            top_owner_id,
+            top_pixel_count: top_pixel_count.max(0) as u64,
+        };
+        let mut batch = Batch::new();
+        batch.end_season(&self.keys, &summary, &self.rules.initial_regions());
+        self.store.commit(batch).await?;
+
+        tracing::info!(
+            "Board {}: season {} ended at block {}, starting season {}",
+            self.keys.board(),
+            season,
+            block_height,
+            season + 1
+        );
+        self.set_season(season + 1);
+        Ok(())
+    }
+
+    /// End every season that is over by the given block, so a season ends on time even
+    /// when nobody draws. Returns whether the season changed.
+    pub async fn advance_to(&mut self, block_height: u64, block_timestamp_ms: u64) -> Result<bool> {
+        let Some(schedule) = self.rules.seasons else {
+            return Ok(false);
+        };
+        let season = self.keys.season();
+        while schedule.has_ended(self.keys.season(), block_height, block_timestamp_ms) {
+            self.end_season(block_height, block_timestamp_ms).await?;
         }
+        Ok(self.keys.season() != season)
+    }
 
-        let blob: Vec<u8> = self
-            .valkey
-            .get(valkey::region_key(rx, ry))
-            .await
-            .unwrap_or_default();
-
-        if blob.is_empty() {
-            // Return a zeroed-out region (all black, undrawn)
-            let empty = vec![0u8; REGION_BLOB_SIZE];
-            self.cache.put((rx, ry), empty.clone());
-            return empty;
+    /// Whether the board's seasons end on a schedule.
+    pub fn has_seasons(&self) -> bool {
+        self.rules.seasons.is_some()
+    }
+
+    /// Get or load a region's raw pixel array (no container header). Returns a clone.
+    pub async fn get_region(&mut self, rx: i32, ry: i32) -> Result<Vec<u8>> {
+        if let Some(blob) = self.cache.get(&(rx, ry)) {
+            metrics().cache_hits.with_label_values(&[self.keys.board()]).inc();
+            return Ok(blob.clone());
         }
+        metrics().cache_misses.with_label_values(&[self.keys.board()]).inc();
 
+        let blob = load_region(self.store.as_ref(), &self.keys, self.encoding, rx, ry).await?;
         self.cache.put((rx, ry), blob.clone());
-        blob
+        Ok(blob)
     }
 
     /// Apply a draw event to the board, enforcing ownership rules.
     /// Returns (applied_pixels, newly_opened_regions).
-    pub async fn apply_event(&mut self, event: &DrawEvent) -> (Vec<AppliedPixel>, Vec<(i32, i32)>) {
-        let owner_id = self.resolve_owner_id(&event.predecessor_id).await;
+    ///
+    /// An event past the end of the current season first ends it; the event is then
+    /// applied to the new season.
+    ///
+    /// All reads happen before any write, and every write of the event is committed in
+    /// one `Batch`, so on error the board is unchanged and the event can be retried.
+    /// The same transaction records the event's `position`; an event at or before the
+    /// last recorded position was already applied and is skipped.
+    pub async fn apply_event(
+        &mut self,
+        event: &DrawEvent,
+    ) -> Result<(Vec<AppliedPixel>, Vec<(i32, i32)>)> {
+        let position = event.position();
+        if let Some(position) = position {
+            let last = self.store.last_applied_event(&self.keys).await?;
+            if last.is_some_and(|last| position <= last) {
+                tracing::warn!(
+                    "Skipping event {} of block {}, already applied",
+                    position.1,
+                    position.0
+                );
+                return Ok((Vec::new(), Vec::new()));
+            }
+        }
+
+        self.advance_to(event.block_height, event.block_timestamp_ms).await?;
+
+        let owner_id = self.resolve_owner_id(&event.predecessor_id).await?;
         let mut applied = Vec::new();
         let mut newly_opened: Vec<(i32, i32)> = Vec::new();
 
-        // Group pixels by region
-        let mut region_pixels: std::collections::HashMap<(i32, i32), Vec<(usize, usize, u8, u8, u8)>> =
-            std::collections::HashMap::new();
+        // Deposit left to spend on this event's pixels, when pricing applies
+        let mut budget = self
+            .rules
+            .pricing
+            .applies_to(event.block_height)
+            .then_some(event.deposit);
+
+        // Group pixels by region. Ordered so deposit spending is deterministic:
+        // regions in (rx, ry) order, pixels in call order within a region.
+        let mut region_pixels: BTreeMap<(i32, i32), Vec<PendingPixel>> = BTreeMap::new();
 
         for pixel in &event.pixels {
             let (r, g, b) = match pixel.rgb() {
                 Some(rgb) => rgb,
                 None => continue,
             };
+            if !self.rules.pixel_in_bounds(pixel.x, pixel.y) {
+                continue;
+            }
             let (rx, ry) = region_coords(pixel.x, pixel.y);
             let (lx, ly) = local_coords(pixel.x, pixel.y);
             region_pixels
@@ -70,48 +207,52 @@ impl Board {
                 .push((lx, ly, r, g, b));
         }
 
+        // Writes of every region, and the blobs to cache once they are committed
+        let mut batch = Batch::new();
+        let mut written: Vec<((i32, i32), Vec<u8>)> = Vec::new();
+
         for ((rx, ry), pixels) in &region_pixels {
             // Gate check: skip regions that are not open for drawing
-            let region_key_str = format!("{}:{}", rx, ry);
-            let is_open: bool = redis::cmd("SISMEMBER")
-                .arg(valkey::OPEN_REGIONS)
-                .arg(&region_key_str)
-                .query_async(&mut self.valkey)
-                .await
-                .unwrap_or(false);
+            if !self.rules.region_in_bounds(*rx, *ry) {
+                continue;
+            }
+            let is_open = newly_opened.contains(&(*rx, *ry))
+                || self.store.is_region_open(&self.keys, *rx, *ry).await?;
             if !is_open {
                 continue;
             }
 
-            let mut blob = self.get_region(*rx, *ry).await;
-            let ts_key = valkey::pixel_ts_key(*rx, *ry);
-            let mut applied_ts: Vec<(String, f64)> = Vec::new();
+            let mut blob = self.get_region(*rx, *ry).await?;
+            let mut encoding = RegionEncoding::detect(&blob).unwrap_or(self.encoding);
+            if owner_id > encoding.max_owner_id() {
+                // Owner does not fit this region's layout; migrate it to the wide one
+                blob = convert_region_blob(&blob, encoding, RegionEncoding::Owner32)
+                    .expect("widening never overflows");
+                encoding = RegionEncoding::Owner32;
+            }
+            // Local and world coordinates of the pixels drawn in this region
+            let mut claimed: Vec<ClaimedPixel> = Vec::new();
             let mut new_pixel_count: i64 = 0;
             let mut stolen_from: HashMap<u32, i64> = HashMap::new();
+            // World coordinates to drop from previous owners' pixel indexes
+            let mut stolen_pixels: HashMap<u32, Vec<(i32, i32)>> = HashMap::new();
 
             for &(lx, ly, r, g, b) in pixels {
-                let offset = pixel_offset(lx, ly);
-                let existing = Pixel::decode(&blob[offset..offset + PIXEL_SIZE]);
+                let offset = encoding.pixel_offset(lx, ly);
+                let existing = encoding.decode(&blob[offset..offset + encoding.pixel_size()]);
 
                 // Ownership check
                 if !existing.is_empty() {
-                    let member = format!("{lx},{ly}");
-                    let ts: Option<f64> = redis::cmd("ZSCORE")
-                        .arg(&ts_key)
-                        .arg(&member)
-                        .query_async(&mut self.valkey)
-                        .await
-                        .unwrap_or(None);
+                    let ts = self.store.pixel_timestamp(&self.keys, (*rx, *ry), lx, ly).await?;
 
                     match ts {
                         None => {
                             // No timestamp found — pre-migration permanent pixel, skip
                             continue;
                         }
-                        Some(ts_f64) => {
-                            let ts_ns = ts_f64 as u64;
-                            let age = event.block_timestamp_ms.saturating_sub(ts_ns);
-                            if age >= OWNERSHIP_DURATION_MS {
+                        Some(ts_ms) => {
+                            let age = event.block_timestamp_ms.saturating_sub(ts_ms);
+                            if age >= self.rules.ownership_duration_ms {
                                 // Pixel is permanent — skip
                                 continue;
                             }
@@ -120,12 +261,23 @@ impl Board {
                     }
                 }
 
+                // Pricing check: anything still drawn here is claimed (permanent skipped above)
+                if let Some(remaining) = budget.as_mut() {
+                    if !self.rules.pricing.try_charge(remaining, !existing.is_empty()) {
+                        continue;
+                    }
+                }
+
+                let x = *rx * REGION_SIZE + lx as i32;
+                let y = *ry * REGION_SIZE + ly as i32;
+
                 // Track newly claimed pixels (undrawn → drawn)
                 if existing.is_empty() {
                     new_pixel_count += 1;
                 } else if existing.owner_id != owner_id {
                     // Stealing a pixel from another user
                     *stolen_from.entry(existing.owner_id).or_insert(0) += 1;
+                    stolen_pixels.entry(existing.owner_id).or_default().push((x, y));
                 }
 
                 // Apply the pixel
@@ -135,12 +287,14 @@ impl Board {
                     b,
                     owner_id,
                 };
-                new_pixel.encode(&mut blob[offset..offset + PIXEL_SIZE]);
+                encoding
+                    .encode(&new_pixel, &mut blob[offset..offset + encoding.pixel_size()])
+                    .expect("region widened to fit owner id");
 
-                applied_ts.push((format!("{lx},{ly}"), event.block_timestamp_ms as f64));
+                claimed.push(((lx, ly), (x, y)));
                 applied.push(AppliedPixel {
-                    x: *rx * REGION_SIZE + lx as i32,
-                    y: *ry * REGION_SIZE + ly as i32,
+                    x,
+                    y,
                     r,
                     g,
                     b,
@@ -148,73 +302,62 @@ impl Board {
                 });
             }
 
-            // Save back to cache
-            self.cache.put((*rx, *ry), blob.clone());
-
-            // Pipeline all writes for this region: ZADD + trim + SET + HSET
-            let mut pipe = redis::pipe();
-
-            if !applied_ts.is_empty() {
-                pipe.cmd("ZADD")
-                    .arg(&ts_key)
-                    .arg(applied_ts.iter().flat_map(|(member, score)| {
-                        vec![score.to_string(), member.clone()]
-                    }).collect::<Vec<_>>())
-                    .ignore();
-
-                let one_hour_ago = event.block_timestamp_ms.saturating_sub(OWNERSHIP_DURATION_MS);
-                pipe.zrembyscore(&ts_key, 0u64, one_hour_ago).ignore();
+            // Queue all writes for this region: timestamps, blob, counts
+            let region = (*rx, *ry);
+            if !claimed.is_empty() {
+                let ownership_cutoff = event
+                    .block_timestamp_ms
+                    .saturating_sub(self.rules.ownership_duration_ms);
+                batch.claim_pixels(
+                    &self.keys,
+                    region,
+                    owner_id,
+                    &claimed,
+                    event.block_timestamp_ms,
+                    ownership_cutoff,
+                );
+            }
+            for (old_owner, pixels) in &stolen_pixels {
+                batch.release_pixels(&self.keys, *old_owner, pixels);
             }
 
-            pipe.set(valkey::region_key(*rx, *ry), blob).ignore();
-            pipe.cmd("HSET")
-                .arg(valkey::region_meta_key(*rx, *ry))
-                .arg("last_updated")
-                .arg(event.block_timestamp_ms)
-                .ignore();
+            batch.set_region(
+                &self.keys,
+                region,
+                encode_region_container(&blob, encoding),
+                event.block_timestamp_ms,
+                average_color(&blob, encoding),
+            );
 
             // Increment pixel count stats
             let total_stolen: i64 = stolen_from.values().sum();
             let owner_gain = new_pixel_count + total_stolen;
             if owner_gain > 0 {
-                pipe.cmd("HINCRBY")
-                    .arg(valkey::ACCOUNT_PIXEL_COUNT)
-                    .arg(owner_id)
-                    .arg(owner_gain)
-                    .ignore();
+                batch.add_owned_pixels(&self.keys, region, owner_id, owner_gain);
             }
             for (old_owner, count) in &stolen_from {
-                pipe.cmd("HINCRBY")
-                    .arg(valkey::ACCOUNT_PIXEL_COUNT)
-                    .arg(*old_owner)
-                    .arg(-*count)
-                    .ignore();
+                batch.add_owned_pixels(&self.keys, region, *old_owner, -*count);
+            }
+            if !stolen_from.is_empty() {
+                // Drop owners who no longer hold any pixel in this region
+                batch.drop_former_region_owners(&self.keys, region);
+            }
+            if !claimed.is_empty() {
+                let drawn = claimed.len() as i64;
+                batch.count_drawn(&self.keys, owner_id, drawn, event.block_timestamp_ms);
             }
             if new_pixel_count > 0 {
-                pipe.cmd("HINCRBY")
-                    .arg(valkey::REGION_PIXEL_COUNT)
-                    .arg(format!("{}:{}", rx, ry))
-                    .arg(new_pixel_count)
-                    .ignore();
+                batch.add_region_pixels(&self.keys, region, new_pixel_count);
             }
 
-            let _: () = pipe
-                .query_async(&mut self.valkey)
-                .await
-                .unwrap_or_else(|e| {
-                    tracing::error!("Failed to write region ({},{}): {}", rx, ry, e);
-                });
+            written.push(((*rx, *ry), blob));
 
-            // Expansion check: if region crossed the threshold, open cardinal neighbors
-            if new_pixel_count > 0 {
-                let count: i64 = redis::cmd("HGET")
-                    .arg(valkey::REGION_PIXEL_COUNT)
-                    .arg(&region_key_str)
-                    .query_async(&mut self.valkey)
-                    .await
-                    .unwrap_or(0);
-
-                if count >= REGION_OPEN_THRESHOLD {
+            // Expansion check: if region crossed the threshold, open cardinal neighbors.
+            // Bounded boards open all their regions up front.
+            if new_pixel_count > 0 && self.rules.bounds.is_none() {
+                let count = self.store.region_pixel_count(&self.keys, *rx, *ry).await?;
+
+                if count + new_pixel_count >= self.rules.region_open_threshold {
                     let neighbors = [
                         (*rx - 1, *ry),
                         (*rx + 1, *ry),
@@ -222,13 +365,12 @@ impl Board {
                         (*rx, *ry + 1),
                     ];
                     for (nx, ny) in neighbors {
-                        let added: i64 = redis::cmd("SADD")
-                            .arg(valkey::OPEN_REGIONS)
-                            .arg(format!("{}:{}", nx, ny))
-                            .query_async(&mut self.valkey)
-                            .await
-                            .unwrap_or(0);
-                        if added == 1 {
+                        if !self.rules.region_in_bounds(nx, ny) || newly_opened.contains(&(nx, ny))
+                        {
+                            continue;
+                        }
+                        if !self.store.is_region_open(&self.keys, nx, ny).await? {
+                            batch.open_regions(&self.keys, &[(nx, ny)]);
                             newly_opened.push((nx, ny));
                         }
                     }
@@ -236,41 +378,136 @@ impl Board {
             }
         }
 
-        (applied, newly_opened)
+        // Store for WebSocket catch-up (trimmed to 2 hours), with the draw itself so a
+        // crash cannot apply one without the other
+        if !applied.is_empty() {
+            let cutoff_ms = event.block_timestamp_ms.saturating_sub(CATCHUP_RETENTION_MS);
+            let message = draw_message(event, &applied);
+            batch.record_draw(&self.keys, message, event.block_timestamp_ms, cutoff_ms);
+        }
+        if let Some(position) = position {
+            batch.set_last_applied_event(&self.keys, position);
+        }
+        self.store.commit(batch).await?;
+        for (coords, blob) in written {
+            self.cache.put(coords, blob);
+        }
+        Ok((applied, newly_opened))
+    }
+
+    /// Fill an empty all-time leaderboard from the pixel counts (data written before
+    /// the leaderboard existed). Accounts holding no pixels are left off it.
+    pub async fn backfill_leaderboard(&mut self) -> Result<()> {
+        let (_, ranked) = self
+            .store
+            .leaderboard(&self.keys, LeaderboardWindow::All, 0, 0, 1)
+            .await?;
+        if ranked > 0 {
+            return Ok(());
+        }
+        let counts: Vec<(u32, i64)> = self
+            .store
+            .account_pixel_counts(&self.keys)
+            .await?
+            .into_iter()
+            .filter(|&(_, c)| c > 0)
+            .collect();
+        if counts.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = Batch::new();
+        batch.set_leaderboard(&self.keys, &counts);
+        self.store.commit(batch).await?;
+        tracing::info!(
+            "Backfilled leaderboard of board {} with {} accounts",
+            self.keys.board(),
+            counts.len()
+        );
+        Ok(())
+    }
+
+    /// Rebuild per-region owner counts from stored blobs for open regions that have
+    /// drawn pixels but no `region_owners` entry yet (data written before it existed).
+    pub async fn backfill_region_owners(&mut self) -> Result<()> {
+        for (rx, ry) in self.store.open_regions(&self.keys).await? {
+            if self.store.has_region_owners(&self.keys, rx, ry).await? {
+                continue;
+            }
+
+            let blob = self.store.region(&self.keys, rx, ry).await?;
+            let Ok(region) = decode_region_blob(&blob) else {
+                continue;
+            };
+            let mut counts: HashMap<u32, i64> = HashMap::new();
+            for chunk in region.pixels.chunks_exact(region.encoding.pixel_size()) {
+                let pixel = region.encoding.decode(chunk);
+                if !pixel.is_empty() {
+                    *counts.entry(pixel.owner_id).or_insert(0) += 1;
+                }
+            }
+            if counts.is_empty() {
+                continue;
+            }
+
+            let counts: Vec<(u32, i64)> = counts.into_iter().collect();
+            let mut batch = Batch::new();
+            batch.set_region_owners(&self.keys, (rx, ry), &counts);
+            self.store.commit(batch).await?;
+            tracing::info!("Backfilled owners for region ({},{})", rx, ry);
+        }
+        Ok(())
+    }
+
+    /// Compute the average color of open regions that have drawn pixels but no stored
+    /// color yet (data written before it was kept up to date).
+    pub async fn backfill_region_colors(&mut self) -> Result<()> {
+        let regions = self.store.open_regions(&self.keys).await?;
+        let summaries = self.store.region_summaries(&self.keys, &regions).await?;
+        for (&(rx, ry), summary) in regions.iter().zip(summaries) {
+            if summary.color.is_some() || summary.top_owner.is_none() {
+                continue;
+            }
+
+            let blob = self.store.region(&self.keys, rx, ry).await?;
+            let Ok(region) = decode_region_blob(&blob) else {
+                continue;
+            };
+            let Some(color) = average_color(&region.pixels, region.encoding) else {
+                continue;
+            };
+
+            let mut batch = Batch::new();
+            batch.set_region_color(&self.keys, (rx, ry), color);
+            self.store.commit(batch).await?;
+            tracing::info!("Backfilled color for region ({},{})", rx, ry);
+        }
+        Ok(())
     }
 
     /// Resolve an account_id to a u32 owner index, creating a new one if needed.
     /// IDs start at 1; 0 is reserved as the "undrawn" sentinel.
-    async fn resolve_owner_id(&mut self, account_id: &str) -> u32 {
-        // Check if account already has an ID
-        let existing: Option<u32> = self
-            .valkey
-            .hget(valkey::ACCOUNT_TO_ID, account_id)
-            .await
-            .unwrap_or(None);
-
-        if let Some(id) = existing {
-            return id;
+    ///
+    /// Fails with `OwnerIdsExhausted` when the board's encoding has no ids left; the
+    /// event is then dropped rather than written with a wrapped owner.
+    async fn resolve_owner_id(&mut self, account_id: &str) -> Result<u32> {
+        let max_owner_id = self.encoding.max_owner_id();
+        let id = self
+            .store
+            .resolve_owner_id(&self.keys, account_id, max_owner_id)
+            .await?;
+
+        // Logged once, not on every event from then on
+        if id > max_owner_id - max_owner_id / 100 && !self.warned_owner_id_limit {
+            self.warned_owner_id_limit = true;
+            tracing::warn!(
+                "Owner id {} is within 1% of the {} limit ({}); switch REGION_ENCODING to owner32",
+                id,
+                self.encoding.name(),
+                max_owner_id
+            );
         }
-
-        // Assign a new ID: hlen + 1 so IDs start at 1 (0 = undrawn sentinel)
-        let new_id: u32 = self
-            .valkey
-            .hlen::<_, u32>(valkey::ACCOUNT_TO_ID)
-            .await
-            .unwrap_or(0)
-            + 1;
-
-        let _: () = redis::pipe()
-            .hset(valkey::ACCOUNT_TO_ID, account_id, new_id).ignore()
-            .hset(valkey::ID_TO_ACCOUNT, new_id, account_id).ignore()
-            .query_async(&mut self.valkey)
-            .await
-            .unwrap_or_else(|e| {
-                tracing::error!("Failed to set owner mappings for {}: {}", account_id, e);
-            });
-
-        new_id
+        Ok(id)
     }
 }
 
@@ -283,3 +520,52 @@ pub struct AppliedPixel {
     pub b: u8,
     pub owner_id: u32,
 }
+
+/// The WebSocket `draw` message of the pixels an event applied.
+pub fn draw_message(event: &DrawEvent, applied: &[AppliedPixel]) -> String {
+    serde_json::json!({
+        "type": "draw",
+        "signer": event.predecessor_id,
+        "block_timestamp_ms": event.block_timestamp_ms,
+        "pixels": applied.iter().map(|p| {
+            serde_json::json!({
+                "x": p.x,
+                "y": p.y,
+                "color": format!("{:02X}{:02X}{:02X}", p.r, p.g, p.b),
+                "owner_id": p.owner_id
+            })
+        }).collect::<Vec<_>>()
+    })
+    .to_string()
+}
+
+/// Load a region's raw pixel array (no container header) from the store.
+///
+/// Stored blobs may be in any supported container version and `RegionEncoding`;
+/// 24-bit pixels are widened on load when the board runs with `Owner32`, and every
+/// region is persisted in the current container on its next write. A blob that
+/// cannot be decoded fails with `Error::CorruptRegion`.
+pub async fn load_region(
+    store: &dyn BoardStore,
+    keys: &Keyspace,
+    encoding: RegionEncoding,
+    rx: i32,
+    ry: i32,
+) -> Result<Vec<u8>> {
+    let blob = store.region(keys, rx, ry).await?;
+
+    let pixels = match decode_region_blob(&blob) {
+        // Return a zeroed-out region (all black, undrawn)
+        Err(_) if blob.is_empty() => vec![0u8; encoding.blob_size()],
+        Ok(region)
+            if region.encoding == RegionEncoding::Owner24 && encoding == RegionEncoding::Owner32 =>
+        {
+            convert_region_blob(&region.pixels, RegionEncoding::Owner24, RegionEncoding::Owner32)
+                .expect("widening never overflows")
+        }
+        Ok(region) => region.pixels,
+        // Never hand out a blank region for the next draw to write over
+        Err(reason) => return Err(Error::CorruptRegion { rx, ry, reason }),
+    };
+    Ok(pixels)
+}
@@ -1,6 +1,17 @@
 pub struct Config {
     pub valkey_url: String,
     pub listen_addr: String,
+    /// Key prefix from `VALKEY_PREFIX`; must match the indexer feeding this server.
+    pub keyspace: common::valkey::Keyspace,
+    /// Boards to host, from the `BOARDS_CONFIG` JSON file shared with the indexer, or
+    /// else the comma-separated `BOARDS` ids with default rules and the env prices. The
+    /// default board, which owns the original un-namespaced keys, is added to a `BOARDS`
+    /// list.
+    pub boards: Vec<common::BoardConfig>,
+    /// Pixel layout for new and migrated regions ("owner24" or "owner32").
+    pub region_encoding: common::RegionEncoding,
+    /// Readiness limits; see `HealthThresholds` for the env vars.
+    pub health: crate::health::HealthThresholds,
 }
 
 impl Config {
@@ -10,6 +21,100 @@ impl Config {
                 .unwrap_or_else(|_| "redis://127.0.0.1:6379".into()),
             listen_addr: std::env::var("LISTEN_ADDR")
                 .unwrap_or_else(|_| "0.0.0.0:3000".into()),
+            keyspace: common::valkey::Keyspace::from_env(),
+            boards: load_boards(),
+            region_encoding: std::env::var("REGION_ENCODING")
+                .ok()
+                .map(|s| {
+                    common::RegionEncoding::from_name(&s)
+                        .unwrap_or_else(|| panic!("invalid REGION_ENCODING: {s}"))
+                })
+                .unwrap_or(common::RegionEncoding::Owner24),
+            health: health_thresholds(),
         }
     }
 }
+
+/// Parse an optional env var, panicking on a present but malformed value.
+fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let value = std::env::var(name).ok()?;
+    Some(
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid {name}: {value}")),
+    )
+}
+
+fn health_thresholds() -> crate::health::HealthThresholds {
+    let defaults = crate::health::HealthThresholds::default();
+    crate::health::HealthThresholds {
+        max_queue_depth: env_parse("HEALTH_MAX_QUEUE_DEPTH").unwrap_or(defaults.max_queue_depth),
+        max_event_age_ms: env_parse("HEALTH_MAX_EVENT_AGE_MS")
+            .unwrap_or(defaults.max_event_age_ms),
+        max_heartbeat_age_ms: env_parse("HEALTH_MAX_HEARTBEAT_AGE_MS")
+            .unwrap_or(defaults.max_heartbeat_age_ms),
+    }
+}
+
+/// Load the hosted boards from `BOARDS_CONFIG`, or build them from the `BOARDS` list.
+///
+/// Prices are per board: in `BOARDS_CONFIG` each board's `rules.pricing`, else the
+/// `PIXEL_PRICE_YOCTO` / `OVERWRITE_PRICE_YOCTO` / `PRICING_START_BLOCK` env vars for
+/// every listed board. Boards are free unless priced, and prices need a start block.
+fn load_boards() -> Vec<common::BoardConfig> {
+    let boards = match std::env::var("BOARDS_CONFIG") {
+        Ok(path) => {
+            if env_pricing().is_some() {
+                panic!("with BOARDS_CONFIG, set prices in each board's rules.pricing");
+            }
+            common::boards::load_boards(&path).unwrap_or_else(|e| panic!("{e}"))
+        }
+        Err(_) => {
+            let pricing = env_pricing().unwrap_or_default();
+            let list = std::env::var("BOARDS").unwrap_or_default();
+            let mut ids = vec![common::valkey::DEFAULT_BOARD.to_string()];
+            for id in list.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+                if !ids.iter().any(|b| b == id) {
+                    ids.push(id.to_string());
+                }
+            }
+            let boards: Vec<_> = ids
+                .into_iter()
+                .map(|id| common::BoardConfig {
+                    id,
+                    contract_ids: Vec::new(),
+                    rules: common::BoardRules {
+                        pricing,
+                        ..Default::default()
+                    },
+                })
+                .collect();
+            common::boards::validate_boards(&boards)
+                .unwrap_or_else(|e| panic!("invalid BOARDS: {e}"));
+            boards
+        }
+    };
+    if let Some(board) = boards
+        .iter()
+        .find(|b| crate::api::RESERVED_BOARD_IDS.contains(&b.id.as_str()))
+    {
+        panic!("board id {} is reserved", board.id);
+    }
+    boards
+}
+
+/// Prices from the env vars, if any is set.
+fn env_pricing() -> Option<common::Pricing> {
+    let pixel_price: Option<u128> = env_parse("PIXEL_PRICE_YOCTO");
+    let overwrite_price: Option<u128> = env_parse("OVERWRITE_PRICE_YOCTO");
+    let start_block_height: Option<u64> = env_parse("PRICING_START_BLOCK");
+    if pixel_price.is_none() && overwrite_price.is_none() && start_block_height.is_none() {
+        return None;
+    }
+    let pricing = common::Pricing::new(
+        pixel_price.unwrap_or(0),
+        overwrite_price.unwrap_or(0),
+        start_block_height,
+    );
+    Some(pricing.unwrap_or_else(|e| panic!("{e}: set PRICING_START_BLOCK")))
+}
@@ -1,114 +1,415 @@
-use common::valkey;
-use common::DrawEvent;
+use common::valkey::Keyspace;
+use common::{DrawEvent, IndexerHeartbeat};
 use redis::AsyncCommands;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use std::time::Instant;
+use tokio::sync::{broadcast, mpsc, RwLock};
 
-use crate::board::Board;
+use crate::board::{draw_message, AppliedPixel, Board};
+use crate::metrics::{metrics, EVENT_APPLIED, EVENT_INVALID, EVENT_REJECTED};
+use crate::storage::{Batch, BoardStore};
 
-/// Two hours in milliseconds (for trimming the WS catch-up sorted set).
-const CATCHUP_RETENTION_MS: u64 = 7_200_000;
+/// Backoff between retries of a failed store or queue operation: doubles from the
+/// initial delay up to the maximum.
+const RETRY_INITIAL_MS: u64 = 100;
+const RETRY_MAX_MS: u64 = 5_000;
 
-/// Consume draw events from the Valkey queue and apply them to the board.
+/// How often a consumer with an empty queue checks whether the indexer has passed the
+/// end of the season.
+const SEASON_CHECK_INTERVAL_MS: u64 = 1_000;
+
+/// One block's draw events for a board, handed over in process instead of through
+/// the Valkey queue, with the indexer progress to record once they are applied.
+#[derive(Debug, Clone)]
+pub struct BlockEvents {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub heartbeat: IndexerHeartbeat,
+    pub events: Vec<DrawEvent>,
+}
+
+/// Where a board's consumer takes draw events from.
+pub enum EventSource {
+    /// The board's draw queue in Valkey, filled by the indexer.
+    Valkey(redis::aio::MultiplexedConnection),
+    /// Blocks processed in the same process. The block's progress is recorded in the
+    /// board's store after its events, so a restart resumes from the first block not
+    /// fully applied.
+    Channel(mpsc::Receiver<BlockEvents>),
+}
+
+/// A consumer's progress, shared with the health checks.
+#[derive(Debug, Default)]
+pub struct ConsumerStatus {
+    running: AtomicBool,
+    /// Wall-clock ms when the last event was taken off the queue, or when the
+    /// consumer started if it has not taken one yet.
+    last_event_ms: AtomicU64,
+    /// Blocks waiting in an `EventSource::Channel`.
+    queued_blocks: AtomicU64,
+}
+
+impl ConsumerStatus {
+    /// Whether the consumer task is still running (false after it exits or panics).
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Milliseconds since the consumer last took an event off the queue.
+    pub fn last_event_age_ms(&self) -> u64 {
+        now_ms().saturating_sub(self.last_event_ms.load(Ordering::Relaxed))
+    }
+
+    /// Blocks waiting to be consumed, when fed over a channel.
+    pub fn queued_blocks(&self) -> u64 {
+        self.queued_blocks.load(Ordering::Relaxed)
+    }
+
+    fn touch(&self) {
+        self.last_event_ms.store(now_ms(), Ordering::Relaxed);
+    }
+}
+
+/// Marks the consumer stopped when `run` returns or unwinds.
+struct RunningGuard<'a>(&'a ConsumerStatus);
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Relaxed);
+        tracing::error!("Consumer stopped");
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Consume draw events from `source` and apply them to the board.
 pub async fn run(
-    mut con: redis::aio::MultiplexedConnection,
+    source: EventSource,
+    keys: Keyspace,
     board: Arc<RwLock<Board>>,
+    store: Arc<dyn BoardStore>,
     broadcast_tx: broadcast::Sender<String>,
+    status: Arc<ConsumerStatus>,
 ) {
     tracing::info!("Consumer started");
+    status.touch();
+    status.running.store(true, Ordering::Relaxed);
+    let _guard = RunningGuard(&status);
+    let consumer = Consumer {
+        board_id: keys.board().to_string(),
+        keys,
+        board,
+        store,
+        broadcast_tx,
+    };
+    match source {
+        EventSource::Valkey(con) => consumer.run_queue(con, &status).await,
+        EventSource::Channel(blocks_rx) => consumer.run_channel(blocks_rx, &status).await,
+    }
+}
 
-    loop {
-        // RPOPLPUSH: atomically move from draw_queue to processing_queue
-        let event_json: Option<String> = match redis::cmd("RPOPLPUSH")
-            .arg(valkey::DRAW_QUEUE)
-            .arg(valkey::PROCESSING_QUEUE)
-            .query_async(&mut con)
-            .await
-        {
-            Ok(v) => v,
-            Err(e) => {
-                tracing::error!("RPOPLPUSH failed: {}", e);
-                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                continue;
+/// What applying an event needs: the board, where to record the catch-up feed and
+/// where to broadcast.
+struct Consumer {
+    keys: Keyspace,
+    board_id: String,
+    board: Arc<RwLock<Board>>,
+    store: Arc<dyn BoardStore>,
+    broadcast_tx: broadcast::Sender<String>,
+}
+
+impl Consumer {
+    async fn run_queue(
+        &self,
+        mut con: redis::aio::MultiplexedConnection,
+        status: &ConsumerStatus,
+    ) {
+        let keys = &self.keys;
+
+        // Put events a previous run took but never finished back at the head of the
+        // queue, oldest first; any it did apply are skipped by their position
+        loop {
+            let requeued: Option<String> = match redis::cmd("LMOVE")
+                .arg(keys.processing_queue())
+                .arg(keys.draw_queue())
+                .arg("LEFT")
+                .arg("RIGHT")
+                .query_async(&mut con)
+                .await
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    metrics().valkey_errors.inc();
+                    tracing::error!("Failed to requeue unfinished events: {}", e);
+                    break;
+                }
+            };
+            if requeued.is_none() {
+                break;
             }
-        };
+            tracing::info!("Requeued an event left unfinished by the previous run");
+        }
+
+        let mut last_season_check = Instant::now();
+        loop {
+            // RPOPLPUSH: atomically move from draw_queue to processing_queue
+            let event_json: Option<String> = match redis::cmd("RPOPLPUSH")
+                .arg(keys.draw_queue())
+                .arg(keys.processing_queue())
+                .query_async(&mut con)
+                .await
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    metrics().valkey_errors.inc();
+                    tracing::error!("RPOPLPUSH failed: {}", e);
+                    tokio::time::sleep(tokio::time::Duration::from_millis(RETRY_INITIAL_MS))
+                        .await;
+                    continue;
+                }
+            };
+
+            let event_json = match event_json {
+                Some(json) => json,
+                None => {
+                    if last_season_check.elapsed().as_millis() as u64 >= SEASON_CHECK_INTERVAL_MS {
+                        self.advance_idle(&mut con).await;
+                        last_season_check = Instant::now();
+                    }
+                    // Queue is empty, wait a bit
+                    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+                    continue;
+                }
+            };
 
-        let event_json = match event_json {
-            Some(json) => json,
-            None => {
-                // Queue is empty, wait a bit
-                tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-                continue;
+            status.touch();
+
+            // Parse and apply
+            match serde_json::from_str(&event_json) {
+                Ok(event) => self.apply(&event).await,
+                Err(e) => {
+                    tracing::error!("Failed to parse draw event: {}", e);
+                    metrics().events.with_label_values(&[&self.board_id, EVENT_INVALID]).inc();
+                }
             }
-        };
 
-        // Parse and apply
-        let event: DrawEvent = match serde_json::from_str(&event_json) {
-            Ok(e) => e,
+            // The event is finished (or unreadable), so keep retrying rather than leave
+            // it in the processing queue
+            let mut attempt = 0;
+            let processing = keys.processing_queue();
+            while let Err(e) = con.lrem::<_, _, ()>(&processing, 1, &event_json).await {
+                metrics().valkey_errors.inc();
+                tracing::error!("Failed to finish event (attempt {}): {}", attempt + 1, e);
+                backoff(attempt).await;
+                attempt += 1;
+            }
+        }
+    }
+
+    async fn run_channel(
+        &self,
+        mut blocks_rx: mpsc::Receiver<BlockEvents>,
+        status: &ConsumerStatus,
+    ) {
+        while let Some(block) = blocks_rx.recv().await {
+            status.queued_blocks.store(blocks_rx.len() as u64, Ordering::Relaxed);
+            status.touch();
+            for event in &block.events {
+                self.apply(event).await;
+            }
+            let block_timestamp_ms = block.heartbeat.block_timestamp_ms;
+            self.advance(block.block_height, block_timestamp_ms).await;
+
+            let mut attempt = 0;
+            loop {
+                let mut batch = Batch::new();
+                let (height, hash) = (block.block_height, &block.block_hash);
+                batch.record_block(&self.keys, height, hash, &block.heartbeat);
+                match self.store.commit(batch).await {
+                    Ok(()) => break,
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to record block {} (attempt {}): {}",
+                            block.block_height,
+                            attempt + 1,
+                            e
+                        );
+                        backoff(attempt).await;
+                        attempt += 1;
+                    }
+                }
+            }
+        }
+        tracing::info!("Event channel closed");
+    }
+
+    /// End the season if the indexer has passed its end. The queue is checked empty after
+    /// reading the heartbeat, and the indexer queues a block's events with its
+    /// heartbeat, so every event up to that block is already applied.
+    async fn advance_idle(&self, con: &mut redis::aio::MultiplexedConnection) {
+        if !self.board.read().await.has_seasons() {
+            return;
+        }
+        let heartbeat = match self.store.indexer_heartbeat(&self.keys).await {
+            Ok(Some(heartbeat)) => heartbeat,
+            Ok(None) => return,
             Err(e) => {
-                tracing::error!("Failed to parse draw event: {}", e);
-                // Remove from processing queue even if parse fails
-                let _: () = con
-                    .lrem(valkey::PROCESSING_QUEUE, 1, &event_json)
-                    .await
-                    .unwrap_or_default();
-                continue;
+                tracing::error!("Failed to read the indexer heartbeat: {}", e);
+                return;
             }
         };
+        match con.llen::<_, u64>(self.keys.draw_queue()).await {
+            Ok(0) => {}
+            Ok(_) => return,
+            Err(e) => {
+                metrics().valkey_errors.inc();
+                tracing::error!("LLEN failed: {}", e);
+                return;
+            }
+        }
+        self.advance(heartbeat.block_height, heartbeat.block_timestamp_ms).await;
+    }
 
-        // Apply to board
-        let (applied, newly_opened) = {
-            let mut board = board.write().await;
-            board.apply_event(&event).await
-        };
+    /// End every season that is over by the given block, retrying until the store
+    /// recovers, and tell clients when a new one starts.
+    async fn advance(&self, block_height: u64, block_timestamp_ms: u64) {
+        let mut attempt = 0;
+        loop {
+            let mut board = self.board.write().await;
+            match board.advance_to(block_height, block_timestamp_ms).await {
+                Ok(false) => return,
+                Ok(true) => {
+                    let season = board.keys().season();
+                    drop(board);
+                    self.announce_season(season);
+                    return;
+                }
+                Err(e) => {
+                    drop(board);
+                    tracing::error!(
+                        "Failed to end the season at block {} (attempt {}): {}",
+                        block_height,
+                        attempt + 1,
+                        e
+                    );
+                    backoff(attempt).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Tell clients to reload everything for the new season.
+    fn announce_season(&self, season: u32) {
+        let season_event = serde_json::json!({
+            "type": "season_started",
+            "season": season,
+        });
+        let _ = self.broadcast_tx.send(season_event.to_string());
+    }
+
+    /// Apply `event` to the board, then record and broadcast what it drew.
+    async fn apply(&self, event: &DrawEvent) {
+        let board_id = self.board_id.as_str();
+
+        // Apply to board, retrying until the store recovers; a failed attempt writes nothing
+        let season_before = self.board.read().await.keys().season();
+        let (applied, newly_opened, season) =
+            match apply_with_retry(&self.board, event, board_id).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    // Retrying cannot help; drop the event like one with no valid pixel
+                    tracing::warn!(
+                        "Dropping event from {} at block {}: {}",
+                        event.predecessor_id,
+                        event.block_height,
+                        e
+                    );
+                    (Vec::new(), Vec::new(), season_before)
+                }
+            };
+        let result = if applied.is_empty() { EVENT_REJECTED } else { EVENT_APPLIED };
+        metrics().events.with_label_values(&[board_id, result]).inc();
+        metrics()
+            .pixels_applied
+            .with_label_values(&[board_id])
+            .inc_by(applied.len() as u64);
+        metrics()
+            .pixels_rejected
+            .with_label_values(&[board_id])
+            .inc_by(event.pixels.len().saturating_sub(applied.len()) as u64);
+        // The event itself belongs to the new season
+        if season != season_before {
+            self.announce_season(season);
+        }
+
+        if applied.is_empty() {
+            return;
+        }
+        // Broadcast to WebSocket subscribers; the catch-up copy was committed with it
+        let ws_json = draw_message(event, &applied);
+        let _ = self.broadcast_tx.send(ws_json);
 
-        // Store in sorted set for WebSocket catch-up (trimmed to 2 hours)
-        if !applied.is_empty() {
-            let ws_event = serde_json::json!({
-                "type": "draw",
-                "signer": event.predecessor_id,
-                "block_timestamp_ms": event.block_timestamp_ms,
-                "pixels": applied.iter().map(|p| {
-                    serde_json::json!({
-                        "x": p.x,
-                        "y": p.y,
-                        "color": format!("{:02X}{:02X}{:02X}", p.r, p.g, p.b),
-                        "owner_id": p.owner_id
-                    })
+        // Broadcast newly opened regions
+        if !newly_opened.is_empty() {
+            let regions_event = serde_json::json!({
+                "type": "regions_opened",
+                "regions": newly_opened.iter().map(|(rx, ry)| {
+                    serde_json::json!({ "rx": rx, "ry": ry })
                 }).collect::<Vec<_>>()
             });
+            let _ = self.broadcast_tx.send(regions_event.to_string());
+        }
+    }
+}
 
-            let ws_json = ws_event.to_string();
-
-            // ZADD + trim + LREM in a single pipeline
-            let two_hours_ago = event.block_timestamp_ms.saturating_sub(CATCHUP_RETENTION_MS);
-            let _: () = redis::pipe()
-                .zadd(valkey::DRAW_EVENTS_ZSET, &ws_json, event.block_timestamp_ms as f64).ignore()
-                .zrembyscore(valkey::DRAW_EVENTS_ZSET, 0u64, two_hours_ago).ignore()
-                .lrem(valkey::PROCESSING_QUEUE, 1, &event_json).ignore()
-                .query_async(&mut con)
-                .await
-                .unwrap_or_default();
-
-            // Broadcast to WebSocket subscribers
-            let _ = broadcast_tx.send(ws_json);
-
-            // Broadcast newly opened regions
-            if !newly_opened.is_empty() {
-                let regions_event = serde_json::json!({
-                    "type": "regions_opened",
-                    "regions": newly_opened.iter().map(|(rx, ry)| {
-                        serde_json::json!({ "rx": rx, "ry": ry })
-                    }).collect::<Vec<_>>()
-                });
-                let _ = broadcast_tx.send(regions_event.to_string());
+/// Apply `event`, retrying transient failures with backoff. Returns the applied
+/// pixels, newly opened regions and the season the event landed in; fails only
+/// with a permanent error.
+async fn apply_with_retry(
+    board: &RwLock<Board>,
+    event: &DrawEvent,
+    board_id: &str,
+) -> crate::Result<(Vec<AppliedPixel>, Vec<(i32, i32)>, u32)> {
+    let mut attempt = 0;
+    loop {
+        let mut board = board.write().await;
+        let started = Instant::now();
+        let outcome = board.apply_event(event).await;
+        metrics()
+            .apply_seconds
+            .with_label_values(&[board_id])
+            .observe(started.elapsed().as_secs_f64());
+        match outcome {
+            Ok((applied, newly_opened)) => {
+                return Ok((applied, newly_opened, board.keys().season()));
             }
-        } else {
-            // Remove from processing queue after successful processing
-            let _: () = con
-                .lrem(valkey::PROCESSING_QUEUE, 1, &event_json)
-                .await
-                .unwrap_or_default();
+            Err(e) if e.is_transient() => {
+                drop(board);
+                tracing::error!(
+                    "Failed to apply event from block {} (attempt {}), retrying: {}",
+                    event.block_height,
+                    attempt + 1,
+                    e
+                );
+                backoff(attempt).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
         }
     }
 }
+
+/// Sleep before retry number `attempt` (from 0) of a failed store or queue operation.
+async fn backoff(attempt: u32) {
+    let delay_ms = RETRY_INITIAL_MS
+        .saturating_mul(1 << attempt.min(16))
+        .min(RETRY_MAX_MS);
+    tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+}
@@ -0,0 +1,72 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+use crate::metrics::metrics;
+
+/// Errors from applying events and serving the API.
+#[derive(Debug)]
+pub enum Error {
+    /// A Valkey command failed or Valkey is unreachable. Transient: retrying the
+    /// operation later is safe.
+    Valkey(redis::RedisError),
+    /// A local store (see `storage::EmbeddedStore`) failed to persist a write, which
+    /// was not applied. Transient, like a Valkey failure.
+    Storage(String),
+    /// The board's encoding has no owner ids left for a new account. Permanent: the
+    /// account's draws can never be applied under the current `REGION_ENCODING`.
+    OwnerIdsExhausted { account_id: String },
+    /// A stored region blob could not be decoded. Permanent: the region is never
+    /// written over, so it stays as found until repaired by hand.
+    CorruptRegion {
+        rx: i32,
+        ry: i32,
+        reason: common::RegionFormatError,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Whether the operation may succeed if retried.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Valkey(_) | Self::Storage(_))
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Valkey(e) => write!(f, "valkey: {e}"),
+            Self::Storage(e) => write!(f, "storage: {e}"),
+            Self::OwnerIdsExhausted { account_id } => {
+                write!(f, "no owner id left for {account_id}")
+            }
+            Self::CorruptRegion { rx, ry, reason } => {
+                write!(f, "region ({rx},{ry}) is unreadable: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<redis::RedisError> for Error {
+    fn from(e: redis::RedisError) -> Self {
+        metrics().valkey_errors.inc();
+        Self::Valkey(e)
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        tracing::error!("Request failed: {}", self);
+        let status = match self {
+            Self::Valkey(_) | Self::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::OwnerIdsExhausted { .. } | Self::CorruptRegion { .. } => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        let body = serde_json::json!({ "error": status.canonical_reason() });
+        (status, axum::Json(body)).into_response()
+    }
+}
@@ -0,0 +1,164 @@
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use common::IndexerHeartbeat;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::api::BoardCtx;
+use crate::metrics::metrics;
+
+/// How long a Valkey or store check may take before it counts as unreachable.
+const VALKEY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Queue depth, last processed block and indexer heartbeat, or why they could not
+/// be read.
+type Reads = Result<(u64, Option<u64>, Option<IndexerHeartbeat>), String>;
+
+/// Limits past which a board reports itself not ready.
+#[derive(Debug, Clone, Copy)]
+pub struct HealthThresholds {
+    /// Queued draw events (`HEALTH_MAX_QUEUE_DEPTH`).
+    pub max_queue_depth: u64,
+    /// Time the consumer may go without taking an event while the queue is not empty
+    /// (`HEALTH_MAX_EVENT_AGE_MS`).
+    pub max_event_age_ms: u64,
+    /// Age of the indexer heartbeat (`HEALTH_MAX_HEARTBEAT_AGE_MS`).
+    pub max_heartbeat_age_ms: u64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            max_queue_depth: 10_000,
+            max_event_age_ms: 60_000,
+            max_heartbeat_age_ms: 60_000,
+        }
+    }
+}
+
+/// Liveness: the board's consumer task is running. 503 means restart the process.
+pub async fn live(state: BoardCtx) -> impl IntoResponse {
+    let running = state.consumer.is_running();
+    let status = if running { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        status,
+        axum::Json(serde_json::json!({
+            "status": if running { "ok" } else { "unavailable" },
+            "consumer_running": running,
+        })),
+    )
+}
+
+/// Readiness: Valkey is reachable, the consumer is running and keeping up with the
+/// queue, and the indexer heartbeat is fresh. Each check is reported; any failure
+/// makes the response 503.
+///
+/// Without Valkey (see `EventSource::Channel`) the board's store stands in for it
+/// and the queue is the consumer's channel of blocks.
+///
+/// A board whose indexer has never written a heartbeat reports it as missing without
+/// failing, since boards can be hosted before (or without) an indexer feeding them.
+pub async fn ready(state: BoardCtx) -> impl IntoResponse {
+    let limits = state.health;
+
+    let reads = match &state.valkey {
+        Some(valkey) => read_valkey(valkey.clone(), &state).await,
+        None => read_store(&state).await,
+    };
+    let (valkey_error, queue_depth, last_block, heartbeat) = match reads {
+        Ok((depth, last_block, heartbeat)) => (None, Some(depth), last_block, heartbeat),
+        Err(e) => (Some(e), None, None, None),
+    };
+    if valkey_error.is_some() && state.valkey.is_some() {
+        metrics().valkey_errors.inc();
+    }
+
+    let running = state.consumer.is_running();
+    let event_age_ms = state.consumer.last_event_age_ms();
+    let backlogged = queue_depth.is_some_and(|d| d > 0);
+    let consumer_ok = running && !(backlogged && event_age_ms > limits.max_event_age_ms);
+    let queue_ok = queue_depth.is_some_and(|d| d <= limits.max_queue_depth);
+
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let heartbeat_age_ms = heartbeat.map(|h| now_ms.saturating_sub(h.updated_ms));
+    let indexer_ok = valkey_error.is_none()
+        && heartbeat_age_ms.is_none_or(|age| age <= limits.max_heartbeat_age_ms);
+
+    let ok = valkey_error.is_none() && consumer_ok && queue_ok && indexer_ok;
+    let status = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let backend = if state.valkey.is_some() { "valkey" } else { "store" };
+    (
+        status,
+        axum::Json(serde_json::json!({
+            "status": if ok { "ok" } else { "unavailable" },
+            "board": state.keys.board(),
+            "last_processed_block": last_block,
+            "queue_length": queue_depth.unwrap_or(0),
+            "checks": {
+                backend: {
+                    "ok": valkey_error.is_none(),
+                    "error": valkey_error,
+                },
+                "consumer": {
+                    "ok": consumer_ok,
+                    "running": running,
+                    "last_event_age_ms": event_age_ms,
+                    "max_event_age_ms": limits.max_event_age_ms,
+                },
+                "queue": {
+                    "ok": queue_ok,
+                    "depth": queue_depth,
+                    "max_depth": limits.max_queue_depth,
+                },
+                "indexer": {
+                    "ok": indexer_ok,
+                    "heartbeat_age_ms": heartbeat_age_ms,
+                    "max_heartbeat_age_ms": limits.max_heartbeat_age_ms,
+                    "block_height": heartbeat.map(|h| h.block_height),
+                    "lag_ms": heartbeat.map(|h| h.lag_ms()),
+                    "behind_ms": heartbeat.map(|h| now_ms.saturating_sub(h.block_timestamp_ms)),
+                    "buffered_blocks": heartbeat.map(|h| h.buffered_blocks),
+                },
+            },
+        })),
+    )
+}
+
+async fn read_valkey(mut valkey: redis::aio::MultiplexedConnection, state: &BoardCtx) -> Reads {
+    let reads = tokio::time::timeout(
+        VALKEY_TIMEOUT,
+        redis::pipe()
+            .cmd("PING")
+            .llen(state.keys.draw_queue())
+            .get(state.keys.last_processed_block())
+            .hgetall(state.keys.indexer_heartbeat())
+            .query_async::<(String, u64, Option<u64>, HashMap<String, u64>)>(&mut valkey),
+    )
+    .await;
+    match reads {
+        Ok(Ok((_, depth, last_block, heartbeat))) => {
+            Ok((depth, last_block, IndexerHeartbeat::from_fields(&heartbeat)))
+        }
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("timed out".to_string()),
+    }
+}
+
+async fn read_store(state: &BoardCtx) -> Reads {
+    let reads = tokio::time::timeout(VALKEY_TIMEOUT, async {
+        let last_block = state.store.last_processed_block(&state.keys).await?;
+        let heartbeat = state.store.indexer_heartbeat(&state.keys).await?;
+        crate::Result::Ok((last_block.map(|(height, _)| height), heartbeat))
+    })
+    .await;
+    match reads {
+        Ok(Ok((last_block, heartbeat))) => {
+            Ok((state.consumer.queued_blocks(), last_block, heartbeat))
+        }
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("timed out".to_string()),
+    }
+}
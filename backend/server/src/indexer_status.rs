@@ -0,0 +1,45 @@
+use common::valkey::Keyspace;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::storage::BoardStore;
+
+/// How often the indexer heartbeat is re-broadcast to WebSocket clients.
+const STATUS_INTERVAL_MS: u64 = 5_000;
+
+/// The board's current `indexer_status` WebSocket message, if the indexer has
+/// written a heartbeat.
+pub async fn status_message(store: &dyn BoardStore, keys: &Keyspace) -> Option<String> {
+    let heartbeat = store
+        .indexer_heartbeat(keys)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to read indexer heartbeat: {}", e);
+            None
+        })?;
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    Some(heartbeat.status_message(now_ms).to_string())
+}
+
+/// Periodically broadcast the indexer heartbeat so clients can show how far the
+/// board trails the chain.
+pub async fn run(
+    store: Arc<dyn BoardStore>,
+    keys: Keyspace,
+    broadcast_tx: broadcast::Sender<String>,
+) {
+    let period = tokio::time::Duration::from_millis(STATUS_INTERVAL_MS);
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if broadcast_tx.receiver_count() == 0 {
+            continue;
+        }
+        if let Some(message) = status_message(store.as_ref(), &keys).await {
+            let _ = broadcast_tx.send(message);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+pub mod api;
+pub mod app;
+pub mod board;
+pub mod config;
+pub mod consumer;
+pub mod error;
+pub mod health;
+pub mod indexer_status;
+pub mod metrics;
+pub mod migrator;
+pub mod storage;
+pub mod ws;
+
+pub use error::{Error, Result};
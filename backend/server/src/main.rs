@@ -1,11 +1,6 @@
-mod api;
-mod board;
-mod config;
-mod consumer;
-mod ws;
-
+use server::{api, app, config};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::broadcast;
 use tower_http::cors::CorsLayer;
 
 async fn shutdown_signal() {
@@ -30,38 +25,31 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let config = config::Config::from_env();
-    tracing::info!("Starting server on {}", config.listen_addr);
+    tracing::info!(
+        "Starting server on {} (keyspace {:?})",
+        config.listen_addr,
+        config.keyspace.name()
+    );
 
     let valkey_client = redis::Client::open(config.valkey_url.as_str())?;
     let valkey_con = valkey_client.get_multiplexed_async_connection().await?;
 
-    // Seed region (0,0) as open (idempotent)
-    let _: i64 = redis::cmd("SADD")
-        .arg(common::valkey::OPEN_REGIONS)
-        .arg("0:0")
-        .query_async(&mut valkey_con.clone())
-        .await?;
-
-    let (broadcast_tx, _) = broadcast::channel::<String>(4096);
-
-    let board = Arc::new(tokio::sync::RwLock::new(
-        board::Board::new(valkey_con.clone()),
-    ));
+    let mut boards = HashMap::new();
+    for board_config in &config.boards {
+        let handle = app::start_board(&config, valkey_con.clone(), board_config).await?;
+        boards.insert(board_config.id.clone(), handle);
+    }
+    tracing::info!(
+        "Hosting boards {:?}",
+        config.boards.iter().map(|b| &b.id).collect::<Vec<_>>()
+    );
 
     let state = api::AppState {
-        board: board.clone(),
-        valkey: valkey_con.clone(),
-        broadcast_tx: broadcast_tx.clone(),
+        boards: Arc::new(boards),
+        valkey: Some(valkey_con.clone()),
+        health: config.health,
     };
 
-    // Start consumer task
-    let consumer_board = board.clone();
-    let consumer_valkey = valkey_con.clone();
-    let consumer_broadcast = broadcast_tx.clone();
-    tokio::spawn(async move {
-        consumer::run(consumer_valkey, consumer_board, consumer_broadcast).await;
-    });
-
     let app = api::router(state)
         .layer(CorsLayer::permissive());
 
@@ -0,0 +1,133 @@
+//! Prometheus metrics, served in the text exposition format at `/metrics`.
+//!
+//! Metric names and labels are relied on by dashboards and alerts; treat them as a
+//! stable interface. Per-board metrics carry a `board` label with the board id.
+
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::LazyLock;
+
+/// Outcomes of a consumed draw event, the `result` label of `board_events_total`.
+pub const EVENT_APPLIED: &str = "applied";
+pub const EVENT_REJECTED: &str = "rejected";
+pub const EVENT_INVALID: &str = "invalid";
+
+pub struct Metrics {
+    registry: Registry,
+    /// `board_events_total{board, result}`: draw events taken off the queue. `result` is
+    /// "applied" (at least one pixel drawn), "rejected" (every pixel refused: closed
+    /// region, owned pixel, deposit, unknown owner) or "invalid" (unparseable entry).
+    pub events: IntCounterVec,
+    /// `board_pixels_applied_total{board}`: pixels written to the board.
+    pub pixels_applied: IntCounterVec,
+    /// `board_pixels_rejected_total{board}`: pixels of consumed events that were refused.
+    pub pixels_rejected: IntCounterVec,
+    /// `board_apply_event_seconds{board}`: time to apply one event, including its
+    /// Valkey writes but not waiting for the board lock.
+    pub apply_seconds: HistogramVec,
+    /// `board_region_cache_hits_total{board}`: region reads served from the LRU cache.
+    pub cache_hits: IntCounterVec,
+    /// `board_region_cache_misses_total{board}`: region reads that went to Valkey.
+    pub cache_misses: IntCounterVec,
+    /// `board_ws_connections{board}`: open WebSocket connections.
+    pub ws_connections: IntGaugeVec,
+    /// `board_ws_lagged_messages_total{board}`: live messages a slow WebSocket client
+    /// missed because it fell behind the broadcast channel (the client is disconnected).
+    pub ws_lagged_messages: IntCounterVec,
+    /// `board_valkey_errors_total`: failed Valkey commands.
+    pub valkey_errors: IntCounter,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let board = &["board"];
+        Self {
+            events: IntCounterVec::new(
+                Opts::new("board_events_total", "Draw events consumed, by result"),
+                &["board", "result"],
+            )
+            .unwrap(),
+            pixels_applied: IntCounterVec::new(
+                Opts::new("board_pixels_applied_total", "Pixels written to the board"),
+                board,
+            )
+            .unwrap(),
+            pixels_rejected: IntCounterVec::new(
+                Opts::new("board_pixels_rejected_total", "Pixels of consumed events refused"),
+                board,
+            )
+            .unwrap(),
+            apply_seconds: HistogramVec::new(
+                HistogramOpts::new("board_apply_event_seconds", "Time to apply one draw event")
+                    .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0]),
+                board,
+            )
+            .unwrap(),
+            cache_hits: IntCounterVec::new(
+                Opts::new("board_region_cache_hits_total", "Region reads served from cache"),
+                board,
+            )
+            .unwrap(),
+            cache_misses: IntCounterVec::new(
+                Opts::new("board_region_cache_misses_total", "Region reads loaded from Valkey"),
+                board,
+            )
+            .unwrap(),
+            ws_connections: IntGaugeVec::new(
+                Opts::new("board_ws_connections", "Open WebSocket connections"),
+                board,
+            )
+            .unwrap(),
+            ws_lagged_messages: IntCounterVec::new(
+                Opts::new(
+                    "board_ws_lagged_messages_total",
+                    "Live messages dropped for WebSocket clients that fell behind",
+                ),
+                board,
+            )
+            .unwrap(),
+            valkey_errors: IntCounter::new("board_valkey_errors_total", "Failed Valkey commands")
+                .unwrap(),
+            registry,
+        }
+        .registered()
+    }
+
+    fn registered(self) -> Self {
+        let collectors: [Box<dyn Collector>; 9] = [
+            Box::new(self.events.clone()),
+            Box::new(self.pixels_applied.clone()),
+            Box::new(self.pixels_rejected.clone()),
+            Box::new(self.apply_seconds.clone()),
+            Box::new(self.cache_hits.clone()),
+            Box::new(self.cache_misses.clone()),
+            Box::new(self.ws_connections.clone()),
+            Box::new(self.ws_lagged_messages.clone()),
+            Box::new(self.valkey_errors.clone()),
+        ];
+        for collector in collectors {
+            self.registry.register(collector).expect("metric names are unique");
+        }
+        self
+    }
+
+    /// Render every metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .expect("text encoding never fails");
+        String::from_utf8(buf).expect("metrics are UTF-8")
+    }
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The process-wide metrics.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
@@ -0,0 +1,119 @@
+use common::region::*;
+use common::valkey::Keyspace;
+
+use crate::metrics::metrics;
+
+/// Regions inspected per SCAN batch.
+const SCAN_BATCH: usize = 100;
+
+/// Pause between batches so the migrator never competes with the consumer for Valkey.
+const BATCH_PAUSE_MS: u64 = 50;
+
+/// Replace KEYS[1] with ARGV[2] only if it still holds ARGV[1], so a region the
+/// consumer rewrote since we read it is left alone (it is already in the new format).
+const COMPARE_AND_SET_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('SET', KEYS[1], ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+/// What one migration pass did with each region it found.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub migrated: u64,
+    /// Already in the current version.
+    pub skipped: u64,
+    /// Rewritten by the consumer between our read and our swap, so left alone.
+    pub raced: u64,
+    pub failed: u64,
+}
+
+/// Rewrite every stored region blob into the current versioned container, in place.
+///
+/// Runs once in the background at startup. Blobs already in the current version are
+/// skipped; unreadable blobs are logged and left untouched.
+pub async fn run(mut con: redis::aio::MultiplexedConnection, keys: Keyspace) -> MigrationReport {
+    let script = redis::Script::new(COMPARE_AND_SET_SCRIPT);
+    let pattern = keys.region_pattern();
+    let mut cursor: u64 = 0;
+    let mut report = MigrationReport::default();
+
+    loop {
+        let (next, keys): (u64, Vec<String>) = match redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(&pattern)
+            .arg("COUNT")
+            .arg(SCAN_BATCH)
+            .query_async(&mut con)
+            .await
+        {
+            Ok(v) => v,
+            Err(e) => {
+                metrics().valkey_errors.inc();
+                tracing::error!("Region migration SCAN failed, stopping: {}", e);
+                return report;
+            }
+        };
+
+        for key in keys {
+            let blob: Vec<u8> = match redis::cmd("GET").arg(&key).query_async(&mut con).await {
+                Ok(b) => b,
+                Err(e) => {
+                    metrics().valkey_errors.inc();
+                    tracing::error!("Region migration GET {} failed: {}", key, e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+
+            let region = match decode_region_blob(&blob) {
+                Ok(region) if region.version == REGION_FORMAT_VERSION => {
+                    report.skipped += 1;
+                    continue;
+                }
+                Ok(region) => region,
+                Err(e) => {
+                    tracing::error!("Region migration skipping unreadable {}: {}", key, e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+
+            let container = encode_region_container(&region.pixels, region.encoding);
+            let swapped: Result<i64, _> = script
+                .key(&key)
+                .arg(&blob)
+                .arg(container)
+                .invoke_async(&mut con)
+                .await;
+            match swapped {
+                Ok(1) => report.migrated += 1,
+                Ok(_) => report.raced += 1,
+                Err(e) => {
+                    metrics().valkey_errors.inc();
+                    tracing::error!("Region migration SET {} failed: {}", key, e);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        cursor = next;
+        if cursor == 0 {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(BATCH_PAUSE_MS)).await;
+    }
+
+    tracing::info!(
+        "Region migration finished: {} migrated, {} already current, {} rewritten meanwhile, \
+         {} failed",
+        report.migrated,
+        report.skipped,
+        report.raced,
+        report.failed
+    );
+    report
+}
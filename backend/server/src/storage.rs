@@ -0,0 +1,617 @@
+//! Where a board keeps its state: region blobs, pixel timestamps, owner mappings,
+//! counters, open regions and seasons.
+//!
+//! `BoardStore` is read through domain-level queries and written through `Batch`es,
+//! each committed atomically. Every backend lays data out under the same `Keyspace`
+//! key names Valkey uses, so a `Batch` is a list of data structure operations:
+//!
+//! - `ValkeyStore`, the default, runs a batch as one MULTI/EXEC.
+//! - `MemoryStore` keeps everything in process, for tests.
+//! - `EmbeddedStore` keeps the same layout in a redb database file, for deployments
+//!   without Valkey.
+
+pub mod embedded;
+pub mod memory;
+pub mod valkey;
+
+use common::valkey::{Keyspace, LEADERBOARD_BUCKET_MS, LEADERBOARD_BUCKET_TTL_SECS};
+use common::IndexerHeartbeat;
+
+use crate::Result;
+
+pub use embedded::EmbeddedStore;
+pub use memory::MemoryStore;
+pub use valkey::ValkeyStore;
+
+/// A region's (rx, ry) coordinates.
+pub type RegionCoords = (i32, i32);
+
+/// A drawn pixel's local (lx, ly) and world (x, y) coordinates.
+pub type ClaimedPixel = ((usize, usize), (i32, i32));
+
+#[async_trait::async_trait]
+pub trait BoardStore: Send + Sync {
+    /// The board's current season number, if it ever advanced past season 0.
+    async fn current_season(&self, keys: &Keyspace) -> Result<Option<u32>>;
+
+    /// Position of the last event applied to the board; see `DrawEvent::position`.
+    async fn last_applied_event(&self, keys: &Keyspace) -> Result<Option<(u64, u32)>>;
+
+    /// A region's stored blob, or empty if it was never written.
+    async fn region(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Vec<u8>>;
+
+    /// Block timestamp of the last write to a region.
+    async fn region_updated_ms(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Option<u64>>;
+
+    /// When the pixel at local (lx, ly) was last drawn, if that is recent enough to be
+    /// still tracked.
+    async fn pixel_timestamp(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        lx: usize,
+        ly: usize,
+    ) -> Result<Option<u64>>;
+
+    /// Local (lx, ly, drawn_ms) of a region's pixels drawn at or after `since_ms`.
+    async fn pixel_timestamps(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        since_ms: u64,
+    ) -> Result<Vec<(u64, u64, u64)>>;
+
+    async fn is_region_open(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool>;
+
+    async fn open_regions(&self, keys: &Keyspace) -> Result<Vec<RegionCoords>>;
+
+    /// Drawn pixels in a region.
+    async fn region_pixel_count(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<i64>;
+
+    /// Owners holding pixels in a region, most pixels first, at most `limit` of them.
+    async fn region_owners(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        limit: usize,
+    ) -> Result<Vec<(u32, i64)>>;
+
+    /// Top owner and average color of each region, in order; one read for them all.
+    async fn region_summaries(
+        &self,
+        keys: &Keyspace,
+        regions: &[RegionCoords],
+    ) -> Result<Vec<RegionSummary>>;
+
+    /// Whether a region's owner counts were ever written.
+    async fn has_region_owners(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool>;
+
+    /// Resolve an account to its owner id, allocating the next free one if needed.
+    /// Fails with `OwnerIdsExhausted` rather than hand out an id above `max_owner_id`.
+    async fn resolve_owner_id(
+        &self,
+        keys: &Keyspace,
+        account_id: &str,
+        max_owner_id: u32,
+    ) -> Result<u32>;
+
+    async fn owner_id(&self, keys: &Keyspace, account_id: &str) -> Result<Option<u32>>;
+
+    /// Account names of `owner_ids`, in order.
+    async fn account_ids(&self, keys: &Keyspace, owner_ids: &[u32]) -> Result<Vec<Option<String>>>;
+
+    /// Pixel count of every account that ever held a pixel.
+    async fn account_pixel_counts(&self, keys: &Keyspace) -> Result<Vec<(u32, i64)>>;
+
+    /// An account's profile; pixels drawn at or after `claimable_since_ms` count as
+    /// still claimable.
+    async fn account_stats(
+        &self,
+        keys: &Keyspace,
+        owner_id: u32,
+        claimable_since_ms: u64,
+    ) -> Result<AccountStats>;
+
+    /// One page of a leaderboard, most pixels first, and the number of ranked accounts.
+    async fn leaderboard(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<(u32, i64)>, u64)>;
+
+    /// An account's 0-based rank and score on a leaderboard, if it is ranked.
+    async fn leaderboard_rank(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        owner_id: u32,
+    ) -> Result<(Option<u64>, Option<i64>)>;
+
+    /// The board's ended seasons, oldest first.
+    async fn ended_seasons(&self, keys: &Keyspace) -> Result<Vec<EndedSeason>>;
+
+    /// WebSocket `draw` messages of events drawn at or after `since_ms`, oldest first.
+    async fn draw_events(&self, keys: &Keyspace, since_ms: u64) -> Result<Vec<String>>;
+
+    /// Height and hash of the last block the indexer handed to the board.
+    async fn last_processed_block(&self, keys: &Keyspace) -> Result<Option<(u64, Option<String>)>>;
+
+    async fn indexer_heartbeat(&self, keys: &Keyspace) -> Result<Option<IndexerHeartbeat>>;
+
+    /// Apply every write of `batch`, or none of them.
+    async fn commit(&self, batch: Batch) -> Result<()>;
+}
+
+/// A leaderboard's time span: all-time pixel counts, or pixels drawn recently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderboardWindow {
+    All,
+    Day,
+    Week,
+}
+
+impl LeaderboardWindow {
+    /// Parse a `window` query value; `None` means all-time.
+    pub fn from_name(name: Option<&str>) -> Option<Self> {
+        match name {
+            None | Some("all") => Some(Self::All),
+            Some("24h") => Some(Self::Day),
+            Some("7d") => Some(Self::Week),
+            Some(_) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Day => "24h",
+            Self::Week => "7d",
+        }
+    }
+
+    /// `leaderboard_drawn` buckets the window spans at `now_ms`; empty for all-time.
+    pub fn buckets(self, keys: &Keyspace, now_ms: u64) -> Vec<String> {
+        let hours = match self {
+            Self::All => 0,
+            Self::Day => 24,
+            Self::Week => 24 * 7,
+        };
+        let current_hour = now_ms / LEADERBOARD_BUCKET_MS;
+        (0..hours)
+            .map(|h| keys.leaderboard_drawn(current_hour.saturating_sub(h)))
+            .collect()
+    }
+}
+
+/// What `/api/account/by-name` reports about an account.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountStats {
+    pub pixel_count: i64,
+    /// Owned pixels still inside their ownership window.
+    pub claimable_pixel_count: i64,
+    pub first_draw_ms: Option<u64>,
+    pub last_draw_ms: Option<u64>,
+    /// Regions the account ever drew in.
+    pub regions: Vec<RegionCoords>,
+}
+
+/// What the world map shows of a region.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionSummary {
+    /// The owner holding the most pixels, and how many.
+    pub top_owner: Option<(u32, i64)>,
+    /// Average color of the drawn pixels, as of the last write.
+    pub color: Option<(u8, u8, u8)>,
+}
+
+/// How a season ended, recorded when the next one starts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeasonSummary {
+    pub ended_block_height: u64,
+    pub ended_timestamp_ms: u64,
+    pub open_regions: u64,
+    pub top_owner_id: u32,
+    pub top_pixel_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndedSeason {
+    pub season: u32,
+    pub summary: SeasonSummary,
+}
+
+/// One data structure write. Members and fields are strings as in Valkey; scores
+/// are floats.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Set {
+        key: String,
+        value: Vec<u8>,
+    },
+    HSet {
+        key: String,
+        field: String,
+        value: String,
+    },
+    HSetNx {
+        key: String,
+        field: String,
+        value: String,
+    },
+    HIncrBy {
+        key: String,
+        field: String,
+        by: i64,
+    },
+    SAdd {
+        key: String,
+        members: Vec<String>,
+    },
+    ZAdd {
+        key: String,
+        members: Vec<(f64, String)>,
+    },
+    ZIncrBy {
+        key: String,
+        member: String,
+        by: i64,
+    },
+    ZRem {
+        key: String,
+        members: Vec<String>,
+    },
+    /// Remove members scored within `[min, max]`.
+    ZRemRangeByScore {
+        key: String,
+        min: f64,
+        max: f64,
+    },
+    /// Delete the key at a wall-clock time in ms.
+    ExpireAt {
+        key: String,
+        at_ms: u64,
+    },
+}
+
+/// Writes committed together by `BoardStore::commit`.
+#[derive(Debug, Default)]
+pub struct Batch {
+    ops: Vec<Op>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    pub fn into_ops(self) -> Vec<Op> {
+        self.ops
+    }
+
+    /// Store a region's blob, when it was written and the average color of its drawn
+    /// pixels.
+    pub fn set_region(
+        &mut self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        blob: Vec<u8>,
+        ts_ms: u64,
+        color: Option<(u8, u8, u8)>,
+    ) {
+        self.ops.push(Op::Set {
+            key: keys.region(rx, ry),
+            value: blob,
+        });
+        self.ops.push(Op::HSet {
+            key: keys.region_meta(rx, ry),
+            field: "last_updated".into(),
+            value: ts_ms.to_string(),
+        });
+        if let Some(color) = color {
+            self.set_region_color(keys, (rx, ry), color);
+        }
+    }
+
+    /// Store the average color of a region's drawn pixels.
+    pub fn set_region_color(
+        &mut self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        (r, g, b): (u8, u8, u8),
+    ) {
+        self.ops.push(Op::HSet {
+            key: keys.region_meta(rx, ry),
+            field: REGION_COLOR_FIELD.into(),
+            value: format!("{r:02X}{g:02X}{b:02X}"),
+        });
+    }
+
+    /// Record pixels `owner_id` drew in a region at `ts_ms`: their timestamps, the
+    /// account's pixel index and first/last draw, dropping entries older than
+    /// `cutoff_ms`, which are permanent. Pixels are given as local and world
+    /// coordinates.
+    pub fn claim_pixels(
+        &mut self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        owner_id: u32,
+        pixels: &[ClaimedPixel],
+        ts_ms: u64,
+        cutoff_ms: u64,
+    ) {
+        let score = ts_ms as f64;
+        let ts_key = keys.pixel_ts(rx, ry);
+        let local = pixels
+            .iter()
+            .map(|((lx, ly), _)| (score, format!("{lx},{ly}")));
+        self.ops.push(Op::ZAdd {
+            key: ts_key.clone(),
+            members: local.collect(),
+        });
+        self.ops.push(Op::ZRemRangeByScore {
+            key: ts_key,
+            min: 0.0,
+            max: cutoff_ms as f64,
+        });
+
+        let account_ts_key = keys.account_pixel_ts(owner_id);
+        let world = pixels.iter().map(|(_, (x, y))| (score, format!("{x},{y}")));
+        self.ops.push(Op::ZAdd {
+            key: account_ts_key.clone(),
+            members: world.collect(),
+        });
+        self.ops.push(Op::ZRemRangeByScore {
+            key: account_ts_key,
+            min: 0.0,
+            max: cutoff_ms as f64,
+        });
+        self.ops.push(Op::SAdd {
+            key: keys.account_regions(owner_id),
+            members: vec![format!("{rx}:{ry}")],
+        });
+        let meta_key = keys.account_meta(owner_id);
+        self.ops.push(Op::HSetNx {
+            key: meta_key.clone(),
+            field: "first_draw_ms".into(),
+            value: ts_ms.to_string(),
+        });
+        self.ops.push(Op::HSet {
+            key: meta_key,
+            field: "last_draw_ms".into(),
+            value: ts_ms.to_string(),
+        });
+    }
+
+    /// Drop world pixels (x, y) taken from `owner_id` out of its pixel index.
+    pub fn release_pixels(&mut self, keys: &Keyspace, owner_id: u32, pixels: &[(i32, i32)]) {
+        self.ops.push(Op::ZRem {
+            key: keys.account_pixel_ts(owner_id),
+            members: pixels.iter().map(|(x, y)| format!("{x},{y}")).collect(),
+        });
+    }
+
+    /// Change the pixels `owner_id` holds, overall and in a region.
+    pub fn add_owned_pixels(
+        &mut self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        owner_id: u32,
+        by: i64,
+    ) {
+        self.ops.push(Op::HIncrBy {
+            key: keys.account_pixel_count(),
+            field: owner_id.to_string(),
+            by,
+        });
+        self.ops.push(Op::ZIncrBy {
+            key: keys.leaderboard(),
+            member: owner_id.to_string(),
+            by,
+        });
+        // Accounts left holding no pixels drop off the leaderboard
+        if by < 0 {
+            self.ops.push(Op::ZRemRangeByScore {
+                key: keys.leaderboard(),
+                min: f64::NEG_INFINITY,
+                max: 0.0,
+            });
+        }
+        self.ops.push(Op::ZIncrBy {
+            key: keys.region_owners(rx, ry),
+            member: owner_id.to_string(),
+            by,
+        });
+    }
+
+    /// Forget a region's owners who no longer hold any pixel in it.
+    pub fn drop_former_region_owners(&mut self, keys: &Keyspace, (rx, ry): RegionCoords) {
+        self.ops.push(Op::ZRemRangeByScore {
+            key: keys.region_owners(rx, ry),
+            min: f64::NEG_INFINITY,
+            max: 0.0,
+        });
+    }
+
+    /// Set a region's owner counts, e.g. as rebuilt from its pixels.
+    pub fn set_region_owners(
+        &mut self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        counts: &[(u32, i64)],
+    ) {
+        self.ops.push(Op::ZAdd {
+            key: keys.region_owners(rx, ry),
+            members: counts
+                .iter()
+                .map(|(id, c)| (*c as f64, id.to_string()))
+                .collect(),
+        });
+    }
+
+    /// Set all-time leaderboard scores, e.g. as rebuilt from the pixel counts.
+    pub fn set_leaderboard(&mut self, keys: &Keyspace, counts: &[(u32, i64)]) {
+        self.ops.push(Op::ZAdd {
+            key: keys.leaderboard(),
+            members: counts
+                .iter()
+                .map(|(id, c)| (*c as f64, id.to_string()))
+                .collect(),
+        });
+    }
+
+    /// Credit `count` drawn pixels to `owner_id` in the hourly bucket of `ts_ms`, which
+    /// the windowed leaderboards add up. The bucket expires a TTL after its hour ends.
+    pub fn count_drawn(&mut self, keys: &Keyspace, owner_id: u32, count: i64, ts_ms: u64) {
+        let bucket = ts_ms / LEADERBOARD_BUCKET_MS;
+        let key = keys.leaderboard_drawn(bucket);
+        self.ops.push(Op::ZIncrBy {
+            key: key.clone(),
+            member: owner_id.to_string(),
+            by: count,
+        });
+        self.ops.push(Op::ExpireAt {
+            key,
+            at_ms: (bucket + 1) * LEADERBOARD_BUCKET_MS + LEADERBOARD_BUCKET_TTL_SECS * 1000,
+        });
+    }
+
+    /// Add newly drawn pixels to a region's count.
+    pub fn add_region_pixels(&mut self, keys: &Keyspace, (rx, ry): RegionCoords, by: i64) {
+        self.ops.push(Op::HIncrBy {
+            key: keys.region_pixel_count(),
+            field: format!("{rx}:{ry}"),
+            by,
+        });
+    }
+
+    pub fn open_regions(&mut self, keys: &Keyspace, regions: &[RegionCoords]) {
+        self.ops.push(Op::SAdd {
+            key: keys.open_regions(),
+            members: regions
+                .iter()
+                .map(|(rx, ry)| format!("{rx}:{ry}"))
+                .collect(),
+        });
+    }
+
+    pub fn set_last_applied_event(
+        &mut self,
+        keys: &Keyspace,
+        (block_height, event_index): (u64, u32),
+    ) {
+        self.ops.push(Op::Set {
+            key: keys.last_applied_event(),
+            value: format!("{block_height}:{event_index}").into_bytes(),
+        });
+    }
+
+    /// Keep an event's WebSocket `draw` message for clients catching up, dropping
+    /// messages drawn at or before `cutoff_ms`.
+    pub fn record_draw(&mut self, keys: &Keyspace, message: String, ts_ms: u64, cutoff_ms: u64) {
+        self.ops.push(Op::ZAdd {
+            key: keys.draw_events(),
+            members: vec![(ts_ms as f64, message)],
+        });
+        self.ops.push(Op::ZRemRangeByScore {
+            key: keys.draw_events(),
+            min: 0.0,
+            max: cutoff_ms as f64,
+        });
+    }
+
+    /// Mark a block processed and record the indexer's heartbeat after it, as the
+    /// indexer does in Valkey.
+    pub fn record_block(
+        &mut self,
+        keys: &Keyspace,
+        block_height: u64,
+        block_hash: &str,
+        heartbeat: &IndexerHeartbeat,
+    ) {
+        self.ops.push(Op::Set {
+            key: keys.last_processed_block(),
+            value: block_height.to_string().into_bytes(),
+        });
+        self.ops.push(Op::Set {
+            key: keys.last_processed_block_hash(),
+            value: block_hash.as_bytes().to_vec(),
+        });
+        for (field, value) in heartbeat.fields() {
+            self.ops.push(Op::HSet {
+                key: keys.indexer_heartbeat(),
+                field: field.into(),
+                value: value.to_string(),
+            });
+        }
+    }
+
+    /// End the season `keys` address and start the next one with `initial_regions`
+    /// open.
+    pub fn end_season(
+        &mut self,
+        keys: &Keyspace,
+        summary: &SeasonSummary,
+        initial_regions: &[RegionCoords],
+    ) {
+        let season = keys.season();
+        let fields = [
+            ("ended_block_height", summary.ended_block_height),
+            ("ended_timestamp_ms", summary.ended_timestamp_ms),
+            ("open_regions", summary.open_regions),
+            ("top_owner_id", summary.top_owner_id as u64),
+            ("top_pixel_count", summary.top_pixel_count),
+        ];
+        for (field, value) in fields {
+            self.ops.push(Op::HSet {
+                key: keys.season_meta(),
+                field: field.into(),
+                value: value.to_string(),
+            });
+        }
+        self.ops.push(Op::ZAdd {
+            key: keys.ended_seasons(),
+            members: vec![(summary.ended_timestamp_ms as f64, season.to_string())],
+        });
+        self.ops.push(Op::Set {
+            key: keys.current_season(),
+            value: (season + 1).to_string().into_bytes(),
+        });
+        self.open_regions(&keys.for_season(season + 1), initial_regions);
+    }
+}
+
+/// Field of `region_meta` holding the region's average color as "RRGGBB".
+pub(crate) const REGION_COLOR_FIELD: &str = "avg_color";
+
+/// Parse a `REGION_COLOR_FIELD` value.
+pub(crate) fn parse_color(value: &str) -> Option<(u8, u8, u8)> {
+    let channel = |i: usize| u8::from_str_radix(value.get(i..i + 2)?, 16).ok();
+    if value.len() != 6 {
+        return None;
+    }
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Parse an `open_regions`-style "rx:ry" member.
+pub(crate) fn parse_region(member: &str) -> Option<RegionCoords> {
+    let (rx, ry) = member.split_once(':')?;
+    Some((rx.parse().ok()?, ry.parse().ok()?))
+}
+
+/// Parse a `last_applied_event` value.
+pub(crate) fn parse_position(value: &str) -> Option<(u64, u32)> {
+    let (block_height, event_index) = value.split_once(':')?;
+    Some((block_height.parse().ok()?, event_index.parse().ok()?))
+}
@@ -0,0 +1,941 @@
+//! A board stored in a local redb database, for deployments without Valkey.
+//!
+//! Keys are laid out as in Valkey, each data structure in its own table keyed by
+//! `key \0 field` (or member). Sorted sets also keep an index keyed by
+//! `key \0 score member`, with the score encoded so the bytes sort like the floats,
+//! which serves ranges and rankings as ordered scans. A batch is one write
+//! transaction, durable once committed.
+//!
+//! redb calls block, so every query runs on the blocking thread pool. Reads each take
+//! their own snapshot and never wait on writes.
+//!
+//! Keys given an expiry read as absent once it passes, and are deleted by the next
+//! write.
+//!
+//! `migrator` only runs against Valkey. Region blobs in an older container version
+//! are still read, but each is only rewritten in the current one when next drawn on.
+
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::Arc;
+
+use common::valkey::Keyspace;
+use common::IndexerHeartbeat;
+use redb::{Database, ReadableTable, TableDefinition};
+
+use super::{
+    parse_color, parse_position, parse_region, AccountStats, Batch, BoardStore, EndedSeason,
+    LeaderboardWindow, Op, RegionCoords, RegionSummary, SeasonSummary, REGION_COLOR_FIELD,
+};
+use crate::{Error, Result};
+
+const DB_FILE: &str = "board.redb";
+
+type Def = TableDefinition<'static, &'static [u8], &'static [u8]>;
+type ReadTables = Tables<redb::ReadOnlyTable<&'static [u8], &'static [u8]>>;
+type WriteTables<'tx> = Tables<redb::Table<'tx, &'static [u8], &'static [u8]>>;
+
+const STRINGS: Def = TableDefinition::new("strings");
+const HASHES: Def = TableDefinition::new("hashes");
+const SETS: Def = TableDefinition::new("sets");
+/// Sorted set members to their scores.
+const ZSETS: Def = TableDefinition::new("zsets");
+/// Sorted set entries in score order; the values are empty.
+const ZINDEX: Def = TableDefinition::new("zindex");
+/// Member count of each sorted set.
+const ZCARD: Def = TableDefinition::new("zcard");
+/// Wall-clock expiry in ms of keys written with `Op::ExpireAt`.
+const EXPIRES: Def = TableDefinition::new("expires");
+
+const TABLES: [Def; 7] = [STRINGS, HASHES, SETS, ZSETS, ZINDEX, ZCARD, EXPIRES];
+
+pub struct EmbeddedStore {
+    db: Arc<Database>,
+}
+
+impl EmbeddedStore {
+    /// Open the store in `dir`, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let db = Database::create(dir.join(DB_FILE)).map_err(std::io::Error::other)?;
+        // Create the tables up front so read transactions can always open them
+        let tx = db.begin_write().map_err(std::io::Error::other)?;
+        for table in TABLES {
+            tx.open_table(table).map_err(std::io::Error::other)?;
+        }
+        tx.commit().map_err(std::io::Error::other)?;
+        tracing::info!("Opened embedded store in {}", dir.display());
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// Run `f` on a snapshot of the store.
+    async fn read<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&ReadTables) -> redb::Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            let tx = db.begin_read().map_err(storage_error)?;
+            let tables =
+                Tables::open(now_ms(), |def| tx.open_table(def)).map_err(storage_error)?;
+            f(&tables).map_err(storage_error)
+        })
+        .await
+        .map_err(storage_error)?
+    }
+
+    /// Run `f` in a write transaction, committed if it succeeds.
+    async fn write<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut WriteTables<'_>) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            let tx = db.begin_write().map_err(storage_error)?;
+            let out = {
+                let mut tables =
+                    Tables::open(now_ms(), |def| tx.open_table(def)).map_err(storage_error)?;
+                tables.purge_expired().map_err(storage_error)?;
+                f(&mut tables)?
+            };
+            tx.commit().map_err(storage_error)?;
+            Ok(out)
+        })
+        .await
+        .map_err(storage_error)?
+    }
+}
+
+fn storage_error(e: impl std::fmt::Display) -> Error {
+    Error::Storage(e.to_string())
+}
+
+/// The store's tables in one transaction, as of `now_ms`: keys expired by then read
+/// as absent.
+struct Tables<T> {
+    now_ms: u64,
+    strings: T,
+    hashes: T,
+    sets: T,
+    zsets: T,
+    zindex: T,
+    zcard: T,
+    expires: T,
+}
+
+impl<T> Tables<T> {
+    fn open<E>(
+        now_ms: u64,
+        mut open: impl FnMut(Def) -> std::result::Result<T, E>,
+    ) -> std::result::Result<Self, E> {
+        Ok(Self {
+            now_ms,
+            strings: open(STRINGS)?,
+            hashes: open(HASHES)?,
+            sets: open(SETS)?,
+            zsets: open(ZSETS)?,
+            zindex: open(ZINDEX)?,
+            zcard: open(ZCARD)?,
+            expires: open(EXPIRES)?,
+        })
+    }
+}
+
+/// `key \0 field`: the entry of a field or member of `key`.
+fn entry_key(key: &str, field: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(key.len() + 1 + field.len());
+    out.extend_from_slice(key.as_bytes());
+    out.push(0);
+    out.extend_from_slice(field);
+    out
+}
+
+/// Bounds of every entry of `key`.
+fn key_range(key: &str) -> (Vec<u8>, Vec<u8>) {
+    let mut end = key.as_bytes().to_vec();
+    end.push(1);
+    (entry_key(key, b""), end)
+}
+
+/// A score as bytes that sort in the same order as the floats.
+fn score_bytes(score: f64) -> [u8; 8] {
+    let bits = score.to_bits();
+    let ordered = if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | 1 << 63
+    };
+    ordered.to_be_bytes()
+}
+
+fn score_from_bytes(bytes: &[u8]) -> f64 {
+    let ordered = u64::from_be_bytes(bytes.try_into().unwrap());
+    let bits = if ordered >> 63 == 1 {
+        ordered & !(1 << 63)
+    } else {
+        !ordered
+    };
+    f64::from_bits(bits)
+}
+
+fn index_key(key: &str, score: f64, member: &[u8]) -> Vec<u8> {
+    let mut out = entry_key(key, &score_bytes(score));
+    out.extend_from_slice(member);
+    out
+}
+
+/// Bounds of the index entries of `key` scored within `[min, max]`.
+fn score_range(key: &str, min: f64, max: f64) -> (Vec<u8>, Vec<u8>) {
+    let start = entry_key(key, &score_bytes(min));
+    let end = match u64::from_be_bytes(score_bytes(max)).checked_add(1) {
+        Some(next) => entry_key(key, &next.to_be_bytes()),
+        None => key_range(key).1,
+    };
+    (start, end)
+}
+
+fn text(bytes: &[u8]) -> Option<&str> {
+    std::str::from_utf8(bytes).ok()
+}
+
+impl<T: ReadableTable<&'static [u8], &'static [u8]>> Tables<T> {
+    /// Whether `key` has expired. Writes purge expired keys first, but reads must not
+    /// serve them in the meantime.
+    fn expired(&self, key: &str) -> redb::Result<bool> {
+        let at = self.expires.get(key.as_bytes())?;
+        Ok(at.is_some_and(|at| u64::from_le_bytes(at.value().try_into().unwrap()) <= self.now_ms))
+    }
+
+    fn string(&self, key: &str) -> redb::Result<Option<Vec<u8>>> {
+        if self.expired(key)? {
+            return Ok(None);
+        }
+        Ok(self
+            .strings
+            .get(key.as_bytes())?
+            .map(|v| v.value().to_vec()))
+    }
+
+    fn string_parsed<P: std::str::FromStr>(&self, key: &str) -> redb::Result<Option<P>> {
+        Ok(self.string(key)?.and_then(|v| text(&v)?.parse().ok()))
+    }
+
+    fn hget(&self, key: &str, field: &str) -> redb::Result<Option<String>> {
+        if self.expired(key)? {
+            return Ok(None);
+        }
+        let value = self
+            .hashes
+            .get(entry_key(key, field.as_bytes()).as_slice())?;
+        Ok(value.and_then(|v| text(v.value()).map(String::from)))
+    }
+
+    fn hget_parsed<P: std::str::FromStr>(&self, key: &str, field: &str) -> redb::Result<Option<P>> {
+        Ok(self.hget(key, field)?.and_then(|v| v.parse().ok()))
+    }
+
+    /// Fields and values of a hash.
+    fn hgetall(&self, key: &str) -> redb::Result<Vec<(String, String)>> {
+        let (start, end) = key_range(key);
+        let prefix = start.len();
+        let mut out = Vec::new();
+        if self.expired(key)? {
+            return Ok(out);
+        }
+        for entry in self.hashes.range(start.as_slice()..end.as_slice())? {
+            let (field, value) = entry?;
+            if let (Some(field), Some(value)) =
+                (text(&field.value()[prefix..]), text(value.value()))
+            {
+                out.push((field.to_string(), value.to_string()));
+            }
+        }
+        Ok(out)
+    }
+
+    fn sismember(&self, key: &str, member: &str) -> redb::Result<bool> {
+        if self.expired(key)? {
+            return Ok(false);
+        }
+        Ok(self
+            .sets
+            .get(entry_key(key, member.as_bytes()).as_slice())?
+            .is_some())
+    }
+
+    fn smembers(&self, key: &str) -> redb::Result<Vec<String>> {
+        let (start, end) = key_range(key);
+        let prefix = start.len();
+        let mut out = Vec::new();
+        if self.expired(key)? {
+            return Ok(out);
+        }
+        for entry in self.sets.range(start.as_slice()..end.as_slice())? {
+            let (member, _) = entry?;
+            out.extend(text(&member.value()[prefix..]).map(String::from));
+        }
+        Ok(out)
+    }
+
+    fn zscore(&self, key: &str, member: &str) -> redb::Result<Option<f64>> {
+        if self.expired(key)? {
+            return Ok(None);
+        }
+        let score = self
+            .zsets
+            .get(entry_key(key, member.as_bytes()).as_slice())?;
+        Ok(score.map(|s| f64::from_le_bytes(s.value().try_into().unwrap())))
+    }
+
+    fn zcard(&self, key: &str) -> redb::Result<u64> {
+        if self.expired(key)? {
+            return Ok(0);
+        }
+        let card = self.zcard.get(key.as_bytes())?;
+        Ok(card.map_or(0, |c| u64::from_le_bytes(c.value().try_into().unwrap())))
+    }
+
+    /// Members and scores of a sorted set within `bounds` of its index, in score order
+    /// (ties by member), or reversed as in ZREVRANGE.
+    fn zscan(
+        &self,
+        key: &str,
+        bounds: (Bound<&[u8]>, Bound<&[u8]>),
+        rev: bool,
+        mut visit: impl FnMut(&str, f64) -> bool,
+    ) -> redb::Result<()> {
+        if self.expired(key)? {
+            return Ok(());
+        }
+        let prefix = key.len() + 1;
+        let range = self.zindex.range::<&[u8]>(bounds)?;
+        let mut step = |entry: redb::Result<_>| -> redb::Result<bool> {
+            let (index, _): (redb::AccessGuard<&[u8]>, _) = entry?;
+            let index = index.value();
+            let score = score_from_bytes(&index[prefix..prefix + 8]);
+            Ok(text(&index[prefix + 8..]).is_none_or(|member| visit(member, score)))
+        };
+        if rev {
+            for entry in range.rev() {
+                if !step(entry)? {
+                    break;
+                }
+            }
+        } else {
+            for entry in range {
+                if !step(entry)? {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Members and scores scored within `[min, max]`, lowest first.
+    fn zrange_by_score(&self, key: &str, min: f64, max: f64) -> redb::Result<Vec<(String, f64)>> {
+        let (start, end) = score_range(key, min, max);
+        let mut out = Vec::new();
+        let bounds = (
+            Bound::Included(start.as_slice()),
+            Bound::Excluded(end.as_slice()),
+        );
+        self.zscan(key, bounds, false, |member, score| {
+            out.push((member.to_string(), score));
+            true
+        })?;
+        Ok(out)
+    }
+
+    /// Up to `limit` members and scores after the first `offset`, highest first.
+    fn zrevrange(
+        &self,
+        key: &str,
+        offset: usize,
+        limit: usize,
+    ) -> redb::Result<Vec<(String, f64)>> {
+        let (start, end) = key_range(key);
+        let mut out = Vec::new();
+        let mut skip = offset;
+        let bounds = (
+            Bound::Included(start.as_slice()),
+            Bound::Excluded(end.as_slice()),
+        );
+        self.zscan(key, bounds, true, |member, score| {
+            if out.len() >= limit {
+                return false;
+            }
+            if skip > 0 {
+                skip -= 1;
+            } else {
+                out.push((member.to_string(), score));
+            }
+            true
+        })?;
+        Ok(out)
+    }
+
+    /// Rank of a member by descending score, and its score.
+    fn zrevrank(&self, key: &str, member: &str) -> redb::Result<Option<(u64, f64)>> {
+        let Some(score) = self.zscore(key, member)? else {
+            return Ok(None);
+        };
+        let entry = index_key(key, score, member.as_bytes());
+        let end = key_range(key).1;
+        let above = self
+            .zindex
+            .range::<&[u8]>((
+                Bound::Excluded(entry.as_slice()),
+                Bound::Excluded(end.as_slice()),
+            ))?
+            .count() as u64;
+        Ok(Some((above, score)))
+    }
+
+    /// Owner ids and scores of a leaderboard, highest first.
+    fn window_leaderboard(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+    ) -> redb::Result<Vec<(u32, i64)>> {
+        let mut totals: std::collections::HashMap<String, f64> = Default::default();
+        for bucket in window.buckets(keys, now_ms) {
+            for (member, score) in
+                self.zrange_by_score(&bucket, f64::NEG_INFINITY, f64::INFINITY)?
+            {
+                *totals.entry(member).or_default() += score;
+            }
+        }
+        let mut totals: Vec<(String, f64)> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+        Ok(totals
+            .into_iter()
+            .filter_map(|(member, score)| Some((member.parse().ok()?, score as i64)))
+            .collect())
+    }
+}
+
+impl WriteTables<'_> {
+    fn apply(&mut self, op: &Op) -> redb::Result<()> {
+        match op {
+            Op::Set { key, value } => {
+                self.strings.insert(key.as_bytes(), value.as_slice())?;
+            }
+            Op::HSet { key, field, value } => {
+                let entry = entry_key(key, field.as_bytes());
+                self.hashes.insert(entry.as_slice(), value.as_bytes())?;
+            }
+            Op::HSetNx { key, field, value } => {
+                let entry = entry_key(key, field.as_bytes());
+                if self.hashes.get(entry.as_slice())?.is_none() {
+                    self.hashes.insert(entry.as_slice(), value.as_bytes())?;
+                }
+            }
+            Op::HIncrBy { key, field, by } => {
+                let value: i64 = self.hget_parsed(key, field)?.unwrap_or(0);
+                let entry = entry_key(key, field.as_bytes());
+                self.hashes
+                    .insert(entry.as_slice(), (value + by).to_string().as_bytes())?;
+            }
+            Op::SAdd { key, members } => {
+                for member in members {
+                    self.sets
+                        .insert(entry_key(key, member.as_bytes()).as_slice(), &[][..])?;
+                }
+            }
+            Op::ZAdd { key, members } => {
+                for (score, member) in members {
+                    self.zset(key, member, *score)?;
+                }
+            }
+            Op::ZIncrBy { key, member, by } => {
+                let score = self.zscore(key, member)?.unwrap_or(0.0) + *by as f64;
+                self.zset(key, member, score)?;
+            }
+            Op::ZRem { key, members } => {
+                for member in members {
+                    self.zrem(key, member)?;
+                }
+            }
+            Op::ZRemRangeByScore { key, min, max } => {
+                for (member, _) in self.zrange_by_score(key, *min, *max)? {
+                    self.zrem(key, &member)?;
+                }
+            }
+            Op::ExpireAt { key, at_ms } => {
+                self.expires
+                    .insert(key.as_bytes(), at_ms.to_le_bytes().as_slice())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn zset(&mut self, key: &str, member: &str, score: f64) -> redb::Result<()> {
+        let entry = entry_key(key, member.as_bytes());
+        let old = self
+            .zsets
+            .insert(entry.as_slice(), score.to_le_bytes().as_slice())?
+            .map(|old| f64::from_le_bytes(old.value().try_into().unwrap()));
+        match old {
+            Some(old) => {
+                self.zindex
+                    .remove(index_key(key, old, member.as_bytes()).as_slice())?;
+            }
+            None => self.add_zcard(key, 1)?,
+        }
+        self.zindex
+            .insert(index_key(key, score, member.as_bytes()).as_slice(), &[][..])?;
+        Ok(())
+    }
+
+    fn zrem(&mut self, key: &str, member: &str) -> redb::Result<()> {
+        let entry = entry_key(key, member.as_bytes());
+        let removed = self
+            .zsets
+            .remove(entry.as_slice())?
+            .map(|old| f64::from_le_bytes(old.value().try_into().unwrap()));
+        if let Some(old) = removed {
+            self.zindex
+                .remove(index_key(key, old, member.as_bytes()).as_slice())?;
+            self.add_zcard(key, -1)?;
+        }
+        Ok(())
+    }
+
+    fn add_zcard(&mut self, key: &str, by: i64) -> redb::Result<()> {
+        let card = self.zcard(key)?.saturating_add_signed(by);
+        if card == 0 {
+            self.zcard.remove(key.as_bytes())?;
+        } else {
+            self.zcard
+                .insert(key.as_bytes(), card.to_le_bytes().as_slice())?;
+        }
+        Ok(())
+    }
+
+    /// Delete every key whose expiry has passed.
+    fn purge_expired(&mut self) -> redb::Result<()> {
+        let mut expired = Vec::new();
+        for entry in self.expires.iter()? {
+            let (key, at) = entry?;
+            if u64::from_le_bytes(at.value().try_into().unwrap()) <= self.now_ms {
+                expired.push(key.value().to_vec());
+            }
+        }
+        for key in expired {
+            self.expires.remove(key.as_slice())?;
+            let Some(key) = text(&key).map(String::from) else {
+                continue;
+            };
+            self.strings.remove(key.as_bytes())?;
+            let (start, end) = key_range(&key);
+            for table in [
+                &mut self.hashes,
+                &mut self.sets,
+                &mut self.zsets,
+                &mut self.zindex,
+            ] {
+                table.retain_in(start.as_slice()..end.as_slice(), |_, _| false)?;
+            }
+            self.zcard.remove(key.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl BoardStore for EmbeddedStore {
+    async fn current_season(&self, keys: &Keyspace) -> Result<Option<u32>> {
+        let key = keys.current_season();
+        self.read(move |t| t.string_parsed(&key)).await
+    }
+
+    async fn last_applied_event(&self, keys: &Keyspace) -> Result<Option<(u64, u32)>> {
+        let key = keys.last_applied_event();
+        self.read(move |t| Ok(t.string(&key)?.and_then(|v| parse_position(text(&v)?))))
+            .await
+    }
+
+    async fn region(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Vec<u8>> {
+        let key = keys.region(rx, ry);
+        self.read(move |t| Ok(t.string(&key)?.unwrap_or_default()))
+            .await
+    }
+
+    async fn region_updated_ms(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Option<u64>> {
+        let key = keys.region_meta(rx, ry);
+        self.read(move |t| t.hget_parsed(&key, "last_updated"))
+            .await
+    }
+
+    async fn pixel_timestamp(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        lx: usize,
+        ly: usize,
+    ) -> Result<Option<u64>> {
+        let key = keys.pixel_ts(rx, ry);
+        let member = format!("{lx},{ly}");
+        self.read(move |t| Ok(t.zscore(&key, &member)?.map(|ts| ts as u64)))
+            .await
+    }
+
+    async fn pixel_timestamps(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        since_ms: u64,
+    ) -> Result<Vec<(u64, u64, u64)>> {
+        let key = keys.pixel_ts(rx, ry);
+        self.read(move |t| {
+            let mut entries: Vec<(u64, u64, u64)> = t
+                .zrange_by_score(&key, since_ms as f64, f64::INFINITY)?
+                .into_iter()
+                .filter_map(|(member, score)| {
+                    let (lx, ly) = member.split_once(',')?;
+                    Some((lx.parse().ok()?, ly.parse().ok()?, score as u64))
+                })
+                .collect();
+            entries.sort_by_key(|&(lx, ly, ts)| (ts, lx, ly));
+            Ok(entries)
+        })
+        .await
+    }
+
+    async fn is_region_open(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool> {
+        let key = keys.open_regions();
+        self.read(move |t| t.sismember(&key, &format!("{rx}:{ry}")))
+            .await
+    }
+
+    async fn open_regions(&self, keys: &Keyspace) -> Result<Vec<RegionCoords>> {
+        let key = keys.open_regions();
+        self.read(move |t| {
+            Ok(t.smembers(&key)?
+                .iter()
+                .filter_map(|m| parse_region(m))
+                .collect())
+        })
+        .await
+    }
+
+    async fn region_pixel_count(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<i64> {
+        let key = keys.region_pixel_count();
+        self.read(move |t| Ok(t.hget_parsed(&key, &format!("{rx}:{ry}"))?.unwrap_or(0)))
+            .await
+    }
+
+    async fn region_owners(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        limit: usize,
+    ) -> Result<Vec<(u32, i64)>> {
+        let key = keys.region_owners(rx, ry);
+        self.read(move |t| {
+            Ok(t.zrevrange(&key, 0, limit)?
+                .into_iter()
+                .filter_map(|(member, score)| Some((member.parse().ok()?, score as i64)))
+                .collect())
+        })
+        .await
+    }
+
+    async fn region_summaries(
+        &self,
+        keys: &Keyspace,
+        regions: &[RegionCoords],
+    ) -> Result<Vec<RegionSummary>> {
+        let keys = keys.clone();
+        let regions = regions.to_vec();
+        self.read(move |t| {
+            let mut summaries = Vec::with_capacity(regions.len());
+            for (rx, ry) in regions {
+                let top = t.zrevrange(&keys.region_owners(rx, ry), 0, 1)?;
+                let color = t.hget(&keys.region_meta(rx, ry), REGION_COLOR_FIELD)?;
+                summaries.push(RegionSummary {
+                    top_owner: top
+                        .first()
+                        .and_then(|(member, score)| Some((member.parse().ok()?, *score as i64))),
+                    color: color.as_deref().and_then(parse_color),
+                });
+            }
+            Ok(summaries)
+        })
+        .await
+    }
+
+    async fn has_region_owners(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool> {
+        let key = keys.region_owners(rx, ry);
+        self.read(move |t| Ok(t.zcard(&key)? > 0)).await
+    }
+
+    async fn resolve_owner_id(
+        &self,
+        keys: &Keyspace,
+        account_id: &str,
+        max_owner_id: u32,
+    ) -> Result<u32> {
+        let keys = keys.clone();
+        let account_id = account_id.to_string();
+        self.write(move |t| {
+            let (to_id, to_account) = (keys.account_to_id(), keys.id_to_account());
+            if let Some(id) = t.hget_parsed(&to_id, &account_id).map_err(storage_error)? {
+                return Ok(id);
+            }
+            // Same scheme as the Valkey allocation script: the counter starts from the
+            // highest id in use, and ids claimed some other way are skipped
+            let counter = t
+                .string_parsed::<u32>(&keys.owner_id_counter())
+                .map_err(storage_error)?;
+            let mut id = match counter {
+                Some(counter) => counter,
+                None => t
+                    .hgetall(&to_account)
+                    .map_err(storage_error)?
+                    .iter()
+                    .filter_map(|(id, _)| id.parse().ok())
+                    .max()
+                    .unwrap_or(0),
+            };
+            loop {
+                id += 1;
+                if id > max_owner_id {
+                    return Err(Error::OwnerIdsExhausted { account_id });
+                }
+                if t.hget(&to_account, &id.to_string())
+                    .map_err(storage_error)?
+                    .is_none()
+                {
+                    break;
+                }
+            }
+
+            let ops = [
+                Op::Set {
+                    key: keys.owner_id_counter(),
+                    value: id.to_string().into_bytes(),
+                },
+                Op::HSet {
+                    key: to_account,
+                    field: id.to_string(),
+                    value: account_id.clone(),
+                },
+                Op::HSet {
+                    key: to_id,
+                    field: account_id,
+                    value: id.to_string(),
+                },
+            ];
+            for op in &ops {
+                t.apply(op).map_err(storage_error)?;
+            }
+            Ok(id)
+        })
+        .await
+    }
+
+    async fn owner_id(&self, keys: &Keyspace, account_id: &str) -> Result<Option<u32>> {
+        let key = keys.account_to_id();
+        let account_id = account_id.to_string();
+        self.read(move |t| t.hget_parsed(&key, &account_id)).await
+    }
+
+    async fn account_ids(&self, keys: &Keyspace, owner_ids: &[u32]) -> Result<Vec<Option<String>>> {
+        let key = keys.id_to_account();
+        let owner_ids = owner_ids.to_vec();
+        self.read(move |t| {
+            owner_ids
+                .iter()
+                .map(|id| t.hget(&key, &id.to_string()))
+                .collect()
+        })
+        .await
+    }
+
+    async fn account_pixel_counts(&self, keys: &Keyspace) -> Result<Vec<(u32, i64)>> {
+        let key = keys.account_pixel_count();
+        self.read(move |t| {
+            Ok(t.hgetall(&key)?
+                .into_iter()
+                .filter_map(|(id, count)| Some((id.parse().ok()?, count.parse().ok()?)))
+                .collect())
+        })
+        .await
+    }
+
+    async fn account_stats(
+        &self,
+        keys: &Keyspace,
+        owner_id: u32,
+        claimable_since_ms: u64,
+    ) -> Result<AccountStats> {
+        let keys = keys.clone();
+        self.read(move |t| {
+            let meta = keys.account_meta(owner_id);
+            let (start, end) = score_range(
+                &keys.account_pixel_ts(owner_id),
+                claimable_since_ms as f64,
+                f64::INFINITY,
+            );
+            let claimable = t.zindex.range(start.as_slice()..end.as_slice())?.count();
+            Ok(AccountStats {
+                pixel_count: t
+                    .hget_parsed(&keys.account_pixel_count(), &owner_id.to_string())?
+                    .unwrap_or(0),
+                claimable_pixel_count: claimable as i64,
+                first_draw_ms: t.hget_parsed(&meta, "first_draw_ms")?,
+                last_draw_ms: t.hget_parsed(&meta, "last_draw_ms")?,
+                regions: t
+                    .smembers(&keys.account_regions(owner_id))?
+                    .iter()
+                    .filter_map(|m| parse_region(m))
+                    .collect(),
+            })
+        })
+        .await
+    }
+
+    async fn leaderboard(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<(u32, i64)>, u64)> {
+        let keys = keys.clone();
+        self.read(move |t| {
+            if let LeaderboardWindow::All = window {
+                let key = keys.leaderboard();
+                let entries = t
+                    .zrevrange(&key, offset, limit)?
+                    .into_iter()
+                    .filter_map(|(member, score)| Some((member.parse().ok()?, score as i64)))
+                    .collect();
+                return Ok((entries, t.zcard(&key)?));
+            }
+            let entries = t.window_leaderboard(&keys, window, now_ms)?;
+            let total = entries.len() as u64;
+            Ok((
+                entries.into_iter().skip(offset).take(limit).collect(),
+                total,
+            ))
+        })
+        .await
+    }
+
+    async fn leaderboard_rank(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        owner_id: u32,
+    ) -> Result<(Option<u64>, Option<i64>)> {
+        let keys = keys.clone();
+        self.read(move |t| {
+            if let LeaderboardWindow::All = window {
+                let rank = t.zrevrank(&keys.leaderboard(), &owner_id.to_string())?;
+                return Ok((rank.map(|(r, _)| r), rank.map(|(_, score)| score as i64)));
+            }
+            let entries = t.window_leaderboard(&keys, window, now_ms)?;
+            Ok(match entries.iter().position(|(id, _)| *id == owner_id) {
+                Some(rank) => (Some(rank as u64), Some(entries[rank].1)),
+                None => (None, None),
+            })
+        })
+        .await
+    }
+
+    async fn ended_seasons(&self, keys: &Keyspace) -> Result<Vec<EndedSeason>> {
+        let keys = keys.clone();
+        self.read(move |t| {
+            let mut ended: Vec<(u32, u64)> = t
+                .zrange_by_score(&keys.ended_seasons(), f64::NEG_INFINITY, f64::INFINITY)?
+                .into_iter()
+                .filter_map(|(season, ts)| Some((season.parse().ok()?, ts as u64)))
+                .collect();
+            ended.sort_by_key(|&(season, ts)| (ts, season));
+            let mut seasons = Vec::with_capacity(ended.len());
+            for (season, ended_ms) in ended {
+                let meta = keys.for_season(season).season_meta();
+                let field = |name: &str| -> redb::Result<i64> {
+                    Ok(t.hget_parsed(&meta, name)?.unwrap_or(0))
+                };
+                seasons.push(EndedSeason {
+                    season,
+                    summary: SeasonSummary {
+                        ended_block_height: field("ended_block_height")? as u64,
+                        ended_timestamp_ms: ended_ms,
+                        open_regions: field("open_regions")? as u64,
+                        top_owner_id: field("top_owner_id")? as u32,
+                        top_pixel_count: field("top_pixel_count")? as u64,
+                    },
+                });
+            }
+            Ok(seasons)
+        })
+        .await
+    }
+
+    async fn draw_events(&self, keys: &Keyspace, since_ms: u64) -> Result<Vec<String>> {
+        let key = keys.draw_events();
+        self.read(move |t| {
+            Ok(t.zrange_by_score(&key, since_ms as f64, f64::INFINITY)?
+                .into_iter()
+                .map(|(message, _)| message)
+                .collect())
+        })
+        .await
+    }
+
+    async fn last_processed_block(&self, keys: &Keyspace) -> Result<Option<(u64, Option<String>)>> {
+        let (height_key, hash_key) = (
+            keys.last_processed_block(),
+            keys.last_processed_block_hash(),
+        );
+        self.read(move |t| {
+            let Some(height) = t.string_parsed(&height_key)? else {
+                return Ok(None);
+            };
+            let hash = t.string(&hash_key)?.and_then(|h| String::from_utf8(h).ok());
+            Ok(Some((height, hash)))
+        })
+        .await
+    }
+
+    async fn indexer_heartbeat(&self, keys: &Keyspace) -> Result<Option<IndexerHeartbeat>> {
+        let key = keys.indexer_heartbeat();
+        self.read(move |t| {
+            let fields: std::collections::HashMap<String, u64> = t
+                .hgetall(&key)?
+                .into_iter()
+                .filter_map(|(field, value)| Some((field, value.parse().ok()?)))
+                .collect();
+            Ok(IndexerHeartbeat::from_fields(&fields))
+        })
+        .await
+    }
+
+    async fn commit(&self, batch: Batch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.write(move |t| {
+            for op in batch.ops() {
+                t.apply(op).map_err(storage_error)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
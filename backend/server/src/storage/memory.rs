@@ -0,0 +1,541 @@
+use common::valkey::Keyspace;
+use common::IndexerHeartbeat;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use super::{
+    parse_color, parse_position, parse_region, AccountStats, Batch, BoardStore, EndedSeason,
+    LeaderboardWindow, Op, RegionCoords, RegionSummary, SeasonSummary, REGION_COLOR_FIELD,
+};
+use crate::{Error, Result};
+
+/// The board's state held in process, for tests; nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<State>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&State) -> T) -> T {
+        f(&self.state.lock().unwrap())
+    }
+
+    fn write(state: &mut State, ops: &[Op]) {
+        state.purge_expired(now_ms());
+        for op in ops {
+            state.apply(op);
+        }
+    }
+}
+
+/// Every key the board has written, by data structure, as Valkey would hold them.
+#[derive(Debug, Default)]
+struct State {
+    strings: HashMap<String, Vec<u8>>,
+    hashes: HashMap<String, HashMap<String, String>>,
+    sets: HashMap<String, BTreeSet<String>>,
+    zsets: HashMap<String, HashMap<String, f64>>,
+    /// Wall-clock expiry in ms, for keys written with `Op::ExpireAt`.
+    expires: HashMap<String, u64>,
+}
+
+impl State {
+    fn apply(&mut self, op: &Op) {
+        match op {
+            Op::Set { key, value } => {
+                self.strings.insert(key.clone(), value.clone());
+            }
+            Op::HSet { key, field, value } => {
+                self.hashes
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(field.clone(), value.clone());
+            }
+            Op::HSetNx { key, field, value } => {
+                let hash = self.hashes.entry(key.clone()).or_default();
+                hash.entry(field.clone()).or_insert_with(|| value.clone());
+            }
+            Op::HIncrBy { key, field, by } => {
+                let hash = self.hashes.entry(key.clone()).or_default();
+                let value = hash.entry(field.clone()).or_insert_with(|| "0".into());
+                *value = (value.parse::<i64>().unwrap_or(0) + by).to_string();
+            }
+            Op::SAdd { key, members } => {
+                self.sets
+                    .entry(key.clone())
+                    .or_default()
+                    .extend(members.iter().cloned());
+            }
+            Op::ZAdd { key, members } => {
+                let zset = self.zsets.entry(key.clone()).or_default();
+                for (score, member) in members {
+                    zset.insert(member.clone(), *score);
+                }
+            }
+            Op::ZIncrBy { key, member, by } => {
+                *self
+                    .zsets
+                    .entry(key.clone())
+                    .or_default()
+                    .entry(member.clone())
+                    .or_default() += *by as f64;
+            }
+            Op::ZRem { key, members } => {
+                if let Some(zset) = self.zsets.get_mut(key) {
+                    for member in members {
+                        zset.remove(member);
+                    }
+                }
+            }
+            Op::ZRemRangeByScore { key, min, max } => {
+                if let Some(zset) = self.zsets.get_mut(key) {
+                    zset.retain(|_, score| *score < *min || *score > *max);
+                }
+            }
+            Op::ExpireAt { key, at_ms } => {
+                self.expires.insert(key.clone(), *at_ms);
+            }
+        }
+    }
+
+    fn purge_expired(&mut self, now_ms: u64) {
+        let expired: Vec<String> = self
+            .expires
+            .iter()
+            .filter(|(_, at)| **at <= now_ms)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.expires.remove(&key);
+            self.strings.remove(&key);
+            self.hashes.remove(&key);
+            self.sets.remove(&key);
+            self.zsets.remove(&key);
+        }
+    }
+
+    fn string(&self, key: &str) -> Option<&[u8]> {
+        self.strings.get(key).map(Vec::as_slice)
+    }
+
+    fn hget(&self, key: &str, field: &str) -> Option<&str> {
+        self.hashes.get(key)?.get(field).map(String::as_str)
+    }
+
+    fn hget_parsed<T: std::str::FromStr>(&self, key: &str, field: &str) -> Option<T> {
+        self.hget(key, field)?.parse().ok()
+    }
+
+    fn set_members(&self, key: &str) -> impl Iterator<Item = &String> {
+        self.sets.get(key).into_iter().flatten()
+    }
+
+    fn zscore(&self, key: &str, member: &str) -> Option<f64> {
+        self.zsets.get(key)?.get(member).copied()
+    }
+
+    /// Members of a sorted set by descending score, ties by descending member as
+    /// in ZREVRANGE.
+    fn zrev(&self, key: &str) -> Vec<(&str, f64)> {
+        let mut entries: Vec<(&str, f64)> = self
+            .zsets
+            .get(key)
+            .into_iter()
+            .flatten()
+            .map(|(member, score)| (member.as_str(), *score))
+            .collect();
+        entries.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.0.cmp(a.0)));
+        entries
+    }
+
+    /// Owner ids and scores of a leaderboard, highest first.
+    fn leaderboard(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+    ) -> Vec<(u32, i64)> {
+        let entries: Vec<(String, f64)> = match window {
+            LeaderboardWindow::All => self
+                .zrev(&keys.leaderboard())
+                .into_iter()
+                .map(|(member, score)| (member.to_string(), score))
+                .collect(),
+            window => {
+                let mut totals: HashMap<String, f64> = HashMap::new();
+                for bucket in window.buckets(keys, now_ms) {
+                    for (member, score) in self.zsets.get(&bucket).into_iter().flatten() {
+                        *totals.entry(member.clone()).or_default() += score;
+                    }
+                }
+                let mut totals: Vec<(String, f64)> = totals.into_iter().collect();
+                totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+                totals
+            }
+        };
+        entries
+            .into_iter()
+            .filter_map(|(member, score)| Some((member.parse().ok()?, score as i64)))
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl BoardStore for MemoryStore {
+    async fn current_season(&self, keys: &Keyspace) -> Result<Option<u32>> {
+        Ok(self.read(|s| {
+            let season = s.string(&keys.current_season())?;
+            std::str::from_utf8(season).ok()?.parse().ok()
+        }))
+    }
+
+    async fn last_applied_event(&self, keys: &Keyspace) -> Result<Option<(u64, u32)>> {
+        Ok(self.read(|s| {
+            let last = s.string(&keys.last_applied_event())?;
+            parse_position(std::str::from_utf8(last).ok()?)
+        }))
+    }
+
+    async fn region(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Vec<u8>> {
+        Ok(self.read(|s| s.string(&keys.region(rx, ry)).unwrap_or_default().to_vec()))
+    }
+
+    async fn region_updated_ms(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Option<u64>> {
+        Ok(self.read(|s| s.hget_parsed(&keys.region_meta(rx, ry), "last_updated")))
+    }
+
+    async fn pixel_timestamp(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        lx: usize,
+        ly: usize,
+    ) -> Result<Option<u64>> {
+        let member = format!("{lx},{ly}");
+        Ok(self.read(|s| {
+            s.zscore(&keys.pixel_ts(rx, ry), &member)
+                .map(|ts| ts as u64)
+        }))
+    }
+
+    async fn pixel_timestamps(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        since_ms: u64,
+    ) -> Result<Vec<(u64, u64, u64)>> {
+        Ok(self.read(|s| {
+            let mut entries: Vec<(u64, u64, u64)> = s
+                .zsets
+                .get(&keys.pixel_ts(rx, ry))
+                .into_iter()
+                .flatten()
+                .filter(|(_, score)| **score >= since_ms as f64)
+                .filter_map(|(member, score)| {
+                    let (lx, ly) = member.split_once(',')?;
+                    Some((lx.parse().ok()?, ly.parse().ok()?, *score as u64))
+                })
+                .collect();
+            entries.sort_by_key(|&(lx, ly, ts)| (ts, lx, ly));
+            entries
+        }))
+    }
+
+    async fn is_region_open(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool> {
+        let member = format!("{rx}:{ry}");
+        Ok(self.read(|s| s.set_members(&keys.open_regions()).any(|m| *m == member)))
+    }
+
+    async fn open_regions(&self, keys: &Keyspace) -> Result<Vec<RegionCoords>> {
+        Ok(self.read(|s| {
+            s.set_members(&keys.open_regions())
+                .filter_map(|m| parse_region(m))
+                .collect()
+        }))
+    }
+
+    async fn region_pixel_count(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<i64> {
+        let field = format!("{rx}:{ry}");
+        Ok(self.read(|s| {
+            s.hget_parsed(&keys.region_pixel_count(), &field)
+                .unwrap_or(0)
+        }))
+    }
+
+    async fn region_owners(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        limit: usize,
+    ) -> Result<Vec<(u32, i64)>> {
+        Ok(self.read(|s| {
+            s.zrev(&keys.region_owners(rx, ry))
+                .into_iter()
+                .filter_map(|(member, score)| Some((member.parse().ok()?, score as i64)))
+                .take(limit)
+                .collect()
+        }))
+    }
+
+    async fn region_summaries(
+        &self,
+        keys: &Keyspace,
+        regions: &[RegionCoords],
+    ) -> Result<Vec<RegionSummary>> {
+        Ok(self.read(|s| {
+            regions
+                .iter()
+                .map(|&(rx, ry)| RegionSummary {
+                    top_owner: s
+                        .zrev(&keys.region_owners(rx, ry))
+                        .first()
+                        .and_then(|(member, score)| Some((member.parse().ok()?, *score as i64))),
+                    color: s
+                        .hget(&keys.region_meta(rx, ry), REGION_COLOR_FIELD)
+                        .and_then(parse_color),
+                })
+                .collect()
+        }))
+    }
+
+    async fn has_region_owners(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool> {
+        Ok(self.read(|s| {
+            s.zsets
+                .get(&keys.region_owners(rx, ry))
+                .is_some_and(|z| !z.is_empty())
+        }))
+    }
+
+    async fn resolve_owner_id(
+        &self,
+        keys: &Keyspace,
+        account_id: &str,
+        max_owner_id: u32,
+    ) -> Result<u32> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = state.hget_parsed(&keys.account_to_id(), account_id) {
+            return Ok(id);
+        }
+        // Same scheme as the Valkey allocation script: the counter starts from the
+        // highest id in use, and ids claimed some other way are skipped
+        let counter = state
+            .string(&keys.owner_id_counter())
+            .and_then(|c| std::str::from_utf8(c).ok()?.parse::<u32>().ok());
+        let taken = state.hashes.get(&keys.id_to_account());
+        let mut id = counter.unwrap_or_else(|| {
+            taken
+                .into_iter()
+                .flatten()
+                .filter_map(|(id, _)| id.parse().ok())
+                .max()
+                .unwrap_or(0)
+        });
+        loop {
+            id += 1;
+            if id > max_owner_id {
+                return Err(Error::OwnerIdsExhausted {
+                    account_id: account_id.to_string(),
+                });
+            }
+            if !taken.is_some_and(|t| t.contains_key(&id.to_string())) {
+                break;
+            }
+        }
+
+        let ops = [
+            Op::Set {
+                key: keys.owner_id_counter(),
+                value: id.to_string().into_bytes(),
+            },
+            Op::HSet {
+                key: keys.id_to_account(),
+                field: id.to_string(),
+                value: account_id.to_string(),
+            },
+            Op::HSet {
+                key: keys.account_to_id(),
+                field: account_id.to_string(),
+                value: id.to_string(),
+            },
+        ];
+        Self::write(&mut state, &ops);
+        Ok(id)
+    }
+
+    async fn owner_id(&self, keys: &Keyspace, account_id: &str) -> Result<Option<u32>> {
+        Ok(self.read(|s| s.hget_parsed(&keys.account_to_id(), account_id)))
+    }
+
+    async fn account_ids(&self, keys: &Keyspace, owner_ids: &[u32]) -> Result<Vec<Option<String>>> {
+        let key = keys.id_to_account();
+        Ok(self.read(|s| {
+            owner_ids
+                .iter()
+                .map(|id| s.hget(&key, &id.to_string()).map(String::from))
+                .collect()
+        }))
+    }
+
+    async fn account_pixel_counts(&self, keys: &Keyspace) -> Result<Vec<(u32, i64)>> {
+        Ok(self.read(|s| {
+            s.hashes
+                .get(&keys.account_pixel_count())
+                .into_iter()
+                .flatten()
+                .filter_map(|(id, count)| Some((id.parse().ok()?, count.parse().ok()?)))
+                .collect()
+        }))
+    }
+
+    async fn account_stats(
+        &self,
+        keys: &Keyspace,
+        owner_id: u32,
+        claimable_since_ms: u64,
+    ) -> Result<AccountStats> {
+        Ok(self.read(|s| {
+            let meta = keys.account_meta(owner_id);
+            AccountStats {
+                pixel_count: s
+                    .hget_parsed(&keys.account_pixel_count(), &owner_id.to_string())
+                    .unwrap_or(0),
+                claimable_pixel_count: s
+                    .zsets
+                    .get(&keys.account_pixel_ts(owner_id))
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, ts)| **ts >= claimable_since_ms as f64)
+                    .count() as i64,
+                first_draw_ms: s.hget_parsed(&meta, "first_draw_ms"),
+                last_draw_ms: s.hget_parsed(&meta, "last_draw_ms"),
+                regions: s
+                    .set_members(&keys.account_regions(owner_id))
+                    .filter_map(|m| parse_region(m))
+                    .collect(),
+            }
+        }))
+    }
+
+    async fn leaderboard(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<(u32, i64)>, u64)> {
+        Ok(self.read(|s| {
+            let entries = s.leaderboard(keys, window, now_ms);
+            let total = entries.len() as u64;
+            (
+                entries.into_iter().skip(offset).take(limit).collect(),
+                total,
+            )
+        }))
+    }
+
+    async fn leaderboard_rank(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        owner_id: u32,
+    ) -> Result<(Option<u64>, Option<i64>)> {
+        Ok(self.read(|s| {
+            let entries = s.leaderboard(keys, window, now_ms);
+            match entries.iter().position(|(id, _)| *id == owner_id) {
+                Some(rank) => (Some(rank as u64), Some(entries[rank].1)),
+                None => (None, None),
+            }
+        }))
+    }
+
+    async fn ended_seasons(&self, keys: &Keyspace) -> Result<Vec<EndedSeason>> {
+        Ok(self.read(|s| {
+            let mut ended: Vec<(u32, u64)> = s
+                .zsets
+                .get(&keys.ended_seasons())
+                .into_iter()
+                .flatten()
+                .filter_map(|(season, ts)| Some((season.parse().ok()?, *ts as u64)))
+                .collect();
+            ended.sort_by_key(|&(season, ts)| (ts, season));
+            ended
+                .into_iter()
+                .map(|(season, ended_ms)| {
+                    let meta = keys.for_season(season).season_meta();
+                    let field = |name: &str| s.hget_parsed(&meta, name).unwrap_or(0);
+                    EndedSeason {
+                        season,
+                        summary: SeasonSummary {
+                            ended_block_height: field("ended_block_height"),
+                            ended_timestamp_ms: ended_ms,
+                            open_regions: field("open_regions"),
+                            top_owner_id: field("top_owner_id") as u32,
+                            top_pixel_count: field("top_pixel_count"),
+                        },
+                    }
+                })
+                .collect()
+        }))
+    }
+
+    async fn draw_events(&self, keys: &Keyspace, since_ms: u64) -> Result<Vec<String>> {
+        Ok(self.read(|s| {
+            let mut events: Vec<(&str, f64)> = s
+                .zsets
+                .get(&keys.draw_events())
+                .into_iter()
+                .flatten()
+                .filter(|(_, ts)| **ts >= since_ms as f64)
+                .map(|(message, ts)| (message.as_str(), *ts))
+                .collect();
+            events.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+            events
+                .into_iter()
+                .map(|(message, _)| message.to_string())
+                .collect()
+        }))
+    }
+
+    async fn last_processed_block(&self, keys: &Keyspace) -> Result<Option<(u64, Option<String>)>> {
+        Ok(self.read(|s| {
+            let height = std::str::from_utf8(s.string(&keys.last_processed_block())?).ok()?;
+            let hash = s
+                .string(&keys.last_processed_block_hash())
+                .and_then(|h| String::from_utf8(h.to_vec()).ok());
+            Some((height.parse().ok()?, hash))
+        }))
+    }
+
+    async fn indexer_heartbeat(&self, keys: &Keyspace) -> Result<Option<IndexerHeartbeat>> {
+        Ok(self.read(|s| {
+            let fields: HashMap<String, u64> = s
+                .hashes
+                .get(&keys.indexer_heartbeat())?
+                .iter()
+                .filter_map(|(field, value)| Some((field.clone(), value.parse().ok()?)))
+                .collect();
+            IndexerHeartbeat::from_fields(&fields)
+        }))
+    }
+
+    async fn commit(&self, batch: Batch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        Self::write(&mut self.state.lock().unwrap(), batch.ops());
+        Ok(())
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
@@ -0,0 +1,361 @@
+use common::valkey::Keyspace;
+use common::IndexerHeartbeat;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+
+use super::{
+    parse_color, parse_position, parse_region, AccountStats, Batch, BoardStore, EndedSeason,
+    LeaderboardWindow, Op, RegionCoords, RegionSummary, SeasonSummary, REGION_COLOR_FIELD,
+};
+use crate::{Error, Result};
+
+/// How long a computed time-windowed leaderboard is reused before recomputing.
+const LEADERBOARD_WINDOW_CACHE_SECS: i64 = 60;
+
+/// The board's state in Valkey, shared with the indexer and other server processes.
+#[derive(Clone)]
+pub struct ValkeyStore {
+    con: redis::aio::MultiplexedConnection,
+}
+
+impl ValkeyStore {
+    pub fn new(con: redis::aio::MultiplexedConnection) -> Self {
+        Self { con }
+    }
+
+    fn con(&self) -> redis::aio::MultiplexedConnection {
+        self.con.clone()
+    }
+
+    /// The sorted set backing a leaderboard, building a windowed union if needed.
+    async fn leaderboard_key(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+    ) -> Result<String> {
+        if window == LeaderboardWindow::All {
+            return Ok(keys.leaderboard());
+        }
+        let key = keys.leaderboard_window(window.name());
+        let mut con = self.con();
+        let exists: bool = con.exists(&key).await?;
+        if !exists {
+            let _: () = redis::pipe()
+                .zunionstore(&key, window.buckets(keys, now_ms))
+                .ignore()
+                .expire(&key, LEADERBOARD_WINDOW_CACHE_SECS)
+                .ignore()
+                .query_async(&mut con)
+                .await?;
+        }
+        Ok(key)
+    }
+}
+
+#[async_trait::async_trait]
+impl BoardStore for ValkeyStore {
+    async fn current_season(&self, keys: &Keyspace) -> Result<Option<u32>> {
+        Ok(self.con().get(keys.current_season()).await?)
+    }
+
+    async fn last_applied_event(&self, keys: &Keyspace) -> Result<Option<(u64, u32)>> {
+        let last: Option<String> = self.con().get(keys.last_applied_event()).await?;
+        Ok(last.as_deref().and_then(parse_position))
+    }
+
+    async fn region(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Vec<u8>> {
+        Ok(self.con().get(keys.region(rx, ry)).await?)
+    }
+
+    async fn region_updated_ms(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<Option<u64>> {
+        Ok(self
+            .con()
+            .hget(keys.region_meta(rx, ry), "last_updated")
+            .await?)
+    }
+
+    async fn pixel_timestamp(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        lx: usize,
+        ly: usize,
+    ) -> Result<Option<u64>> {
+        let ts: Option<f64> = self
+            .con()
+            .zscore(keys.pixel_ts(rx, ry), format!("{lx},{ly}"))
+            .await?;
+        Ok(ts.map(|ts| ts as u64))
+    }
+
+    async fn pixel_timestamps(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        since_ms: u64,
+    ) -> Result<Vec<(u64, u64, u64)>> {
+        let entries: Vec<(String, f64)> = self
+            .con()
+            .zrangebyscore_withscores(keys.pixel_ts(rx, ry), since_ms, "+inf")
+            .await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(member, score)| {
+                let (lx, ly) = member.split_once(',')?;
+                Some((lx.parse().ok()?, ly.parse().ok()?, score as u64))
+            })
+            .collect())
+    }
+
+    async fn is_region_open(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool> {
+        Ok(self
+            .con()
+            .sismember(keys.open_regions(), format!("{rx}:{ry}"))
+            .await?)
+    }
+
+    async fn open_regions(&self, keys: &Keyspace) -> Result<Vec<RegionCoords>> {
+        let members: Vec<String> = self.con().smembers(keys.open_regions()).await?;
+        Ok(members.iter().filter_map(|m| parse_region(m)).collect())
+    }
+
+    async fn region_pixel_count(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<i64> {
+        let count: Option<i64> = self
+            .con()
+            .hget(keys.region_pixel_count(), format!("{rx}:{ry}"))
+            .await?;
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn region_owners(
+        &self,
+        keys: &Keyspace,
+        (rx, ry): RegionCoords,
+        limit: usize,
+    ) -> Result<Vec<(u32, i64)>> {
+        Ok(self
+            .con()
+            .zrevrange_withscores(keys.region_owners(rx, ry), 0, limit as isize - 1)
+            .await?)
+    }
+
+    async fn region_summaries(
+        &self,
+        keys: &Keyspace,
+        regions: &[RegionCoords],
+    ) -> Result<Vec<RegionSummary>> {
+        if regions.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = redis::pipe();
+        for &(rx, ry) in regions {
+            pipe.zrevrange_withscores(keys.region_owners(rx, ry), 0, 0)
+                .hget(keys.region_meta(rx, ry), REGION_COLOR_FIELD);
+        }
+        let replies: Vec<redis::Value> = pipe.query_async(&mut self.con()).await?;
+        let summaries = replies
+            .chunks_exact(2)
+            .map(|reply| {
+                let top: Vec<(u32, i64)> = redis::from_redis_value(&reply[0])?;
+                let color: Option<String> = redis::from_redis_value(&reply[1])?;
+                Ok(RegionSummary {
+                    top_owner: top.first().copied(),
+                    color: color.as_deref().and_then(parse_color),
+                })
+            })
+            .collect::<redis::RedisResult<_>>()?;
+        Ok(summaries)
+    }
+
+    async fn has_region_owners(&self, keys: &Keyspace, rx: i32, ry: i32) -> Result<bool> {
+        Ok(self.con().exists(keys.region_owners(rx, ry)).await?)
+    }
+
+    async fn resolve_owner_id(
+        &self,
+        keys: &Keyspace,
+        account_id: &str,
+        max_owner_id: u32,
+    ) -> Result<u32> {
+        common::owner_ids::resolve_or_allocate(&mut self.con(), keys, account_id, max_owner_id)
+            .await
+            .map_err(|e| {
+                if common::owner_ids::is_exhausted(&e) {
+                    Error::OwnerIdsExhausted {
+                        account_id: account_id.to_string(),
+                    }
+                } else {
+                    e.into()
+                }
+            })
+    }
+
+    async fn owner_id(&self, keys: &Keyspace, account_id: &str) -> Result<Option<u32>> {
+        Ok(self.con().hget(keys.account_to_id(), account_id).await?)
+    }
+
+    async fn account_ids(&self, keys: &Keyspace, owner_ids: &[u32]) -> Result<Vec<Option<String>>> {
+        if owner_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(redis::cmd("HMGET")
+            .arg(keys.id_to_account())
+            .arg(owner_ids)
+            .query_async(&mut self.con())
+            .await?)
+    }
+
+    async fn account_pixel_counts(&self, keys: &Keyspace) -> Result<Vec<(u32, i64)>> {
+        Ok(self.con().hgetall(keys.account_pixel_count()).await?)
+    }
+
+    async fn account_stats(
+        &self,
+        keys: &Keyspace,
+        owner_id: u32,
+        claimable_since_ms: u64,
+    ) -> Result<AccountStats> {
+        let (pixel_count, claimable, first_draw, last_draw, regions): (
+            Option<i64>,
+            i64,
+            Option<u64>,
+            Option<u64>,
+            Vec<String>,
+        ) = redis::pipe()
+            .hget(keys.account_pixel_count(), owner_id)
+            .zcount(keys.account_pixel_ts(owner_id), claimable_since_ms, "+inf")
+            .hget(keys.account_meta(owner_id), "first_draw_ms")
+            .hget(keys.account_meta(owner_id), "last_draw_ms")
+            .smembers(keys.account_regions(owner_id))
+            .query_async(&mut self.con())
+            .await?;
+        Ok(AccountStats {
+            pixel_count: pixel_count.unwrap_or(0),
+            claimable_pixel_count: claimable,
+            first_draw_ms: first_draw,
+            last_draw_ms: last_draw,
+            regions: regions.iter().filter_map(|m| parse_region(m)).collect(),
+        })
+    }
+
+    async fn leaderboard(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<(u32, i64)>, u64)> {
+        let key = self.leaderboard_key(keys, window, now_ms).await?;
+        let start = offset.min(isize::MAX as usize);
+        let stop = start.saturating_add(limit.saturating_sub(1)).min(isize::MAX as usize);
+        Ok(redis::pipe()
+            .zrevrange_withscores(&key, start as isize, stop as isize)
+            .zcard(&key)
+            .query_async(&mut self.con())
+            .await?)
+    }
+
+    async fn leaderboard_rank(
+        &self,
+        keys: &Keyspace,
+        window: LeaderboardWindow,
+        now_ms: u64,
+        owner_id: u32,
+    ) -> Result<(Option<u64>, Option<i64>)> {
+        let key = self.leaderboard_key(keys, window, now_ms).await?;
+        Ok(redis::pipe()
+            .zrevrank(&key, owner_id)
+            .zscore(&key, owner_id)
+            .query_async(&mut self.con())
+            .await?)
+    }
+
+    async fn ended_seasons(&self, keys: &Keyspace) -> Result<Vec<EndedSeason>> {
+        let mut con = self.con();
+        let ended: Vec<(u32, u64)> = con.zrange_withscores(keys.ended_seasons(), 0, -1).await?;
+        if ended.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = redis::pipe();
+        for (season, _) in &ended {
+            pipe.hgetall(keys.for_season(*season).season_meta());
+        }
+        let metas: Vec<HashMap<String, u64>> = pipe.query_async(&mut con).await?;
+
+        Ok(ended
+            .into_iter()
+            .zip(metas)
+            .map(|((season, ended_ms), meta)| {
+                let field = |name: &str| meta.get(name).copied().unwrap_or(0);
+                EndedSeason {
+                    season,
+                    summary: SeasonSummary {
+                        ended_block_height: field("ended_block_height"),
+                        ended_timestamp_ms: ended_ms,
+                        open_regions: field("open_regions"),
+                        top_owner_id: field("top_owner_id") as u32,
+                        top_pixel_count: field("top_pixel_count"),
+                    },
+                }
+            })
+            .collect())
+    }
+
+    async fn draw_events(&self, keys: &Keyspace, since_ms: u64) -> Result<Vec<String>> {
+        Ok(self
+            .con()
+            .zrangebyscore(keys.draw_events(), since_ms, "+inf")
+            .await?)
+    }
+
+    async fn last_processed_block(&self, keys: &Keyspace) -> Result<Option<(u64, Option<String>)>> {
+        let (height, hash): (Option<u64>, Option<String>) = redis::pipe()
+            .get(keys.last_processed_block())
+            .get(keys.last_processed_block_hash())
+            .query_async(&mut self.con())
+            .await?;
+        Ok(height.map(|height| (height, hash)))
+    }
+
+    async fn indexer_heartbeat(&self, keys: &Keyspace) -> Result<Option<IndexerHeartbeat>> {
+        Ok(IndexerHeartbeat::read(&mut self.con(), keys).await?)
+    }
+
+    async fn commit(&self, batch: Batch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for op in batch.into_ops() {
+            match op {
+                Op::Set { key, value } => pipe.set(key, value),
+                Op::HSet { key, field, value } => pipe.hset(key, field, value),
+                Op::HSetNx { key, field, value } => pipe.hset_nx(key, field, value),
+                Op::HIncrBy { key, field, by } => pipe.hincr(key, field, by),
+                Op::SAdd { key, members } => pipe.sadd(key, members),
+                Op::ZAdd { key, members } => pipe.zadd_multiple(key, &members),
+                Op::ZIncrBy { key, member, by } => pipe.zincr(key, member, by),
+                Op::ZRem { key, members } => pipe.zrem(key, members),
+                Op::ZRemRangeByScore { key, min, max } => {
+                    pipe.zrembyscore(key, score_arg(min), score_arg(max))
+                }
+                Op::ExpireAt { key, at_ms } => pipe.pexpire_at(key, at_ms as i64),
+            }
+            .ignore();
+        }
+        let _: () = pipe.query_async(&mut self.con()).await?;
+        Ok(())
+    }
+}
+
+/// A score bound as Valkey spells it.
+fn score_arg(score: f64) -> String {
+    match score {
+        f64::NEG_INFINITY => "-inf".into(),
+        f64::INFINITY => "+inf".into(),
+        _ => score.to_string(),
+    }
+}
@@ -1,11 +1,16 @@
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, StreamExt};
-use redis::AsyncCommands;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc;
 
-use crate::api::AppState;
+use crate::api::BoardCtx;
+use crate::indexer_status;
+use crate::metrics::metrics;
+use crate::storage::BoardStore;
 
-pub async fn handle_socket(socket: WebSocket, state: AppState) {
+pub async fn handle_socket(socket: WebSocket, state: BoardCtx) {
+    let connections = metrics().ws_connections.with_label_values(&[state.keys.board()]);
+    connections.inc();
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
     // Channel for sending messages to the client (from both broadcast and catch-up)
@@ -14,10 +19,25 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
     // Subscribe to broadcast channel
     let mut broadcast_rx = state.broadcast_tx.subscribe();
 
+    // Start with the indexer's status rather than waiting for the next broadcast
+    let status = indexer_status::status_message(state.store.as_ref(), &state.keys).await;
+    if let Some(status) = status {
+        let _ = tx.send(status).await;
+    }
+
     // Task: forward broadcast events to the mpsc channel
     let broadcast_tx = tx.clone();
+    let lagged = metrics().ws_lagged_messages.with_label_values(&[state.keys.board()]);
     let broadcast_task = tokio::spawn(async move {
-        while let Ok(msg) = broadcast_rx.recv().await {
+        loop {
+            let msg = match broadcast_rx.recv().await {
+                Ok(msg) => msg,
+                Err(RecvError::Lagged(missed)) => {
+                    lagged.inc_by(missed);
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            };
             if broadcast_tx.send(msg).await.is_err() {
                 break;
             }
@@ -34,11 +54,16 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
     });
 
     // Handle incoming messages from client
-    let valkey = state.valkey.clone();
+    let store = state.store.clone();
+    let keys = state.keys.clone();
     let recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = ws_receiver.next().await {
             if let Message::Text(text) = msg {
-                handle_client_message(&text, &valkey, &tx).await;
+                // Hang up on failure; the client reconnects and asks to catch up again
+                if let Err(e) = handle_client_message(&text, store.as_ref(), &keys, &tx).await {
+                    tracing::error!("WebSocket catch-up failed: {}", e);
+                    break;
+                }
             }
         }
     });
@@ -49,26 +74,24 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
         _ = send_task => {},
         _ = recv_task => {},
     }
+    connections.dec();
 }
 
 async fn handle_client_message(
     text: &str,
-    valkey: &redis::aio::MultiplexedConnection,
+    store: &dyn BoardStore,
+    keys: &common::valkey::Keyspace,
     sender: &mpsc::Sender<String>,
-) {
+) -> crate::Result<()> {
     let msg: serde_json::Value = match serde_json::from_str(text) {
         Ok(v) => v,
-        Err(_) => return,
+        Err(_) => return Ok(()),
     };
 
     if msg.get("type").and_then(|t| t.as_str()) == Some("catch_up") {
         if let Some(since) = msg.get("since_timestamp_ms").and_then(|t| t.as_f64()) {
             let since_ts = since as u64;
-            let events: Vec<String> = valkey
-                .clone()
-                .zrangebyscore(common::valkey::DRAW_EVENTS_ZSET, since_ts, "+inf")
-                .await
-                .unwrap_or_default();
+            let events = store.draw_events(keys, since_ts).await?;
 
             tracing::info!(
                 "WebSocket catch-up: {} events since {}",
@@ -83,4 +106,5 @@ async fn handle_client_message(
             }
         }
     }
+    Ok(())
 }
@@ -0,0 +1,299 @@
+//! Draw events go in through the queue the indexer fills; the board's state comes out
+//! through the REST API and the WebSocket feed.
+
+mod support;
+
+use axum::http::StatusCode;
+use common::boards::{BoardBounds, SeasonSchedule};
+use common::BoardRules;
+use serde_json::json;
+use support::{Harness, T0_MS};
+
+const MINUTE_MS: u64 = 60_000;
+
+fn sorted_regions(body: &serde_json::Value) -> Vec<(i64, i64)> {
+    let mut regions: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| (r["rx"].as_i64().unwrap(), r["ry"].as_i64().unwrap()))
+        .collect();
+    regions.sort();
+    regions
+}
+
+#[tokio::test]
+async fn a_pixel_can_be_taken_within_the_ownership_window_and_is_permanent_after_it() {
+    let mut board = Harness::start(BoardRules::default()).await;
+    let mut ws = board.ws().await;
+
+    board.draw("alice.near", 0, &[(5, 5)]).await;
+    let alice = board.owner_id("alice.near").await;
+    assert_eq!(board.pixel(5, 5).await.owner_id, alice);
+    let msg = ws.recv().await;
+    assert_eq!(msg["type"], "draw");
+    assert_eq!(msg["signer"], "alice.near");
+    assert_eq!(msg["pixels"], json!([{ "x": 5, "y": 5, "color": "FF0000", "owner_id": alice }]));
+
+    // Still within alice's hour: anyone may take it
+    board.draw("bob.near", 59 * MINUTE_MS, &[(5, 5)]).await;
+    let bob = board.owner_id("bob.near").await;
+    assert_eq!(board.pixel(5, 5).await.owner_id, bob);
+    assert_eq!(ws.recv().await["signer"], "bob.near");
+    let (_, alice_profile) = board.get("/api/account/by-name/alice.near").await;
+    assert_eq!(alice_profile["pixel_count"], 0);
+    let (_, bob_profile) = board.get("/api/account/by-name/bob.near").await;
+    assert_eq!(bob_profile["pixel_count"], 1);
+
+    // An hour after bob drew it the pixel is his for good
+    board.draw("carol.near", 119 * MINUTE_MS, &[(5, 5)]).await;
+    assert_eq!(board.pixel(5, 5).await.owner_id, bob);
+    let (_, carol_profile) = board.get("/api/account/by-name/carol.near").await;
+    assert_eq!(carol_profile["pixel_count"], 0);
+
+    // Carol's rejected draw was not broadcast: the next message is dave's
+    board.draw("dave.near", 120 * MINUTE_MS, &[(6, 6)]).await;
+    assert_eq!(ws.recv().await["signer"], "dave.near");
+
+    let (_, stats) = board.get("/api/stats/region/0/0").await;
+    assert_eq!(stats["count"], 2);
+    let (_, leaderboard) = board.get("/api/leaderboard").await;
+    let leaders: Vec<_> = leaderboard["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["account_id"].as_str().unwrap(), e["pixel_count"].as_i64().unwrap()))
+        .collect();
+    // Ties rank the later owner id first, as Valkey's ZREVRANGE does. Alice holds
+    // nothing any more, so she is off the board
+    assert_eq!(leaders, vec![("dave.near", 1), ("bob.near", 1)]);
+    assert_eq!(leaderboard["total"], 2);
+}
+
+#[tokio::test]
+async fn leaderboard_pages_past_the_last_rank_are_rejected() {
+    let mut board = Harness::start(BoardRules::default()).await;
+    board.draw("alice.near", 0, &[(5, 5)]).await;
+
+    let huge = format!("/api/leaderboard?offset={}&limit=10", usize::MAX - 5);
+    assert_eq!(board.get(&huge).await.0, StatusCode::BAD_REQUEST);
+    let past_isize = format!("/api/leaderboard?offset={}", isize::MAX);
+    assert_eq!(board.get(&past_isize).await.0, StatusCode::BAD_REQUEST);
+
+    // A far but representable page is just empty
+    let (status, page) = board.get("/api/leaderboard?offset=1000000").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["entries"], json!([]));
+    assert_eq!(page["total"], 1);
+}
+
+#[tokio::test]
+async fn closed_regions_ignore_draws_until_a_neighbor_fills_up() {
+    let rules = BoardRules {
+        region_open_threshold: 3,
+        ..Default::default()
+    };
+    let mut board = Harness::start(rules).await;
+    let mut ws = board.ws().await;
+    let (status, open) = board.get("/api/open-regions").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(sorted_regions(&open), vec![(0, 0)]);
+
+    // (130, 5) is in region (1, 0), which is closed
+    board.draw("alice.near", 0, &[(130, 5)]).await;
+    assert_eq!(board.pixel(130, 5).await.owner_id, 0);
+    let (_, stats) = board.get("/api/stats/region/1/0").await;
+    assert_eq!(stats["count"], 0);
+
+    board.draw("alice.near", 1_000, &[(1, 1), (2, 2), (3, 3)]).await;
+    assert_eq!(ws.recv().await["type"], "draw");
+    let opened = ws.recv().await;
+    assert_eq!(opened["type"], "regions_opened");
+    let neighbors = vec![(-1, 0), (0, -1), (0, 1), (1, 0)];
+    assert_eq!(sorted_regions(&opened["regions"]), neighbors);
+    let (_, open) = board.get("/api/open-regions").await;
+    assert_eq!(sorted_regions(&open), vec![(-1, 0), (0, -1), (0, 0), (0, 1), (1, 0)]);
+
+    board.draw("alice.near", 2_000, &[(130, 5)]).await;
+    let alice = board.owner_id("alice.near").await;
+    assert_eq!(board.pixel(130, 5).await.owner_id, alice);
+    let (_, region_meta) = board.get("/api/region/1/0/meta").await;
+    assert_eq!(region_meta["last_updated"], T0_MS + 2_000);
+}
+
+#[tokio::test]
+async fn a_reconnecting_client_catches_up_on_missed_draws() {
+    let mut board = Harness::start(BoardRules::default()).await;
+    board.draw("alice.near", 0, &[(1, 1)]).await;
+    board.draw("bob.near", 1_000, &[(2, 2)]).await;
+
+    let mut ws = board.ws().await;
+    ws.send(json!({ "type": "catch_up", "since_timestamp_ms": T0_MS + 500 })).await;
+    let msg = ws.recv().await;
+    assert_eq!(msg["signer"], "bob.near");
+    assert_eq!(msg["block_timestamp_ms"], T0_MS + 1_000);
+
+    // Live draws follow the catch-up
+    board.draw("carol.near", 2_000, &[(3, 3)]).await;
+    assert_eq!(ws.recv().await["signer"], "carol.near");
+}
+
+#[tokio::test]
+async fn the_world_map_shows_each_regions_top_owner_and_color() {
+    let rules = BoardRules {
+        region_open_threshold: 3,
+        ..Default::default()
+    };
+    let mut board = Harness::start(rules).await;
+    board.draw("alice.near", 0, &[(1, 1), (2, 2)]).await;
+    board.draw("bob.near", 1_000, &[(3, 3)]).await;
+    let alice = board.owner_id("alice.near").await;
+
+    let (status, map) = board.get("/api/map/owners").await;
+    assert_eq!(status, StatusCode::OK);
+    let region = |rx: i64, ry: i64| {
+        map.as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["rx"] == rx && r["ry"] == ry)
+            .cloned()
+            .unwrap()
+    };
+    assert_eq!(
+        region(0, 0),
+        json!({
+            "rx": 0,
+            "ry": 0,
+            "owner_id": alice,
+            "account_id": "alice.near",
+            "pixel_count": 2,
+            "color": "FF0000",
+        })
+    );
+    // The neighbors opened by bob's draw have nothing drawn yet
+    assert_eq!(map.as_array().unwrap().len(), 5);
+    assert_eq!(region(1, 0)["owner_id"], serde_json::Value::Null);
+    assert_eq!(region(1, 0)["color"], serde_json::Value::Null);
+}
+
+/// Seasons ending at block 102 and every 10 blocks after.
+fn seasons_by_block() -> BoardRules {
+    BoardRules {
+        seasons: Some(SeasonSchedule {
+            end_block_height: Some(102),
+            end_timestamp_ms: None,
+            length_blocks: Some(10),
+            length_ms: None,
+        }),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn a_season_ends_at_its_block_and_stays_readable() {
+    let mut board = Harness::start(seasons_by_block()).await;
+    let mut ws = board.ws().await;
+    board.draw("alice.near", 0, &[(5, 5)]).await;
+    board.draw("bob.near", 1_000, &[(6, 6), (7, 7)]).await;
+    let (alice, bob) = (board.owner_id("alice.near").await, board.owner_id("bob.near").await);
+
+    // Block 102 is past the end: season 0 is frozen and carol draws in season 1
+    board.draw("carol.near", 2_000, &[(8, 8)]).await;
+    assert_eq!(ws.recv().await["signer"], "alice.near");
+    assert_eq!(ws.recv().await["signer"], "bob.near");
+    assert_eq!(ws.recv().await, json!({ "type": "season_started", "season": 1 }));
+    assert_eq!(ws.recv().await["signer"], "carol.near");
+
+    let (status, seasons) = board.get("/api/seasons").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        seasons,
+        json!({
+            "current": 1,
+            "ended": [{
+                "season": 0,
+                "ended_timestamp_ms": T0_MS + 2_000,
+                "ended_block_height": 102,
+                "open_regions": 1,
+                "top_owner_id": bob,
+                "top_pixel_count": 2,
+            }],
+        })
+    );
+
+    // The live board starts over; the ended one is served under its season
+    assert_eq!(board.pixel(5, 5).await.owner_id, 0);
+    assert_eq!(board.pixel_at("/api/season/0", 5, 5).await.owner_id, alice);
+    assert_eq!(board.pixel_at("/api/season/0", 8, 8).await.owner_id, 0);
+    let carol = board.owner_id("carol.near").await;
+    assert_eq!(board.pixel_at("/api/season/1", 8, 8).await.owner_id, carol);
+    let (_, leaderboard) = board.get("/api/season/0/leaderboard").await;
+    assert_eq!(leaderboard["entries"][0]["account_id"], "bob.near");
+    let (_, leaderboard) = board.get("/api/leaderboard").await;
+    assert_eq!(leaderboard["entries"][0]["account_id"], "carol.near");
+    let (_, rules) = board.get("/api/season/0/rules").await;
+    assert_eq!(rules["season"], 0);
+    let (_, rules) = board.get("/api/rules").await;
+    assert_eq!(rules["season"], 1);
+
+    // Seasons that have not started do not exist yet
+    let (status, _) = board.get("/api/season/2/rules").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = board.get("/api/default/season/2/region/0/0").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn a_season_ends_on_time_without_draws() {
+    let mut board = Harness::start(seasons_by_block()).await;
+    let mut ws = board.ws().await;
+    board.draw("alice.near", 0, &[(5, 5)]).await;
+    assert_eq!(ws.recv().await["signer"], "alice.near");
+
+    // The indexer passes the end of the season with no draws for the board
+    board.heartbeat(102, 2_000).await;
+    assert_eq!(ws.recv().await, json!({ "type": "season_started", "season": 1 }));
+    let (_, seasons) = board.get("/api/seasons").await;
+    assert_eq!(seasons["current"], 1);
+    assert_eq!(seasons["ended"][0]["ended_block_height"], 102);
+    assert_eq!(seasons["ended"][0]["top_pixel_count"], 1);
+    assert_eq!(board.pixel(5, 5).await.owner_id, 0);
+
+    // Several seasons past the end each end in turn
+    board.heartbeat(125, 30_000).await;
+    assert_eq!(ws.recv().await, json!({ "type": "season_started", "season": 3 }));
+    let (_, seasons) = board.get("/api/seasons").await;
+    assert_eq!(seasons["ended"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn a_bounded_board_rejects_pixels_outside_it() {
+    let rules = BoardRules {
+        region_open_threshold: 3,
+        bounds: Some(BoardBounds {
+            width: 200,
+            height: 50,
+        }),
+        ..Default::default()
+    };
+    let mut board = Harness::start(rules).await;
+    let (_, open) = board.get("/api/open-regions").await;
+    assert_eq!(sorted_regions(&open), vec![(0, 0), (1, 0)]);
+    let (_, rules) = board.get("/api/rules").await;
+    assert_eq!(rules["bounds"], json!({ "width": 200, "height": 50 }));
+
+    // Only pixels within 200x50 are drawn, even in regions that are open
+    let pixels = [(1, 1), (2, 2), (3, 3), (150, 10), (210, 10), (10, 60), (-5, 5)];
+    board.draw("alice.near", 0, &pixels).await;
+    let alice = board.owner_id("alice.near").await;
+    for (x, y) in [(1, 1), (2, 2), (3, 3), (150, 10)] {
+        assert_eq!(board.pixel(x, y).await.owner_id, alice);
+    }
+    assert_eq!(board.pixel(210, 10).await.owner_id, 0);
+    let (_, profile) = board.get("/api/account/by-name/alice.near").await;
+    assert_eq!(profile["pixel_count"], 4);
+
+    // A full region opens no neighbors outside the board
+    let (_, open) = board.get("/api/open-regions").await;
+    assert_eq!(sorted_regions(&open), vec![(0, 0), (1, 0)]);
+}
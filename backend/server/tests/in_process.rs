@@ -0,0 +1,180 @@
+//! A board fed over a channel instead of the Valkey queue, persisting to the embedded
+//! store, as the all-in-one binary runs it.
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::valkey::{Keyspace, DEFAULT_BOARD};
+use common::{BoardConfig, DrawEvent, DrawPixel, IndexerHeartbeat, RegionEncoding};
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use server::api::{self, AppState};
+use server::config::Config;
+use server::consumer::{BlockEvents, EventSource};
+use server::storage::{BoardStore, EmbeddedStore};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+
+const T0_MS: u64 = 1_700_000_000_000;
+
+fn config() -> Config {
+    Config {
+        valkey_url: String::new(),
+        listen_addr: "127.0.0.1:0".into(),
+        keyspace: Keyspace::default(),
+        boards: vec![BoardConfig {
+            id: DEFAULT_BOARD.into(),
+            contract_ids: Vec::new(),
+            rules: Default::default(),
+        }],
+        region_encoding: RegionEncoding::Owner24,
+        health: Default::default(),
+    }
+}
+
+/// Block `height`, `height` seconds after `T0_MS`, with a draw of one pixel per
+/// account.
+fn block(height: u64, draws: &[(&str, (i32, i32))]) -> BlockEvents {
+    let block_timestamp_ms = T0_MS + height * 1_000;
+    let events = draws
+        .iter()
+        .enumerate()
+        .map(|(i, &(account, (x, y)))| DrawEvent {
+            predecessor_id: account.to_string(),
+            block_height: height,
+            block_timestamp_ms,
+            pixels: vec![DrawPixel {
+                x,
+                y,
+                color: "FF0000".to_string(),
+            }],
+            deposit: 0,
+            event_index: Some(i as u32),
+        })
+        .collect();
+    BlockEvents {
+        block_height: height,
+        block_hash: format!("hash-{height}"),
+        heartbeat: IndexerHeartbeat {
+            block_height: height,
+            block_timestamp_ms,
+            updated_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            buffered_blocks: 0,
+        },
+        events,
+    }
+}
+
+/// Wait until the board has recorded block `height` as processed.
+async fn settle(store: &dyn BoardStore, keys: &Keyspace, height: u64) {
+    for _ in 0..1000 {
+        let last = store.last_processed_block(keys).await.unwrap();
+        if last.is_some_and(|(h, _)| h >= height) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("consumer stalled");
+}
+
+async fn get(app: &axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn a_board_fed_over_a_channel_serves_the_same_api() {
+    let dir = std::env::temp_dir().join(format!("board-in-process-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store: Arc<dyn BoardStore> = Arc::new(EmbeddedStore::open(&dir).unwrap());
+    let config = config();
+    let (blocks_tx, blocks_rx) = mpsc::channel(16);
+    let source = EventSource::Channel(blocks_rx);
+    let handle = server::app::start_board_with(&config, store.clone(), source, &config.boards[0])
+        .await
+        .unwrap();
+    let keys = handle.keys.clone();
+    let app = api::router(AppState {
+        boards: Arc::new(HashMap::from([(DEFAULT_BOARD.to_string(), handle)])),
+        valkey: None,
+        health: config.health,
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let served = app.clone();
+    tokio::spawn(async move { axum::serve(listener, served).await });
+
+    blocks_tx
+        .send(block(100, &[("alice.near", (1, 1)), ("bob.near", (2, 2))]))
+        .await
+        .unwrap();
+    blocks_tx.send(block(101, &[])).await.unwrap();
+    settle(store.as_ref(), &keys, 101).await;
+
+    let (status, alice) = get(&app, "/api/account/by-name/alice.near").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(alice["pixel_count"], 1);
+    let (_, stats) = get(&app, "/api/stats/region/0/0").await;
+    assert_eq!(stats["count"], 2);
+
+    // Progress is recorded in the store in place of the indexer's keys
+    let (status, health) = get(&app, "/api/health/ready").await;
+    assert_eq!(status, StatusCode::OK, "{health}");
+    assert_eq!(health["last_processed_block"], 101);
+    assert_eq!(health["checks"]["store"]["ok"], true);
+    assert_eq!(health["checks"]["indexer"]["block_height"], 101);
+    assert_eq!(
+        store.last_processed_block(&keys).await.unwrap(),
+        Some((101, Some("hash-101".to_string())))
+    );
+
+    // A block resent after a restart is not applied twice
+    blocks_tx
+        .send(block(100, &[("alice.near", (1, 1)), ("bob.near", (2, 2))]))
+        .await
+        .unwrap();
+    blocks_tx
+        .send(block(102, &[("carol.near", (3, 3))]))
+        .await
+        .unwrap();
+    settle(store.as_ref(), &keys, 102).await;
+    let (_, stats) = get(&app, "/api/stats/region/0/0").await;
+    assert_eq!(stats["count"], 3);
+
+    // Clients catch up on draws from the store
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .unwrap();
+    let catch_up = json!({ "type": "catch_up", "since_timestamp_ms": T0_MS + 101_000 });
+    ws.send(Message::text(catch_up.to_string())).await.unwrap();
+    let msg = loop {
+        let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("no WebSocket message")
+            .unwrap()
+            .unwrap();
+        if let Message::Text(text) = msg {
+            let msg: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if msg["type"] == "draw" {
+                break msg;
+            }
+        }
+    };
+    assert_eq!(msg["signer"], "carol.near");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
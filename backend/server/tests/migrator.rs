@@ -0,0 +1,70 @@
+use common::valkey::Keyspace;
+use common::{decode_region_blob, encode_region_container, Pixel, RegionEncoding};
+use fake_valkey::FakeValkey;
+use redis::AsyncCommands;
+use server::migrator::{self, MigrationReport};
+
+fn headerless(encoding: RegionEncoding, owner_id: u32) -> Vec<u8> {
+    let mut pixels = vec![0u8; encoding.blob_size()];
+    let pixel = Pixel { r: 1, g: 2, b: 3, owner_id };
+    encoding.encode(&pixel, &mut pixels[encoding.pixel_offset(4, 4)..]).unwrap();
+    pixels
+}
+
+#[tokio::test]
+async fn headerless_regions_are_rewritten_once_and_others_left_alone() {
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let keys = Keyspace::default();
+
+    let narrow = headerless(RegionEncoding::Owner24, 7);
+    let wide = headerless(RegionEncoding::Owner32, 70_000_000);
+    let current = encode_region_container(&narrow, RegionEncoding::Owner24);
+    let garbage = b"not a region".to_vec();
+    let _: () = redis::pipe()
+        .set(keys.region(0, 0), &narrow)
+        .set(keys.region(1, 0), &wide)
+        .set(keys.region(0, 1), &current)
+        .set(keys.region(1, 1), &garbage)
+        .query_async(&mut con)
+        .await
+        .unwrap();
+
+    let report = migrator::run(con.clone(), keys.clone()).await;
+    assert_eq!(report, MigrationReport { migrated: 2, skipped: 1, raced: 0, failed: 1 });
+
+    for (rx, ry, pixels, encoding) in [
+        (0, 0, &narrow, RegionEncoding::Owner24),
+        (1, 0, &wide, RegionEncoding::Owner32),
+    ] {
+        let blob: Vec<u8> = con.get(keys.region(rx, ry)).await.unwrap();
+        let region = decode_region_blob(&blob).unwrap();
+        assert_eq!((region.version, region.encoding), (1, encoding));
+        assert_eq!(&region.pixels, pixels);
+    }
+    let untouched: Vec<u8> = con.get(keys.region(1, 1)).await.unwrap();
+    assert_eq!(untouched, garbage);
+
+    // A second pass finds nothing left to do
+    let report = migrator::run(con.clone(), keys.clone()).await;
+    assert_eq!(report, MigrationReport { migrated: 0, skipped: 3, raced: 0, failed: 1 });
+}
+
+#[tokio::test]
+async fn a_failed_swap_is_counted_and_retried_on_the_next_pass() {
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let keys = Keyspace::default();
+    let narrow = headerless(RegionEncoding::Owner24, 7);
+    let _: () = con.set(keys.region(0, 0), &narrow).await.unwrap();
+
+    valkey.fail_next("EVALSHA", 1);
+    valkey.fail_next("EVAL", 1);
+    let report = migrator::run(con.clone(), keys.clone()).await;
+    assert_eq!(report, MigrationReport { failed: 1, ..Default::default() });
+    let blob: Vec<u8> = con.get(keys.region(0, 0)).await.unwrap();
+    assert_eq!(blob, narrow);
+
+    let report = migrator::run(con.clone(), keys.clone()).await;
+    assert_eq!(report, MigrationReport { migrated: 1, ..Default::default() });
+}
@@ -0,0 +1,90 @@
+//! The board spends each draw's deposit on its pixels in order.
+
+use common::valkey::Keyspace;
+use common::{BoardRules, DrawEvent, DrawPixel, Pricing, RegionEncoding};
+use server::board::Board;
+use server::storage::MemoryStore;
+use std::sync::Arc;
+
+const T0_MS: u64 = 1_700_000_000_000;
+const HOUR_MS: u64 = 3_600_000;
+
+const PRICING: Pricing = Pricing {
+    pixel_price: 10,
+    overwrite_price: 25,
+    start_block_height: 10,
+};
+
+fn draw(
+    account: &str,
+    block_height: u64,
+    ts_ms: u64,
+    deposit: u128,
+    pixels: &[(i32, i32)],
+) -> DrawEvent {
+    DrawEvent {
+        predecessor_id: account.to_string(),
+        block_height,
+        block_timestamp_ms: ts_ms,
+        pixels: pixels
+            .iter()
+            .map(|&(x, y)| DrawPixel {
+                x,
+                y,
+                color: "00FF00".to_string(),
+            })
+            .collect(),
+        deposit,
+        event_index: Some(0),
+    }
+}
+
+async fn board() -> Board {
+    let keys = Keyspace::default().for_board(common::valkey::DEFAULT_BOARD);
+    let mut board = Board::new(
+        Arc::new(MemoryStore::new()),
+        keys,
+        RegionEncoding::Owner24,
+        BoardRules {
+            pricing: PRICING,
+            ..Default::default()
+        },
+    );
+    board.start().await.unwrap();
+    board
+}
+
+/// World coordinates of the pixels an event applied.
+async fn applied(board: &mut Board, event: &DrawEvent) -> Vec<(i32, i32)> {
+    let (pixels, _) = board.apply_event(event).await.unwrap();
+    pixels.iter().map(|p| (p.x, p.y)).collect()
+}
+
+#[tokio::test]
+async fn the_deposit_is_spent_on_pixels_in_call_order() {
+    let mut board = board().await;
+
+    // Before the pricing start block draws are free
+    let free = draw("alice.near", 9, T0_MS, 0, &[(1, 1), (2, 2)]);
+    assert_eq!(applied(&mut board, &free).await, vec![(1, 1), (2, 2)]);
+
+    // An overwrite of a claimed pixel (25) and an empty pixel (10) leave 5: the last
+    // pixel is not paid for
+    let bob = draw("bob.near", 10, T0_MS + 1_000, 40, &[(1, 1), (5, 5), (6, 6)]);
+    assert_eq!(applied(&mut board, &bob).await, vec![(1, 1), (5, 5)]);
+
+    // Too little for an overwrite, enough for the empty pixel after it
+    let carol = draw("carol.near", 11, T0_MS + 2_000, 20, &[(5, 5), (7, 7)]);
+    assert_eq!(applied(&mut board, &carol).await, vec![(7, 7)]);
+}
+
+#[tokio::test]
+async fn permanent_pixels_are_skipped_without_spending_the_deposit() {
+    let mut board = board().await;
+    let alice = draw("alice.near", 10, T0_MS, 10, &[(1, 1)]);
+    assert_eq!(applied(&mut board, &alice).await, vec![(1, 1)]);
+
+    // Alice's pixel is permanent an hour later, so the deposit covers the next one
+    let bob = draw("bob.near", 11, T0_MS + HOUR_MS, 10, &[(1, 1), (2, 2)]);
+    assert_eq!(applied(&mut board, &bob).await, vec![(2, 2)]);
+}
@@ -0,0 +1,268 @@
+//! Every `BoardStore` backend holds the same board after the same draws.
+
+use common::valkey::Keyspace;
+use common::{BoardRules, DrawEvent, DrawPixel, RegionEncoding};
+use fake_valkey::FakeValkey;
+use server::board::Board;
+use server::storage::{
+    AccountStats, Batch, BoardStore, EmbeddedStore, LeaderboardWindow, MemoryStore, RegionSummary,
+    ValkeyStore,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const T0_MS: u64 = 1_700_000_000_000;
+
+fn keys() -> Keyspace {
+    Keyspace::default().for_board(common::valkey::DEFAULT_BOARD)
+}
+
+fn draw(account: &str, block_height: u64, pixels: &[(i32, i32)]) -> DrawEvent {
+    DrawEvent {
+        predecessor_id: account.to_string(),
+        block_height,
+        block_timestamp_ms: T0_MS + block_height * 1_000,
+        pixels: pixels
+            .iter()
+            .map(|&(x, y)| DrawPixel {
+                x,
+                y,
+                color: "00FF00".to_string(),
+            })
+            .collect(),
+        deposit: 0,
+        event_index: Some(0),
+    }
+}
+
+/// Alice fills enough of region (0, 0) to open its neighbors, bob takes one of her
+/// pixels and draws in a neighbor, and the same event is replayed.
+async fn play(store: Arc<dyn BoardStore>) {
+    let rules = BoardRules {
+        region_open_threshold: 3,
+        ..Default::default()
+    };
+    let mut board = Board::new(
+        store,
+        keys(),
+        RegionEncoding::Owner24,
+        rules,
+    );
+    board.start().await.unwrap();
+    let events = [
+        draw("alice.near", 1, &[(1, 1), (2, 2), (3, 3)]),
+        draw("bob.near", 2, &[(2, 2), (130, 5)]),
+        draw("bob.near", 2, &[(2, 2), (130, 5)]),
+        draw("carol.near", 3, &[(4, 4)]),
+    ];
+    for event in &events {
+        board.apply_event(event).await.unwrap();
+    }
+}
+
+/// What the API would serve about the board `play` leaves.
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    regions: Vec<Vec<u8>>,
+    open_regions: Vec<(i32, i32)>,
+    leaderboard: (Vec<(u32, i64)>, u64),
+    bob_rank: (Option<u64>, Option<i64>),
+    accounts: Vec<(String, AccountStats)>,
+    region_owners: Vec<(u32, i64)>,
+    summaries: Vec<RegionSummary>,
+    timestamps: Vec<(u64, u64, u64)>,
+    pixel_count: i64,
+    last_applied_event: Option<(u64, u32)>,
+}
+
+async fn snapshot(store: &dyn BoardStore) -> Snapshot {
+    let keys = keys();
+    let mut open_regions = store.open_regions(&keys).await.unwrap();
+    open_regions.sort();
+    let mut regions = Vec::new();
+    for &(rx, ry) in &open_regions {
+        regions.push(store.region(&keys, rx, ry).await.unwrap());
+    }
+    let mut accounts = Vec::new();
+    for name in ["alice.near", "bob.near", "carol.near"] {
+        let owner_id = store.owner_id(&keys, name).await.unwrap().unwrap();
+        let mut stats = store.account_stats(&keys, owner_id, T0_MS).await.unwrap();
+        stats.regions.sort();
+        accounts.push((name.to_string(), stats));
+    }
+    let summaries = store.region_summaries(&keys, &open_regions).await.unwrap();
+    let bob = store.owner_id(&keys, "bob.near").await.unwrap().unwrap();
+    let all = LeaderboardWindow::All;
+    Snapshot {
+        regions,
+        open_regions,
+        leaderboard: store.leaderboard(&keys, all, T0_MS, 0, 10).await.unwrap(),
+        bob_rank: store
+            .leaderboard_rank(&keys, all, T0_MS, bob)
+            .await
+            .unwrap(),
+        accounts,
+        region_owners: store.region_owners(&keys, (0, 0), 10).await.unwrap(),
+        summaries,
+        timestamps: store.pixel_timestamps(&keys, (0, 0), 0).await.unwrap(),
+        pixel_count: store.region_pixel_count(&keys, 0, 0).await.unwrap(),
+        last_applied_event: store.last_applied_event(&keys).await.unwrap(),
+    }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("board-store-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn every_store_holds_the_same_board() {
+    let memory: Arc<dyn BoardStore> = Arc::new(MemoryStore::new());
+    play(memory.clone()).await;
+    let expected = snapshot(memory.as_ref()).await;
+    assert_eq!(
+        expected.open_regions,
+        vec![(-1, 0), (0, -1), (0, 0), (0, 1), (1, 0)]
+    );
+    assert_eq!(expected.pixel_count, 4);
+    assert_eq!(expected.last_applied_event, Some((3, 0)));
+    assert_eq!(expected.accounts[0].1.pixel_count, 2);
+    assert_eq!(expected.accounts[1].1.pixel_count, 2);
+    assert_eq!(expected.accounts[1].1.regions, vec![(0, 0), (1, 0)]);
+    // Region (0, 0): alice keeps two of her pixels, all drawn green
+    assert_eq!(
+        expected.summaries[2],
+        RegionSummary {
+            top_owner: Some((1, 2)),
+            color: Some((0, 255, 0)),
+        }
+    );
+
+    let valkey = FakeValkey::start().await;
+    let valkey_store: Arc<dyn BoardStore> = Arc::new(ValkeyStore::new(valkey.connect().await));
+    play(valkey_store.clone()).await;
+    assert_eq!(snapshot(valkey_store.as_ref()).await, expected);
+
+    let dir = temp_dir("same");
+    let embedded: Arc<dyn BoardStore> = Arc::new(EmbeddedStore::open(&dir).unwrap());
+    play(embedded.clone()).await;
+    assert_eq!(snapshot(embedded.as_ref()).await, expected);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn the_embedded_store_survives_a_restart() {
+    let dir = temp_dir("reopen");
+    let store: Arc<dyn BoardStore> = Arc::new(EmbeddedStore::open(&dir).unwrap());
+    play(store.clone()).await;
+    let expected = snapshot(store.as_ref()).await;
+    drop(store);
+
+    let store: Arc<dyn BoardStore> = Arc::new(EmbeddedStore::open(&dir).unwrap());
+    assert_eq!(snapshot(store.as_ref()).await, expected);
+    // The hourly buckets `play` wrote expired long ago, so they read as absent even
+    // before a write purges them
+    let day = LeaderboardWindow::Day;
+    assert_eq!(
+        store.leaderboard(&keys(), day, T0_MS, 0, 10).await.unwrap(),
+        (vec![], 0)
+    );
+
+    // New owners continue after the ones already handed out
+    let keys = keys();
+    let dave = store
+        .resolve_owner_id(&keys, "dave.near", 100)
+        .await
+        .unwrap();
+    assert_eq!(dave, 4);
+
+    // Expiry is stored as a time, so hourly buckets keep theirs across restarts: the
+    // old ones are purged, the current one is kept
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let mut batch = Batch::new();
+    batch.count_drawn(&keys, dave, 5, now_ms);
+    store.commit(batch).await.unwrap();
+    drop(store);
+
+    let store = EmbeddedStore::open(&dir).unwrap();
+    assert_eq!(store.owner_id(&keys, "dave.near").await.unwrap(), Some(4));
+    assert_eq!(
+        store.leaderboard(&keys, day, now_ms, 0, 10).await.unwrap(),
+        (vec![(dave, 5)], 1)
+    );
+    assert_eq!(
+        store.leaderboard(&keys, day, T0_MS, 0, 10).await.unwrap(),
+        (vec![], 0)
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn owner_ids_run_out_at_the_encoding_limit() {
+    let store = MemoryStore::new();
+    let keys = keys();
+    assert_eq!(
+        store
+            .resolve_owner_id(&keys, "alice.near", 2)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        store.resolve_owner_id(&keys, "bob.near", 2).await.unwrap(),
+        2
+    );
+    assert_eq!(
+        store
+            .resolve_owner_id(&keys, "alice.near", 2)
+            .await
+            .unwrap(),
+        1
+    );
+    let err = store
+        .resolve_owner_id(&keys, "carol.near", 2)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, server::Error::OwnerIdsExhausted { .. }));
+}
+
+#[tokio::test]
+async fn the_leaderboard_backfill_ranks_the_current_seasons_pixel_holders() {
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let keys = keys();
+    let season = keys.for_season(1);
+    let _: () = redis::pipe()
+        .set(keys.current_season(), 1)
+        .hset_multiple(season.account_pixel_count(), &[(1, 3), (2, 0), (3, -2)])
+        .hset(keys.account_pixel_count(), 4, 9)
+        .query_async(&mut con)
+        .await
+        .unwrap();
+
+    let store: Arc<dyn BoardStore> = Arc::new(ValkeyStore::new(con.clone()));
+    let mut board = Board::new(store, keys.clone(), RegionEncoding::Owner24, BoardRules::default());
+    board.start().await.unwrap();
+    assert_eq!(board.keys().season(), 1);
+    board.backfill_leaderboard().await.unwrap();
+
+    let ranked: Vec<(u32, i64)> = redis::cmd("ZRANGE")
+        .arg(season.leaderboard())
+        .arg(0)
+        .arg(-1)
+        .arg("WITHSCORES")
+        .query_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(ranked, vec![(1, 3)]);
+    let season_zero: u64 = redis::cmd("ZCARD")
+        .arg(keys.leaderboard())
+        .query_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!(season_zero, 0);
+}
@@ -0,0 +1,208 @@
+//! End-to-end harness: a board started the way the server binary starts it, backed
+//! by `FakeValkey` and served over a real socket, fed through its draw queue.
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::valkey::{Keyspace, DEFAULT_BOARD};
+use common::{
+    BoardConfig, BoardRules, DrawEvent, DrawPixel, IndexerHeartbeat, Pixel, RegionEncoding,
+};
+use fake_valkey::FakeValkey;
+use futures::{SinkExt, StreamExt};
+use redis::AsyncCommands;
+use server::api::{self, AppState};
+use server::config::Config;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tower::ServiceExt;
+
+/// Block timestamp of the first event in a test; later ones are offsets from it.
+pub const T0_MS: u64 = 1_700_000_000_000;
+
+pub struct Harness {
+    pub con: redis::aio::MultiplexedConnection,
+    /// Keys of the default board's current season.
+    pub keys: Keyspace,
+    pub app: axum::Router,
+    broadcast_tx: tokio::sync::broadcast::Sender<String>,
+    addr: std::net::SocketAddr,
+    next_height: u64,
+}
+
+impl Harness {
+    /// Host the default board with `rules` and start its consumer.
+    pub async fn start(rules: BoardRules) -> Self {
+        let valkey = FakeValkey::start().await;
+        let config = Config {
+            valkey_url: valkey.url(),
+            listen_addr: "127.0.0.1:0".into(),
+            keyspace: Keyspace::default(),
+            boards: vec![BoardConfig {
+                id: DEFAULT_BOARD.into(),
+                contract_ids: Vec::new(),
+                rules,
+            }],
+            region_encoding: RegionEncoding::Owner24,
+            health: Default::default(),
+        };
+        let handle = server::app::start_board(&config, valkey.connect().await, &config.boards[0])
+            .await
+            .unwrap();
+        let keys = handle.keys.clone();
+        let broadcast_tx = handle.broadcast_tx.clone();
+        let app = api::router(AppState {
+            boards: Arc::new(HashMap::from([(DEFAULT_BOARD.to_string(), handle)])),
+            valkey: Some(valkey.connect().await),
+            health: config.health,
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = app.clone();
+        tokio::spawn(async move { axum::serve(listener, served).await });
+
+        Self {
+            con: valkey.connect().await,
+            keys,
+            app,
+            broadcast_tx,
+            addr,
+            next_height: 100,
+        }
+    }
+
+    /// Queue a draw of red pixels by `account`, `offset_ms` after `T0_MS`, as the
+    /// indexer would, and wait until the consumer has finished it.
+    pub async fn draw(&mut self, account: &str, offset_ms: u64, pixels: &[(i32, i32)]) {
+        let event = DrawEvent {
+            predecessor_id: account.to_string(),
+            block_height: self.next_height,
+            block_timestamp_ms: T0_MS + offset_ms,
+            pixels: pixels
+                .iter()
+                .map(|&(x, y)| DrawPixel { x, y, color: "FF0000".to_string() })
+                .collect(),
+            deposit: 0,
+            event_index: Some(0),
+        };
+        self.next_height += 1;
+        let _: () = self
+            .con
+            .lpush(self.keys.draw_queue(), serde_json::to_string(&event).unwrap())
+            .await
+            .unwrap();
+        self.settle().await;
+    }
+
+    /// Record that the indexer has finished block `height`, `offset_ms` after `T0_MS`,
+    /// as it does for blocks without draws.
+    pub async fn heartbeat(&mut self, height: u64, offset_ms: u64) {
+        let heartbeat = IndexerHeartbeat {
+            block_height: height,
+            block_timestamp_ms: T0_MS + offset_ms,
+            updated_ms: T0_MS + offset_ms,
+            buffered_blocks: 0,
+        };
+        let _: () = self
+            .con
+            .hset_multiple(self.keys.indexer_heartbeat(), &heartbeat.fields())
+            .await
+            .unwrap();
+        self.next_height = self.next_height.max(height + 1);
+    }
+
+    /// Wait until the draw queue is empty and no event is being applied.
+    pub async fn settle(&mut self) {
+        for _ in 0..1000 {
+            let (queued, processing): (u64, u64) = redis::pipe()
+                .llen(self.keys.draw_queue())
+                .llen(self.keys.processing_queue())
+                .query_async(&mut self.con)
+                .await
+                .unwrap();
+            if (queued, processing) == (0, 0) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("consumer stalled");
+    }
+
+    /// GET `uri` from the router; the body as JSON, or null if it is not JSON.
+    pub async fn get(&self, uri: &str) -> (StatusCode, serde_json::Value) {
+        let (status, body) = self.get_bytes(uri).await;
+        (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+    }
+
+    pub async fn get_bytes(&self, uri: &str) -> (StatusCode, Vec<u8>) {
+        let response = self
+            .app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    /// Pixel (x, y) as served by `/api/region/{rx}/{ry}`.
+    pub async fn pixel(&self, x: i32, y: i32) -> Pixel {
+        self.pixel_at("/api", x, y).await
+    }
+
+    /// Pixel (x, y) as served by `{prefix}/region/{rx}/{ry}`, e.g. under
+    /// `/api/season/{season}`.
+    pub async fn pixel_at(&self, prefix: &str, x: i32, y: i32) -> Pixel {
+        let (rx, ry) = common::region_coords(x, y);
+        let (lx, ly) = common::local_coords(x, y);
+        let (status, blob) = self.get_bytes(&format!("{prefix}/region/{rx}/{ry}")).await;
+        assert_eq!(status, StatusCode::OK);
+        Pixel::decode(&blob[common::pixel_offset(lx, ly)..])
+    }
+
+    /// Owner id of `account`, which must have drawn.
+    pub async fn owner_id(&self, account: &str) -> u32 {
+        let (status, body) = self.get(&format!("/api/account/by-name/{account}")).await;
+        assert_eq!(status, StatusCode::OK, "{account}");
+        body["owner_id"].as_u64().unwrap() as u32
+    }
+
+    /// Open a WebSocket to the default board's live feed.
+    pub async fn ws(&self) -> Ws {
+        let subscribers = self.broadcast_tx.receiver_count();
+        let url = format!("ws://{}/ws", self.addr);
+        let (stream, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        // The socket subscribes to the feed just after the handshake
+        while self.broadcast_tx.receiver_count() == subscribers {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        Ws(stream)
+    }
+}
+
+pub struct Ws(
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
+);
+
+impl Ws {
+    pub async fn send(&mut self, msg: serde_json::Value) {
+        self.0.send(Message::text(msg.to_string())).await.unwrap();
+    }
+
+    /// The next JSON message, failing after a few seconds of silence.
+    pub async fn recv(&mut self) -> serde_json::Value {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), self.0.next())
+                .await
+                .expect("no WebSocket message")
+                .unwrap()
+                .unwrap();
+            if let Message::Text(text) = msg {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::valkey::{Keyspace, LEADERBOARD_BUCKET_MS};
+use common::{BoardRules, DrawEvent, DrawPixel, RegionEncoding};
+use fake_valkey::FakeValkey;
+use redis::AsyncCommands;
+use server::api::{AppState, BoardHandle};
+use server::board::Board;
+use server::consumer::{self, ConsumerStatus, EventSource};
+use server::storage::{BoardStore, ValkeyStore};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tower::ServiceExt;
+
+async fn store(valkey: &FakeValkey) -> Arc<dyn BoardStore> {
+    Arc::new(ValkeyStore::new(valkey.connect().await))
+}
+
+async fn start_board(valkey: &FakeValkey, keys: &Keyspace) -> Board {
+    let mut board = Board::new(
+        store(valkey).await,
+        keys.clone(),
+        RegionEncoding::Owner24,
+        BoardRules::default(),
+    );
+    board.start().await.unwrap();
+    board
+}
+
+fn draw(account: &str, block_height: u64, pixels: &[(i32, i32)]) -> DrawEvent {
+    DrawEvent {
+        predecessor_id: account.to_string(),
+        block_height,
+        block_timestamp_ms: 1_700_000_000_000 + block_height,
+        pixels: pixels
+            .iter()
+            .map(|&(x, y)| DrawPixel { x, y, color: "FF0000".to_string() })
+            .collect(),
+        deposit: 0,
+        event_index: Some(0),
+    }
+}
+
+#[tokio::test]
+async fn a_failed_commit_leaves_the_board_unchanged() {
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let keys = Keyspace::default();
+    let mut board = start_board(&valkey, &keys).await;
+    let event = draw("alice.near", 100, &[(1, 1), (2, 2)]);
+
+    valkey.fail_next("EXEC", 1);
+    let err = board.apply_event(&event).await.unwrap_err();
+    assert!(err.is_transient(), "{err}");
+
+    let region: Option<Vec<u8>> = con.get(keys.region(0, 0)).await.unwrap();
+    assert_eq!(region, None);
+    let leaderboard: u64 = con.zcard(keys.leaderboard()).await.unwrap();
+    assert_eq!(leaderboard, 0);
+    // Nor was the cached region touched
+    assert!(board.get_region(0, 0).await.unwrap().iter().all(|&b| b == 0));
+
+    let (applied, _) = board.apply_event(&event).await.unwrap();
+    assert_eq!(applied.len(), 2);
+    let count: i64 = con.hget(keys.account_pixel_count(), applied[0].owner_id).await.unwrap();
+    assert_eq!(count, 2);
+    // The catch-up copy of the draw is committed with it
+    let catch_up: Vec<String> = con.zrange(keys.draw_events(), 0, -1).await.unwrap();
+    assert_eq!(catch_up, vec![server::board::draw_message(&event, &applied)]);
+}
+
+#[tokio::test]
+async fn a_failed_read_is_an_error_not_a_closed_region() {
+    let valkey = FakeValkey::start().await;
+    let keys = Keyspace::default();
+    let mut board = start_board(&valkey, &keys).await;
+    let event = draw("alice.near", 100, &[(1, 1)]);
+
+    valkey.fail_next("SISMEMBER", 1);
+    assert!(board.apply_event(&event).await.unwrap_err().is_transient());
+
+    let (applied, _) = board.apply_event(&event).await.unwrap();
+    assert_eq!(applied.len(), 1);
+
+    // Overwriting reads the pixel's timestamp; failing that must not skip the pixel
+    valkey.fail_next("ZSCORE", 1);
+    assert!(board.apply_event(&draw("bob.near", 101, &[(1, 1)])).await.is_err());
+    let (applied, _) = board.apply_event(&draw("bob.near", 101, &[(1, 1)])).await.unwrap();
+    assert_eq!(applied.len(), 1);
+}
+
+#[tokio::test]
+async fn the_consumer_retries_without_dropping_or_repeating_events() {
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let keys = Keyspace::default();
+    let board = Arc::new(RwLock::new(start_board(&valkey, &keys).await));
+
+    for (i, pixel) in [(1, 1), (2, 2), (3, 3)].into_iter().enumerate() {
+        let event = draw("alice.near", 100 + i as u64, &[pixel]);
+        let _: () = con
+            .lpush(keys.draw_queue(), serde_json::to_string(&event).unwrap())
+            .await
+            .unwrap();
+    }
+    valkey.fail_next("EXEC", 2);
+    valkey.fail_next("LREM", 1);
+    valkey.fail_next("RPOPLPUSH", 1);
+
+    let (broadcast_tx, mut broadcast_rx) = broadcast::channel(64);
+    let consumer = tokio::spawn(consumer::run(
+        EventSource::Valkey(valkey.connect().await),
+        keys.clone(),
+        board.clone(),
+        store(&valkey).await,
+        broadcast_tx,
+        Arc::new(ConsumerStatus::default()),
+    ));
+
+    let mut draws = Vec::new();
+    while draws.len() < 3 {
+        let msg = tokio::time::timeout(Duration::from_secs(10), broadcast_rx.recv())
+            .await
+            .expect("consumer stalled")
+            .unwrap();
+        let msg: serde_json::Value = serde_json::from_str(&msg).unwrap();
+        if msg["type"] == "draw" {
+            draws.push(msg["pixels"][0]["x"].as_i64().unwrap());
+        }
+    }
+    assert_eq!(draws, vec![1, 2, 3]);
+
+    let (queued, processing): (u64, u64) = redis::pipe()
+        .llen(keys.draw_queue())
+        .llen(keys.processing_queue())
+        .query_async(&mut con)
+        .await
+        .unwrap();
+    assert_eq!((queued, processing), (0, 0));
+    let count: i64 = con.hget(keys.account_pixel_count(), 1).await.unwrap();
+    assert_eq!(count, 3);
+    let catch_up: u64 = con.zcard(keys.draw_events()).await.unwrap();
+    assert_eq!(catch_up, 3);
+
+    consumer.abort();
+}
+
+#[tokio::test]
+async fn an_event_applied_before_a_crash_is_not_applied_again() {
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let keys = Keyspace::default();
+    let event = draw("alice.near", 100, &[(1, 1)]);
+    let _: () = con
+        .lpush(keys.draw_queue(), serde_json::to_string(&event).unwrap())
+        .await
+        .unwrap();
+
+    // Crash after the event is applied but before it is acknowledged
+    valkey.disconnect_on("LREM");
+    let board = Arc::new(RwLock::new(start_board(&valkey, &keys).await));
+    let crashed = tokio::spawn(consumer::run(
+        EventSource::Valkey(valkey.connect().await),
+        keys.clone(),
+        board,
+        store(&valkey).await,
+        broadcast::channel(16).0,
+        Arc::new(ConsumerStatus::default()),
+    ));
+    while valkey.calls("LREM") == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    crashed.abort();
+    let processing: u64 = con.llen(keys.processing_queue()).await.unwrap();
+    assert_eq!(processing, 1);
+
+    // The restarted consumer takes the event again and recognizes it
+    let board = Arc::new(RwLock::new(start_board(&valkey, &keys).await));
+    let restarted = tokio::spawn(consumer::run(
+        EventSource::Valkey(valkey.connect().await),
+        keys.clone(),
+        board,
+        store(&valkey).await,
+        broadcast::channel(16).0,
+        Arc::new(ConsumerStatus::default()),
+    ));
+    loop {
+        let (queued, processing): (u64, u64) = redis::pipe()
+            .llen(keys.draw_queue())
+            .llen(keys.processing_queue())
+            .query_async(&mut con)
+            .await
+            .unwrap();
+        if (queued, processing) == (0, 0) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    restarted.abort();
+
+    let bucket = keys.leaderboard_drawn(event.block_timestamp_ms / LEADERBOARD_BUCKET_MS);
+    let drawn: i64 = con.zscore(bucket, 1).await.unwrap();
+    assert_eq!(drawn, 1);
+    let catch_up: u64 = con.zcard(keys.draw_events()).await.unwrap();
+    assert_eq!(catch_up, 1);
+}
+
+async fn get(app: &axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+async fn router(valkey: &FakeValkey, keys: &Keyspace, board: Board) -> axum::Router {
+    let handle = BoardHandle {
+        keys: keys.clone(),
+        rules: BoardRules::default(),
+        season: board.season_handle(),
+        board: Arc::new(RwLock::new(board)),
+        store: store(valkey).await,
+        broadcast_tx: broadcast::channel(16).0,
+        consumer: Arc::new(ConsumerStatus::default()),
+    };
+    server::api::router(AppState {
+        boards: Arc::new(HashMap::from([(keys.board().to_string(), handle)])),
+        valkey: Some(valkey.connect().await),
+        health: Default::default(),
+    })
+}
+
+#[tokio::test]
+async fn the_api_reports_an_outage_as_503() {
+    let valkey = FakeValkey::start().await;
+    let keys = Keyspace::default().for_board(common::valkey::DEFAULT_BOARD);
+    let board = start_board(&valkey, &keys).await;
+    let app = router(&valkey, &keys, board).await;
+
+    assert_eq!(get(&app, "/api/region/0/0").await.0, StatusCode::OK);
+    assert_eq!(get(&app, "/api/account/by-name/alice.near").await.0, StatusCode::NOT_FOUND);
+
+    valkey.set_down(true);
+    for uri in [
+        "/api/region/0/0",
+        "/api/region/5/5",
+        "/api/leaderboard",
+        "/api/open-regions",
+        "/api/account/by-name/alice.near",
+    ] {
+        let (status, body) = get(&app, uri).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{uri}");
+        assert_eq!(body["error"], "Service Unavailable", "{uri}");
+    }
+
+    valkey.set_down(false);
+    assert_eq!(get(&app, "/api/region/5/5").await.0, StatusCode::OK);
+    assert_eq!(get(&app, "/api/account/by-name/alice.near").await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn an_unreadable_region_is_served_as_an_error_and_never_drawn_over() {
+    let valkey = FakeValkey::start().await;
+    let mut con = valkey.connect().await;
+    let keys = Keyspace::default().for_board(common::valkey::DEFAULT_BOARD);
+    let mut board = start_board(&valkey, &keys).await;
+
+    // A container whose payload no longer matches its checksum
+    let mut corrupt = common::encode_region_container(
+        &vec![0u8; RegionEncoding::Owner24.blob_size()],
+        RegionEncoding::Owner24,
+    );
+    corrupt[common::REGION_HEADER_SIZE] ^= 0xFF;
+    let _: () = con.set(keys.region(0, 0), &corrupt).await.unwrap();
+
+    let err = board.apply_event(&draw("alice.near", 100, &[(1, 1)])).await.unwrap_err();
+    assert!(matches!(err, server::Error::CorruptRegion { rx: 0, ry: 0, .. }), "{err}");
+    assert!(!err.is_transient());
+    let stored: Vec<u8> = con.get(keys.region(0, 0)).await.unwrap();
+    assert_eq!(stored, corrupt);
+
+    let app = router(&valkey, &keys, board).await;
+    assert_eq!(get(&app, "/api/region/0/0").await.0, StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -1,10 +1,351 @@
-#![no_std]
+//! Drawing board contract. `draw` validates its JSON arguments and attached
+//! deposit, and emits a NEP-297 event log; the board state itself lives off-chain.
+//!
+//! Expected args: `{"pixels":[{"x":0,"y":0,"color":"FF5733"}, ...]}`.
+//!
+//! The contract only knows the pixel count, so it enforces `PIXEL_PRICE` per pixel.
+//! Overwriting a claimed pixel costs `OVERWRITE_PRICE`; that is enforced off-chain by
+//! the server, which spends the logged deposit on the call's pixels in order.
+
+#![cfg_attr(target_arch = "wasm32", no_std)]
 #![allow(non_snake_case)]
+// Only `draw` on wasm32 reaches the validation code; host builds exist for tests.
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+/// Maximum number of pixels in a single `draw` call. Keeps the event log well
+/// under the protocol's 16 KiB per-receipt log limit.
+pub const MAX_PIXELS_PER_DRAW: usize = 250;
+
+/// Coordinates must lie within `[-MAX_COORD, MAX_COORD]` on both axes.
+pub const MAX_COORD: i64 = 1_000_000;
+
+/// Price per pixel in yoctoNEAR. Set `PIXEL_PRICE_YOCTO` at build time to override.
+pub const PIXEL_PRICE: u128 = parse_u128(match option_env!("PIXEL_PRICE_YOCTO") {
+    Some(v) => v,
+    None => "100000000000000000000", // 0.0001 NEAR
+});
+
+/// Price in yoctoNEAR for overwriting a pixel that is still claimed. Not enforced
+/// on-chain: the server charges it, and the server and frontend default to the same
+/// prices, so a build overriding either one needs the same values given to them.
+pub const OVERWRITE_PRICE: u128 = parse_u128(match option_env!("OVERWRITE_PRICE_YOCTO") {
+    Some(v) => v,
+    None => "200000000000000000000", // 0.0002 NEAR
+});
+
+const fn parse_u128(s: &str) -> u128 {
+    let bytes = s.as_bytes();
+    let mut value: u128 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "price must be a decimal yoctoNEAR amount");
+        value = value * 10 + (bytes[i] - b'0') as u128;
+        i += 1;
+    }
+    value
+}
+
+/// Largest accepted argument payload in bytes.
+const MAX_INPUT_LEN: usize = 32 * 1024;
+
+/// Event log buffer size (NEAR caps total logs per receipt at 16 KiB).
+const MAX_LOG_LEN: usize = 16 * 1024;
+
+/// Largest NEAR account id length.
+const MAX_ACCOUNT_ID_LEN: usize = 64;
+
+const EVENT_STANDARD: &[u8] = b"berrydraw";
+const EVENT_VERSION: &[u8] = b"1.0.0";
+
+const INPUT_REGISTER: u64 = 0;
+const PREDECESSOR_REGISTER: u64 = 1;
 
+#[cfg(target_arch = "wasm32")]
+mod sys {
+    extern "C" {
+        pub fn input(register_id: u64);
+        pub fn predecessor_account_id(register_id: u64);
+        pub fn attached_deposit(balance_ptr: u64);
+        pub fn register_len(register_id: u64) -> u64;
+        pub fn read_register(register_id: u64, ptr: u64);
+        pub fn log_utf8(len: u64, ptr: u64);
+        pub fn panic_utf8(len: u64, ptr: u64) -> !;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
 #[panic_handler]
 fn on_panic(_info: &::core::panic::PanicInfo) -> ! {
-    loop {}
+    fail(b"unexpected panic")
+}
+
+/// Abort the call with a message; the receipt fails and no event is logged.
+#[cfg(target_arch = "wasm32")]
+fn fail(msg: &[u8]) -> ! {
+    unsafe { sys::panic_utf8(msg.len() as u64, msg.as_ptr() as u64) }
+}
+
+/// Read a host register into `buf`, failing if it does not fit.
+#[cfg(target_arch = "wasm32")]
+fn read_register(register_id: u64, buf: &mut [u8], too_long: &[u8]) -> usize {
+    let len = unsafe { sys::register_len(register_id) } as usize;
+    if len > buf.len() {
+        fail(too_long);
+    }
+    unsafe { sys::read_register(register_id, buf.as_mut_ptr() as u64) };
+    len
 }
 
+#[cfg(target_arch = "wasm32")]
 #[no_mangle]
-pub unsafe fn draw() {}
+pub extern "C" fn draw() {
+    let mut input = [0u8; MAX_INPUT_LEN];
+    unsafe { sys::input(INPUT_REGISTER) };
+    let input_len = read_register(INPUT_REGISTER, &mut input, b"args too large");
+
+    let mut predecessor = [0u8; MAX_ACCOUNT_ID_LEN];
+    unsafe { sys::predecessor_account_id(PREDECESSOR_REGISTER) };
+    let predecessor_len = read_register(PREDECESSOR_REGISTER, &mut predecessor, b"account id too long");
+
+    let mut deposit = [0u8; 16];
+    unsafe { sys::attached_deposit(deposit.as_mut_ptr() as u64) };
+    let deposit = u128::from_le_bytes(deposit);
+
+    let mut log = [0u8; MAX_LOG_LEN];
+    let account_id = &predecessor[..predecessor_len];
+    let log_len = match build_event(&input[..input_len], account_id, deposit, &mut log) {
+        Ok(len) => len,
+        Err(msg) => fail(msg),
+    };
+    unsafe { sys::log_utf8(log_len as u64, log.as_ptr() as u64) };
+}
+
+/// A validated pixel. `color` is normalized to uppercase hex.
+#[derive(Clone, Copy)]
+struct Pixel {
+    x: i64,
+    y: i64,
+    color: [u8; 6],
+}
+
+/// Validate draw args and deposit, and write the `EVENT_JSON:` log line into `out`.
+/// Returns the log length, or the reason the call was rejected.
+fn build_event(
+    args: &[u8],
+    account_id: &[u8],
+    deposit: u128,
+    out: &mut [u8],
+) -> Result<usize, &'static [u8]> {
+    let mut w = Writer { buf: out, len: 0 };
+    w.push(b"EVENT_JSON:{\"standard\":\"")?;
+    w.push(EVENT_STANDARD)?;
+    w.push(b"\",\"version\":\"")?;
+    w.push(EVENT_VERSION)?;
+    w.push(b"\",\"event\":\"draw\",\"data\":[{\"account_id\":\"")?;
+    w.push(account_id)?;
+    w.push(b"\",\"deposit\":\"")?;
+    w.push_u128(deposit)?;
+    w.push(b"\",\"pixels\":[")?;
+
+    let mut p = Parser { s: args, pos: 0 };
+    let mut count = 0usize;
+    p.expect(b'{')?;
+    let key = p.string()?;
+    if key != b"pixels" {
+        return Err(b"expected a single \"pixels\" field");
+    }
+    p.expect(b':')?;
+    p.expect(b'[')?;
+    if !p.eat(b']') {
+        loop {
+            let pixel = p.pixel()?;
+            if count == MAX_PIXELS_PER_DRAW {
+                return Err(b"too many pixels");
+            }
+            if count > 0 {
+                w.push(b",")?;
+            }
+            w.push(b"{\"x\":")?;
+            w.push_int(pixel.x)?;
+            w.push(b",\"y\":")?;
+            w.push_int(pixel.y)?;
+            w.push(b",\"color\":\"")?;
+            w.push(&pixel.color)?;
+            w.push(b"\"}")?;
+            count += 1;
+            if p.eat(b']') {
+                break;
+            }
+            p.expect(b',')?;
+        }
+    }
+    p.expect(b'}')?;
+    p.end()?;
+    if count == 0 {
+        return Err(b"no pixels");
+    }
+    if deposit < (count as u128).saturating_mul(PIXEL_PRICE) {
+        return Err(b"insufficient deposit");
+    }
+
+    w.push(b"]}]}")?;
+    Ok(w.len)
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_ws(&mut self) {
+        while self.pos < self.s.len() && matches!(self.s[self.pos], b' ' | b'\t' | b'\n' | b'\r') {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_ws();
+        if self.pos < self.s.len() && self.s[self.pos] == c {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), &'static [u8]> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(b"malformed args")
+        }
+    }
+
+    fn end(&mut self) -> Result<(), &'static [u8]> {
+        self.skip_ws();
+        if self.pos == self.s.len() {
+            Ok(())
+        } else {
+            Err(b"trailing data after args")
+        }
+    }
+
+    /// A string without escapes (keys and colors never need them).
+    fn string(&mut self) -> Result<&'a [u8], &'static [u8]> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        while self.pos < self.s.len() && self.s[self.pos] != b'"' {
+            if self.s[self.pos] == b'\\' {
+                return Err(b"malformed args");
+            }
+            self.pos += 1;
+        }
+        if self.pos == self.s.len() {
+            return Err(b"malformed args");
+        }
+        self.pos += 1;
+        Ok(&self.s[start..self.pos - 1])
+    }
+
+    fn coord(&mut self) -> Result<i64, &'static [u8]> {
+        self.skip_ws();
+        let negative = self.pos < self.s.len() && self.s[self.pos] == b'-';
+        if negative {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        let mut value: i64 = 0;
+        while self.pos < self.s.len() && self.s[self.pos].is_ascii_digit() {
+            value = value * 10 + (self.s[self.pos] - b'0') as i64;
+            if value > MAX_COORD {
+                return Err(b"coordinate out of range");
+            }
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(b"malformed coordinate");
+        }
+        Ok(if negative { -value } else { value })
+    }
+
+    fn color(&mut self) -> Result<[u8; 6], &'static [u8]> {
+        let s = self.string()?;
+        if s.len() != 6 {
+            return Err(b"malformed color");
+        }
+        let mut color = [0u8; 6];
+        for (dst, &c) in color.iter_mut().zip(s) {
+            if !c.is_ascii_hexdigit() {
+                return Err(b"malformed color");
+            }
+            *dst = c.to_ascii_uppercase();
+        }
+        Ok(color)
+    }
+
+    fn pixel(&mut self) -> Result<Pixel, &'static [u8]> {
+        let (mut x, mut y, mut color) = (None, None, None);
+        self.expect(b'{')?;
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            let slot_taken = match key {
+                b"x" => x.replace(self.coord()?).is_some(),
+                b"y" => y.replace(self.coord()?).is_some(),
+                b"color" => color.replace(self.color()?).is_some(),
+                _ => return Err(b"unexpected pixel field"),
+            };
+            if slot_taken {
+                return Err(b"duplicate pixel field");
+            }
+            if self.eat(b'}') {
+                break;
+            }
+            self.expect(b',')?;
+        }
+        match (x, y, color) {
+            (Some(x), Some(y), Some(color)) => Ok(Pixel { x, y, color }),
+            _ => Err(b"pixel is missing a field"),
+        }
+    }
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn push(&mut self, bytes: &[u8]) -> Result<(), &'static [u8]> {
+        let end = self.len + bytes.len();
+        if end > self.buf.len() {
+            return Err(b"event log too long");
+        }
+        self.buf[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn push_int(&mut self, value: i64) -> Result<(), &'static [u8]> {
+        if value < 0 {
+            self.push(b"-")?;
+        }
+        self.push_u128(value.unsigned_abs() as u128)
+    }
+
+    fn push_u128(&mut self, value: u128) -> Result<(), &'static [u8]> {
+        let mut digits = [0u8; 39];
+        let mut i = digits.len();
+        let mut v = value;
+        loop {
+            i -= 1;
+            digits[i] = b'0' + (v % 10) as u8;
+            v /= 10;
+            if v == 0 {
+                break;
+            }
+        }
+        self.push(&digits[i..])
+    }
+}
@@ -0,0 +1,213 @@
+//! Runs the compiled contract in a local wasm interpreter with stubbed NEAR host
+//! functions. No network or sandbox node needed.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+
+/// Default build-time price per pixel (0.0001 NEAR).
+const PIXEL_PRICE: u128 = 100_000_000_000_000_000_000;
+
+/// Build the contract for wasm32 once per test run, into a separate target dir so the
+/// nested cargo does not wait on the lock held by `cargo test`.
+fn contract_wasm() -> &'static [u8] {
+    static WASM: OnceLock<Vec<u8>> = OnceLock::new();
+    WASM.get_or_init(|| {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let target_dir = manifest_dir.join("target").join("wasm-tests");
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--release", "--offline", "--target", "wasm32-unknown-unknown"])
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .current_dir(&manifest_dir)
+            .status()
+            .expect("failed to run cargo");
+        assert!(status.success(), "wasm32 build failed");
+        std::fs::read(
+            target_dir.join("wasm32-unknown-unknown/release/big_drawing_berry_contract.wasm"),
+        )
+        .unwrap()
+    })
+}
+
+#[derive(Default)]
+struct Host {
+    input: Vec<u8>,
+    predecessor: String,
+    deposit: u128,
+    registers: HashMap<u64, Vec<u8>>,
+    logs: Vec<String>,
+    panic_msg: Option<String>,
+}
+
+fn memory(caller: &Caller<'_, Host>) -> wasmi::Memory {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(m)) => m,
+        _ => panic!("contract does not export memory"),
+    }
+}
+
+fn read_str(caller: &Caller<'_, Host>, len: u64, ptr: u64) -> String {
+    let mut buf = vec![0u8; len as usize];
+    memory(caller).read(caller, ptr as usize, &mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+/// Outcome of a single `draw` call.
+enum Outcome {
+    Ok(Vec<String>),
+    Panic(String),
+}
+
+fn call_draw(predecessor: &str, args: &str, deposit: u128) -> Outcome {
+    let engine = Engine::default();
+    let module = Module::new(&engine, contract_wasm()).unwrap();
+    let mut store = Store::new(
+        &engine,
+        Host {
+            input: args.as_bytes().to_vec(),
+            predecessor: predecessor.to_string(),
+            deposit,
+            ..Default::default()
+        },
+    );
+
+    let mut linker = <Linker<Host>>::new(&engine);
+    linker
+        .func_wrap("env", "input", |mut caller: Caller<'_, Host>, register_id: u64| {
+            let input = caller.data().input.clone();
+            caller.data_mut().registers.insert(register_id, input);
+        })
+        .unwrap()
+        .func_wrap("env", "predecessor_account_id", |mut caller: Caller<'_, Host>, register_id: u64| {
+            let account = caller.data().predecessor.clone().into_bytes();
+            caller.data_mut().registers.insert(register_id, account);
+        })
+        .unwrap()
+        .func_wrap("env", "attached_deposit", |mut caller: Caller<'_, Host>, ptr: u64| {
+            let deposit = caller.data().deposit.to_le_bytes();
+            memory(&caller).write(&mut caller, ptr as usize, &deposit).unwrap();
+        })
+        .unwrap()
+        .func_wrap("env", "register_len", |caller: Caller<'_, Host>, register_id: u64| -> u64 {
+            caller.data().registers.get(&register_id).map_or(u64::MAX, |r| r.len() as u64)
+        })
+        .unwrap()
+        .func_wrap("env", "read_register", |mut caller: Caller<'_, Host>, register_id: u64, ptr: u64| {
+            let data = caller.data().registers[&register_id].clone();
+            memory(&caller).write(&mut caller, ptr as usize, &data).unwrap();
+        })
+        .unwrap()
+        .func_wrap("env", "log_utf8", |mut caller: Caller<'_, Host>, len: u64, ptr: u64| {
+            let msg = read_str(&caller, len, ptr);
+            caller.data_mut().logs.push(msg);
+        })
+        .unwrap()
+        .func_wrap(
+            "env",
+            "panic_utf8",
+            |mut caller: Caller<'_, Host>, len: u64, ptr: u64| -> Result<(), wasmi::Error> {
+                let msg = read_str(&caller, len, ptr);
+                caller.data_mut().panic_msg = Some(msg.clone());
+                Err(wasmi::Error::new(msg))
+            },
+        )
+        .unwrap();
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .unwrap()
+        .start(&mut store)
+        .unwrap();
+    let draw = instance.get_typed_func::<(), ()>(&store, "draw").unwrap();
+
+    match draw.call(&mut store, ()) {
+        Ok(()) => Outcome::Ok(std::mem::take(&mut store.data_mut().logs)),
+        Err(_) => Outcome::Panic(store.data().panic_msg.clone().expect("trap without panic_utf8")),
+    }
+}
+
+/// A deposit that covers any valid pixel list, for tests not about pricing.
+const AMPLE_DEPOSIT: u128 = 250 * PIXEL_PRICE;
+
+fn assert_panics(args: &str, expected: &str) {
+    assert_panics_with_deposit(args, AMPLE_DEPOSIT, expected);
+}
+
+fn assert_panics_with_deposit(args: &str, deposit: u128, expected: &str) {
+    match call_draw("alice.near", args, deposit) {
+        Outcome::Panic(msg) => assert_eq!(msg, expected, "args: {args}"),
+        Outcome::Ok(logs) => panic!("expected panic for {args}, got logs {logs:?}"),
+    }
+}
+
+#[test]
+fn valid_draw_emits_event() {
+    let args = r#"{"pixels":[{"x":1,"y":-2,"color":"ff5733"},{ "color": "000000", "y": 0, "x": -1000000 }]}"#;
+    let logs = match call_draw("alice.near", args, 3 * PIXEL_PRICE) {
+        Outcome::Ok(logs) => logs,
+        Outcome::Panic(msg) => panic!("unexpected panic: {msg}"),
+    };
+
+    assert_eq!(
+        logs,
+        vec![concat!(
+            r#"EVENT_JSON:{"standard":"berrydraw","version":"1.0.0","event":"draw","data":[{"account_id":"alice.near","deposit":"300000000000000000000","pixels":["#,
+            r#"{"x":1,"y":-2,"color":"FF5733"},{"x":-1000000,"y":0,"color":"000000"}]}]}"#
+        )]
+    );
+
+    // The event payload must be valid JSON after the prefix
+    let json = logs[0].strip_prefix("EVENT_JSON:").unwrap();
+    assert!(json.starts_with('{') && json.ends_with('}'));
+}
+
+#[test]
+fn rejects_malformed_colors() {
+    assert_panics(r#"{"pixels":[{"x":0,"y":0,"color":"GG0000"}]}"#, "malformed color");
+    assert_panics(r#"{"pixels":[{"x":0,"y":0,"color":"FFF"}]}"#, "malformed color");
+    assert_panics(r#"{"pixels":[{"x":0,"y":0,"color":12}]}"#, "malformed args");
+}
+
+#[test]
+fn rejects_out_of_range_coordinates() {
+    assert_panics(r#"{"pixels":[{"x":1000001,"y":0,"color":"FFFFFF"}]}"#, "coordinate out of range");
+    assert_panics(r#"{"pixels":[{"x":0,"y":-99999999999,"color":"FFFFFF"}]}"#, "coordinate out of range");
+    assert_panics(r#"{"pixels":[{"x":1.5,"y":0,"color":"FFFFFF"}]}"#, "malformed args");
+}
+
+#[test]
+fn rejects_oversized_pixel_lists() {
+    let pixel = r#"{"x":0,"y":0,"color":"FFFFFF"}"#;
+    let ok = format!(r#"{{"pixels":[{}]}}"#, vec![pixel; 250].join(","));
+    assert!(matches!(call_draw("alice.near", &ok, AMPLE_DEPOSIT), Outcome::Ok(_)));
+
+    let too_many = format!(r#"{{"pixels":[{}]}}"#, vec![pixel; 251].join(","));
+    assert_panics(&too_many, "too many pixels");
+}
+
+#[test]
+fn rejects_malformed_args() {
+    assert_panics("", "malformed args");
+    assert_panics("not json", "malformed args");
+    assert_panics(r#"{"pixels":[]}"#, "no pixels");
+    assert_panics(r#"{"pixels":[{"x":0,"y":0}]}"#, "pixel is missing a field");
+    assert_panics(r#"{"pixels":[{"x":0,"x":1,"y":0,"color":"FFFFFF"}]}"#, "duplicate pixel field");
+    assert_panics(r#"{"pixels":[{"x":0,"y":0,"color":"FFFFFF","z":1}]}"#, "unexpected pixel field");
+    assert_panics(r#"{"other":[]}"#, r#"expected a single "pixels" field"#);
+    assert_panics(r#"{"pixels":[{"x":0,"y":0,"color":"FFFFFF"}]} extra"#, "trailing data after args");
+}
+
+#[test]
+fn requires_deposit_proportional_to_pixel_count() {
+    let one = r#"{"pixels":[{"x":0,"y":0,"color":"FFFFFF"}]}"#;
+    let two = r#"{"pixels":[{"x":0,"y":0,"color":"FFFFFF"},{"x":1,"y":0,"color":"FFFFFF"}]}"#;
+
+    assert!(matches!(call_draw("alice.near", one, PIXEL_PRICE), Outcome::Ok(_)));
+    assert_panics_with_deposit(one, PIXEL_PRICE - 1, "insufficient deposit");
+    assert_panics_with_deposit(one, 0, "insufficient deposit");
+    assert!(matches!(call_draw("alice.near", two, 2 * PIXEL_PRICE), Outcome::Ok(_)));
+    assert_panics_with_deposit(two, 2 * PIXEL_PRICE - 1, "insufficient deposit");
+}
@@ -8,6 +8,7 @@ import WalletButton from "./components/WalletButton";
 import Toolbar from "./components/Toolbar";
 import Minimap from "./components/Minimap";
 import ZoomControls from "./components/ZoomControls";
+import IndexerLag from "./components/IndexerLag";
 import { REGION_SIZE, PIXEL_SIZE } from "./lib/constants";
 import { resolveOwnerSync, resolveOwner } from "./lib/owner-cache";
 import type { DrawEventWS } from "./lib/types";
@@ -27,7 +28,7 @@ export default function App() {
   }, []);
 
   const pixelTimestampsRef = useRef<Map<string, number>>(new Map());
-  const { regionImages, regionDataRef, openRegionsRef } = useBoard(camera, canvasSize.w, canvasSize.h, onDrawEvent, pixelTimestampsRef);
+  const { regionImages, regionDataRef, openRegionsRef, boundsRef, behindMs } = useBoard(camera, canvasSize.w, canvasSize.h, onDrawEvent, pixelTimestampsRef);
 
   const {
     mode,
@@ -54,7 +55,14 @@ export default function App() {
     setAutoSubmit,
     unsubmittedPixelCount,
     canDrawAt,
-  } = useDrawing(callDraw, accountId, regionDataRef, openRegionsRef, pixelTimestampsRef);
+  } = useDrawing(
+    callDraw,
+    accountId,
+    regionDataRef,
+    openRegionsRef,
+    pixelTimestampsRef,
+    boundsRef
+  );
 
   // Wire the callback ref to the actual handler
   useEffect(() => {
@@ -174,6 +182,8 @@ export default function App() {
 
       <ZoomControls onZoomIn={zoomIn} onZoomOut={zoomOut} />
 
+      <IndexerLag behindMs={behindMs} />
+
       {cursorCoords && (
         <div
           style={{
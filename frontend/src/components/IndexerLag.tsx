@@ -0,0 +1,34 @@
+import { INDEXER_LAG_NOTICE_MS } from "../lib/constants";
+
+interface Props {
+  behindMs: number | null;
+}
+
+/** Notice shown while the board trails the chain, from the server's indexer status. */
+export default function IndexerLag({ behindMs }: Props) {
+  if (behindMs === null || behindMs < INDEXER_LAG_NOTICE_MS) return null;
+
+  return (
+    <div style={styles.container}>
+      Board is {Math.round(behindMs / 1000)} seconds behind
+    </div>
+  );
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  container: {
+    position: "absolute",
+    top: 16,
+    left: "50%",
+    transform: "translateX(-50%)",
+    zIndex: 100,
+    background: "rgba(200, 140, 20, 0.9)",
+    color: "#fff",
+    padding: "4px 12px",
+    borderRadius: 6,
+    fontSize: 13,
+    pointerEvents: "none",
+    userSelect: "none",
+    boxShadow: "0 2px 8px rgba(0,0,0,0.5)",
+  },
+};
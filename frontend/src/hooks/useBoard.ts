@@ -1,12 +1,20 @@
 import { useEffect, useRef, useCallback, useState } from "react";
-import type { Camera, DrawEventWS } from "../lib/types";
+import type { BoardBounds, Camera, DrawEventWS } from "../lib/types";
 import { REGION_SIZE, PIXEL_SIZE } from "../lib/constants";
-import { fetchRegion, fetchRegionsBatch, fetchOpenRegions, fetchRegionTimestamps } from "../lib/api";
-import { getCachedRegion, setCachedRegion } from "../lib/region-cache";
+import {
+  fetchRegion,
+  fetchRegionsBatch,
+  fetchOpenRegions,
+  fetchRegionTimestamps,
+  fetchRules,
+} from "../lib/api";
+import { clearCachedRegions, getCachedRegion, setCachedRegion } from "../lib/region-cache";
 import { decodeRegionToImageData, getVisibleRegions } from "../lib/canvas-renderer";
 import { WebSocketClient } from "../lib/ws";
 
 const OPEN_REGIONS_CACHE_KEY = "open_regions";
+// Season the cached regions belong to
+const SEASON_CACHE_KEY = "season";
 
 function loadCachedOpenRegions(): Set<string> {
   try {
@@ -25,9 +33,14 @@ function saveCachedOpenRegions(regions: Set<string>) {
   } catch {}
 }
 
+function loadCachedSeason(): number {
+  return parseInt(localStorage.getItem(SEASON_CACHE_KEY) || "0", 10);
+}
+
 /**
  * Manages region data fetching, caching, and live WebSocket updates.
  * Returns a Map of region keys to ImageBitmaps for rendering, plus regionDataRef.
+ * When a season ends everything loaded is dropped and the new season is loaded.
  */
 export function useBoard(
   camera: Camera,
@@ -43,8 +56,46 @@ export function useBoard(
   const wsRef = useRef<WebSocketClient | null>(null);
   const openRegionsRef = useRef<Set<string>>(loadCachedOpenRegions());
   const [openRegionsVersion, setOpenRegionsVersion] = useState(0);
+  const boundsRef = useRef<BoardBounds | null>(null);
+  // How far the board trails the chain, from the latest indexer status
+  const [behindMs, setBehindMs] = useState<number | null>(null);
+  // Bumped when a season starts, so loads begun before it are discarded
+  const generationRef = useRef(0);
+
+  const loadOpenRegions = useCallback(async () => {
+    try {
+      const regions = await fetchOpenRegions();
+      for (const r of regions) {
+        openRegionsRef.current.add(`${r.rx}:${r.ry}`);
+      }
+      saveCachedOpenRegions(openRegionsRef.current);
+      setOpenRegionsVersion((v) => v + 1);
+    } catch (e) {
+      console.error("Failed to fetch open regions:", e);
+    }
+  }, []);
+
+  // Forget the previous season's regions, in memory and cached, and load the new one's
+  const startSeason = useCallback(async (season: number) => {
+    generationRef.current += 1;
+    regionDataRef.current.clear();
+    regionMetaRef.current.clear();
+    fetchingRef.current.clear();
+    pixelTimestampsRef?.current?.clear();
+    openRegionsRef.current = new Set();
+    setRegionImages(new Map());
+    try {
+      await clearCachedRegions();
+    } catch (e) {
+      console.error("Failed to clear cached regions:", e);
+    }
+    try {
+      localStorage.setItem(SEASON_CACHE_KEY, String(season));
+    } catch {}
+    await loadOpenRegions();
+  }, [loadOpenRegions]);
 
-  // Connect WebSocket + fetch open regions
+  // Connect WebSocket + fetch rules and open regions
   useEffect(() => {
     const ws = new WebSocketClient();
     wsRef.current = ws;
@@ -63,14 +114,28 @@ export function useBoard(
       setOpenRegionsVersion((v) => v + 1);
     });
 
-    // Fetch initial open regions from server
-    fetchOpenRegions().then((regions) => {
-      for (const r of regions) {
-        openRegionsRef.current.add(`${r.rx}:${r.ry}`);
+    ws.onIndexerStatus((event) => {
+      setBehindMs(event.behind_ms);
+    });
+
+    ws.onSeasonStarted((event) => {
+      startSeason(event.season);
+    });
+
+    // Cached regions from an earlier season are dropped before anything is loaded
+    (async () => {
+      try {
+        const rules = await fetchRules();
+        boundsRef.current = rules.bounds;
+        if (rules.season !== loadCachedSeason()) {
+          await startSeason(rules.season);
+          return;
+        }
+      } catch (e) {
+        console.error("Failed to fetch board rules:", e);
       }
-      saveCachedOpenRegions(openRegionsRef.current);
-      setOpenRegionsVersion((v) => v + 1);
-    }).catch((e) => console.error("Failed to fetch open regions:", e));
+      await loadOpenRegions();
+    })();
 
     return () => ws.disconnect();
   }, []);
@@ -161,13 +226,16 @@ export function useBoard(
     }
 
     (async () => {
+      const generation = generationRef.current;
+      const stale = () => generationRef.current !== generation;
+
       // Phase 1: Load from IndexedDB cache and render immediately
       const cachedKeys = new Set<string>();
 
       await Promise.all(toLoad.map(async ({ rx, ry, key }) => {
         try {
           const cached = await getCachedRegion(rx, ry);
-          if (cached) {
+          if (cached && !stale()) {
             regionDataRef.current.set(key, cached.data);
             regionMetaRef.current.set(key, cached.lastUpdated);
             cachedKeys.add(key);
@@ -213,6 +281,7 @@ export function useBoard(
       await Promise.all(toFetchFull.map(async ({ rx, ry, key }) => {
         try {
           const { data, lastUpdated } = await fetchRegion(rx, ry);
+          if (stale()) return;
           regionDataRef.current.set(key, data);
           regionMetaRef.current.set(key, lastUpdated);
           await setCachedRegion(rx, ry, data, lastUpdated);
@@ -231,6 +300,7 @@ export function useBoard(
         await Promise.all(toLoad.map(async ({ rx, ry, key }) => {
           try {
             const timestamps = await fetchRegionTimestamps(rx, ry);
+            if (stale()) return;
             for (const [lx, ly, tsMs] of timestamps) {
               const wx = rx * REGION_SIZE + lx;
               const wy = ry * REGION_SIZE + ly;
@@ -242,12 +312,13 @@ export function useBoard(
         }));
       }
 
-      // Clean up fetching state
+      // Clean up fetching state; a new season already started over
+      if (stale()) return;
       for (const { key } of toLoad) {
         fetchingRef.current.delete(key);
       }
     })();
   }, [camera, canvasWidth, canvasHeight, openRegionsVersion, rebuildImages]);
 
-  return { regionImages, regionDataRef, openRegionsRef };
+  return { regionImages, regionDataRef, openRegionsRef, boundsRef, behindMs };
 }
@@ -1,6 +1,8 @@
 import { useState, useCallback, useRef, useEffect } from "react";
 import { REGION_SIZE, PIXEL_SIZE } from "../lib/constants";
-import type { DrawEventWS } from "../lib/types";
+import { splitIntoBatches, submitBatches } from "../lib/draw-batches";
+import { pixelInBounds } from "../lib/board-rules";
+import type { BoardBounds, DrawEventWS } from "../lib/types";
 
 export type Mode = "move" | "draw";
 
@@ -89,11 +91,15 @@ function derivePixels(strokes: Stroke[], currentStroke: Stroke): Pixel[] {
 }
 
 export function useDrawing(
-  callDraw: (pixels: Array<{ x: number; y: number; color: string }>) => Promise<void>,
+  callDraw: (
+    pixels: Array<{ x: number; y: number; color: string }>,
+    overwriteCount: number
+  ) => Promise<void>,
   accountId: string | null,
   regionDataRef: React.RefObject<Map<string, ArrayBuffer>>,
   openRegionsRef: React.RefObject<Set<string>>,
-  pixelTimestampsRef: React.RefObject<Map<string, number>>
+  pixelTimestampsRef: React.RefObject<Map<string, number>>,
+  boundsRef: React.RefObject<BoardBounds | null>
 ) {
   const [mode, setModeRaw] = useState<Mode>(() => {
     const saved = localStorage.getItem("draw_mode");
@@ -196,24 +202,36 @@ export function useDrawing(
     const submittedStrokes = [...strokesRef.current];
 
     // Capture original on-chain colors for undo recovery
+    // Pixels still within their ownership window are charged the overwrite price;
+    // permanent ones are skipped by the server and only cost the contract's base price
     const originals = new Map<string, string>();
+    const overwrites = new Set<string>();
     const regionData = regionDataRef.current;
+    const now = Date.now();
     if (regionData) {
       for (const p of pixels) {
+        const key = `${p.x},${p.y}`;
         const color = getPixelColor(p.x, p.y, new Map(), regionData);
         if (color !== null) {
-          originals.set(`${p.x},${p.y}`, color === UNDRAWN ? "000000" : color);
+          originals.set(key, color === UNDRAWN ? "000000" : color);
+          const ts = pixelTimestampsRef.current.get(key);
+          if (color !== UNDRAWN && ts && now - ts < OWNERSHIP_DURATION_MS) overwrites.add(key);
         }
       }
     }
+    // The contract takes at most MAX_PIXELS_PER_DRAW pixels per call, each paid separately
+    const batches = splitIntoBatches(pixels, (p) => overwrites.has(`${p.x},${p.y}`));
 
     isSendingRef.current = true;
     setIsSending(true);
     let success = false;
     try {
-      await callDraw(pixels);
-      success = true;
-      submittedPixelsRef.current = [...submittedPixelsRef.current, ...pixels];
+      const { sent, error } = await submitBatches(batches, callDraw);
+      if (error !== null) console.error("Failed to submit pixels:", error);
+      success = error === null;
+      if (sent.length === 0) return;
+      submittedPixelsRef.current = [...submittedPixelsRef.current, ...sent];
+      const sentKeys = new Set(sent.map(p => `${p.x},${p.y}`));
 
       // Detect strokes undone during in-flight TX
       const currentStrokeSet = new Set(strokesRef.current);
@@ -224,7 +242,18 @@ export function useDrawing(
       strokesRef.current = strokesRef.current.filter(s => !submittedSet.has(s));
       redoStackRef.current = [];
 
-      // Create recovery stroke for undone pixels
+      // Pixels of the calls that failed stay pending, unless undone during the TX
+      if (sent.length < pixels.length) {
+        const unsentStroke = derivePixels(
+          submittedStrokes.filter(s => currentStrokeSet.has(s)),
+          []
+        ).filter(p => !sentKeys.has(`${p.x},${p.y}`));
+        if (unsentStroke.length > 0) {
+          strokesRef.current = [unsentStroke, ...strokesRef.current];
+        }
+      }
+
+      // Create recovery stroke for undone pixels that reached the chain
       if (undoneStrokes.length > 0 && originals.size > 0) {
         const remainingPixelKeys = new Set(
           derivePixels(strokesRef.current, currentStrokeRef.current)
@@ -234,7 +263,7 @@ export function useDrawing(
         for (const stroke of undoneStrokes) {
           for (const p of stroke) {
             const key = `${p.x},${p.y}`;
-            if (remainingPixelKeys.has(key)) continue;
+            if (remainingPixelKeys.has(key) || !sentKeys.has(key)) continue;
             const originalColor = originals.get(key);
             if (originalColor) {
               recoveryMap.set(key, { x: p.x, y: p.y, color: originalColor });
@@ -248,8 +277,6 @@ export function useDrawing(
       }
 
       recomputePending();
-    } catch (e) {
-      console.error("Failed to submit pixels:", e);
     } finally {
       isSendingRef.current = false;
       setIsSending(false);
@@ -258,7 +285,7 @@ export function useDrawing(
         scheduleAutoSubmit();
       }
     }
-  }, [callDraw, recomputePending, scheduleAutoSubmit]);
+  }, [callDraw, recomputePending, scheduleAutoSubmit, regionDataRef, pixelTimestampsRef]);
   doSubmitRef.current = doSubmit;
 
   const startDrawing = useCallback(() => {
@@ -304,6 +331,7 @@ export function useDrawing(
 
       const px = Math.floor(worldX);
       const py = Math.floor(worldY);
+      if (!pixelInBounds(boundsRef.current, px, py)) return;
 
       // Block drawing in locked regions
       const rx = Math.floor(px / REGION_SIZE);
@@ -348,7 +376,7 @@ export function useDrawing(
 
       recomputePending();
     },
-    [mode, accountId, colorHex, regionDataRef, openRegionsRef, recomputePending]
+    [mode, accountId, colorHex, regionDataRef, openRegionsRef, boundsRef, recomputePending]
   );
 
   const fillAtPoint = useCallback(
@@ -359,6 +387,7 @@ export function useDrawing(
 
       const px = Math.floor(worldX);
       const py = Math.floor(worldY);
+      if (!pixelInBounds(boundsRef.current, px, py)) return;
 
       // Block fill in locked regions
       const fillRx = Math.floor(px / REGION_SIZE);
@@ -393,6 +422,8 @@ export function useDrawing(
           const nk = `${nx},${ny}`;
           if (visited.has(nk)) continue;
           visited.add(nk);
+          // Don't fill past the edge of a bounded board
+          if (!pixelInBounds(boundsRef.current, nx, ny)) continue;
           // Don't fill into locked regions
           const nrx = Math.floor(nx / REGION_SIZE);
           const nry = Math.floor(ny / REGION_SIZE);
@@ -420,7 +451,16 @@ export function useDrawing(
       scheduleAutoSubmit();
       setFillMode(false);
     },
-    [mode, accountId, colorHex, regionDataRef, openRegionsRef, recomputePending, scheduleAutoSubmit]
+    [
+      mode,
+      accountId,
+      colorHex,
+      regionDataRef,
+      openRegionsRef,
+      boundsRef,
+      recomputePending,
+      scheduleAutoSubmit,
+    ]
   );
 
   const undo = useCallback(() => {
@@ -493,6 +533,7 @@ export function useDrawing(
       if (!accountId) return false;
       const px = Math.floor(worldX);
       const py = Math.floor(worldY);
+      if (!pixelInBounds(boundsRef.current, px, py)) return false;
       const rx = Math.floor(px / REGION_SIZE);
       const ry = Math.floor(py / REGION_SIZE);
       const rkey = `${rx}:${ry}`;
@@ -510,7 +551,7 @@ export function useDrawing(
       if (!ts) return false;
       return Date.now() - ts < OWNERSHIP_DURATION_MS;
     },
-    [accountId, regionDataRef, openRegionsRef]
+    [accountId, regionDataRef, openRegionsRef, boundsRef]
   );
 
   return {
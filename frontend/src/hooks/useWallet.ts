@@ -1,6 +1,6 @@
 import { useCallback } from "react";
 import { useWalletSelector } from "@near-wallet-selector/react-hook";
-import { CONTRACT_ID } from "../lib/constants";
+import { CONTRACT_ID, OVERWRITE_PRICE_YOCTO, PIXEL_PRICE_YOCTO } from "../lib/constants";
 
 export function useWallet() {
   const {
@@ -12,13 +12,16 @@ export function useWallet() {
   } = useWalletSelector();
 
   const callDraw = useCallback(
-    async (pixels: Array<{ x: number; y: number; color: string }>) => {
+    async (pixels: Array<{ x: number; y: number; color: string }>, overwriteCount = 0) => {
+      const deposit =
+        BigInt(pixels.length - overwriteCount) * PIXEL_PRICE_YOCTO +
+        BigInt(overwriteCount) * OVERWRITE_PRICE_YOCTO;
       await callFunction({
         contractId: CONTRACT_ID,
         method: "draw",
         args: { pixels },
         gas: "30000000000000",
-        deposit: "0",
+        deposit: deposit.toString(),
       });
     },
     [callFunction]
@@ -1,5 +1,5 @@
 import { API_BASE } from "./constants";
-import type { RegionCoord, RegionMeta } from "./types";
+import type { BoardRules, RegionCoord, RegionMeta } from "./types";
 
 export async function fetchRegion(rx: number, ry: number): Promise<{
   data: ArrayBuffer;
@@ -29,6 +29,11 @@ export async function fetchOpenRegions(): Promise<RegionCoord[]> {
   return res.json();
 }
 
+export async function fetchRules(): Promise<BoardRules> {
+  const res = await fetch(`${API_BASE}/api/rules`);
+  return res.json();
+}
+
 /** Fetch fresh pixel timestamps (< 1hr old) for a region. Returns [[lx, ly, ts_ms], ...] */
 export async function fetchRegionTimestamps(
   rx: number,
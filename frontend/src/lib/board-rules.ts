@@ -0,0 +1,7 @@
+import type { BoardBounds } from "./types";
+
+/** Whether pixel (x, y) lies on the board; unbounded boards have no edge. */
+export function pixelInBounds(bounds: BoardBounds | null, x: number, y: number): boolean {
+  if (!bounds) return true;
+  return x >= 0 && y >= 0 && x < bounds.width && y < bounds.height;
+}
@@ -1,4 +1,9 @@
 export const CONTRACT_ID = "berryfast.near";
+// Must match the contract's PIXEL_PRICE_YOCTO / OVERWRITE_PRICE_YOCTO build settings
+export const PIXEL_PRICE_YOCTO = 100000000000000000000n; // 0.0001 NEAR
+export const OVERWRITE_PRICE_YOCTO = 200000000000000000000n; // 0.0002 NEAR
+// Must match the contract's MAX_PIXELS_PER_DRAW; larger draws are split into several calls
+export const MAX_PIXELS_PER_DRAW = 250;
 export const REGION_SIZE = 128;
 export const PIXEL_SIZE = 6; // 3 (RGB) + 3 (owner_id u24)
 export const REGION_BLOB_SIZE = REGION_SIZE * REGION_SIZE * PIXEL_SIZE;
@@ -10,3 +15,5 @@ export const GRID_ZOOM_THRESHOLD = 16; // Show grid lines at this zoom level and
 
 export const API_BASE = "https://api.berry.fastnear.com";
 export const WS_URL = "https://api.berry.fastnear.com/ws";
+// Tell the user the board is behind the chain once it trails by this much
+export const INDEXER_LAG_NOTICE_MS = 10_000;
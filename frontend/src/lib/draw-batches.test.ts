@@ -0,0 +1,53 @@
+import { describe, expect, it } from "vitest";
+import { MAX_PIXELS_PER_DRAW } from "./constants";
+import { splitIntoBatches, submitBatches, type PendingPixel } from "./draw-batches";
+
+/** A horizontal stroke of `n` pixels starting at x = 0. */
+function stroke(n: number): PendingPixel[] {
+  return Array.from({ length: n }, (_, x) => ({ x, y: 0, color: "FF0000" }));
+}
+
+describe("splitIntoBatches", () => {
+  it("splits a stroke of more than MAX_PIXELS_PER_DRAW pixels into contract-sized calls", () => {
+    const pixels = stroke(600);
+    // Every third pixel overwrites a claimed one
+    const batches = splitIntoBatches(pixels, (p) => p.x % 3 === 0);
+
+    expect(batches.map((b) => b.pixels.length)).toEqual([250, 250, 100]);
+    expect(batches.every((b) => b.pixels.length <= MAX_PIXELS_PER_DRAW)).toBe(true);
+    expect(batches.flatMap((b) => b.pixels)).toEqual(pixels);
+    expect(batches.map((b) => b.overwriteCount)).toEqual([84, 83, 33]);
+  });
+
+  it("keeps a small stroke in one call", () => {
+    expect(splitIntoBatches(stroke(250), () => false)).toHaveLength(1);
+    expect(splitIntoBatches([], () => false)).toEqual([]);
+  });
+});
+
+describe("submitBatches", () => {
+  it("pays for each call separately", async () => {
+    const calls: Array<[number, number]> = [];
+    const batches = splitIntoBatches(stroke(300), (p) => p.x >= 240);
+    const { sent, error } = await submitBatches(batches, async (pixels, overwriteCount) => {
+      calls.push([pixels.length, overwriteCount]);
+    });
+
+    expect(error).toBeNull();
+    expect(sent).toHaveLength(300);
+    expect(calls).toEqual([[250, 10], [50, 50]]);
+  });
+
+  it("stops at the first failed call and reports what went through", async () => {
+    const batches = splitIntoBatches(stroke(700), () => false);
+    let calls = 0;
+    const { sent, error } = await submitBatches(batches, async () => {
+      calls++;
+      if (calls === 2) throw new Error("rejected in wallet");
+    });
+
+    expect(calls).toBe(2);
+    expect((error as Error).message).toBe("rejected in wallet");
+    expect(sent).toEqual(stroke(250));
+  });
+});
@@ -0,0 +1,44 @@
+import { MAX_PIXELS_PER_DRAW } from "./constants";
+
+export type PendingPixel = { x: number; y: number; color: string };
+
+/** One `draw` call: at most MAX_PIXELS_PER_DRAW pixels, and how many of them are
+ *  charged the overwrite price. */
+export interface DrawBatch {
+  pixels: PendingPixel[];
+  overwriteCount: number;
+}
+
+/** Split pixels, in order, into draw calls the contract accepts. */
+export function splitIntoBatches(
+  pixels: PendingPixel[],
+  isOverwrite: (p: PendingPixel) => boolean
+): DrawBatch[] {
+  const batches: DrawBatch[] = [];
+  for (let i = 0; i < pixels.length; i += MAX_PIXELS_PER_DRAW) {
+    const chunk = pixels.slice(i, i + MAX_PIXELS_PER_DRAW);
+    batches.push({
+      pixels: chunk,
+      overwriteCount: chunk.filter(isOverwrite).length,
+    });
+  }
+  return batches;
+}
+
+/** Send batches one call at a time, stopping at the first failure. `sent` holds the
+ *  pixels of the calls that went through, a prefix of the batches' pixels. */
+export async function submitBatches(
+  batches: DrawBatch[],
+  callDraw: (pixels: PendingPixel[], overwriteCount: number) => Promise<void>
+): Promise<{ sent: PendingPixel[]; error: unknown }> {
+  const sent: PendingPixel[] = [];
+  for (const batch of batches) {
+    try {
+      await callDraw(batch.pixels, batch.overwriteCount);
+    } catch (error) {
+      return { sent, error: error ?? new Error("draw failed") };
+    }
+    sent.push(...batch.pixels);
+  }
+  return { sent, error: null };
+}
@@ -1,4 +1,4 @@
-import { get, set } from "idb-keyval";
+import { delMany, get, keys, set } from "idb-keyval";
 
 function cacheKey(rx: number, ry: number): string {
   return `region:${rx}:${ry}`;
@@ -29,3 +29,11 @@ export async function setCachedRegion(
   await set(cacheKey(rx, ry), data);
   await set(metaKey(rx, ry), lastUpdated);
 }
+
+/** Drop every cached region, e.g. once they belong to an ended season. */
+export async function clearCachedRegions(): Promise<void> {
+  const cached = (await keys()).filter(
+    (k) => typeof k === "string" && (k.startsWith("region:") || k.startsWith("region_meta:"))
+  );
+  await delMany(cached);
+}
@@ -34,4 +34,39 @@ export interface RegionsOpenedEvent {
   regions: RegionCoord[];
 }
 
-export type WSEvent = DrawEventWS | RegionsOpenedEvent;
+export interface IndexerStatusEvent {
+  type: "indexer_status";
+  block_height: number;
+  block_timestamp_ms: number;
+  updated_ms: number;
+  lag_ms: number;
+  buffered_blocks: number;
+  behind_ms: number; // how far the board trails the chain, as of sending
+}
+
+/** The board's previous season ended; everything served before belongs to it. */
+export interface SeasonStartedEvent {
+  type: "season_started";
+  season: number;
+}
+
+/** Size of a bounded board: pixels [0, width) x [0, height). */
+export interface BoardBounds {
+  width: number;
+  height: number;
+}
+
+export interface BoardRules {
+  board: string;
+  ownership_duration_ms: number;
+  region_open_threshold: number;
+  max_region_radius: number | null;
+  bounds: BoardBounds | null;
+  season: number;
+}
+
+export type WSEvent =
+  | DrawEventWS
+  | RegionsOpenedEvent
+  | IndexerStatusEvent
+  | SeasonStartedEvent;
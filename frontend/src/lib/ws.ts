@@ -1,13 +1,23 @@
 import { WS_URL } from "./constants";
-import type { DrawEventWS, RegionsOpenedEvent, WSEvent } from "./types";
+import type {
+  DrawEventWS,
+  IndexerStatusEvent,
+  RegionsOpenedEvent,
+  SeasonStartedEvent,
+  WSEvent,
+} from "./types";
 
 type DrawHandler = (event: DrawEventWS) => void;
 type RegionsOpenedHandler = (event: RegionsOpenedEvent) => void;
+type IndexerStatusHandler = (event: IndexerStatusEvent) => void;
+type SeasonStartedHandler = (event: SeasonStartedEvent) => void;
 
 export class WebSocketClient {
   private ws: WebSocket | null = null;
   private handlers: DrawHandler[] = [];
   private regionsOpenedHandlers: RegionsOpenedHandler[] = [];
+  private indexerStatusHandlers: IndexerStatusHandler[] = [];
+  private seasonStartedHandlers: SeasonStartedHandler[] = [];
   private reconnectTimer: ReturnType<typeof setTimeout> | null = null;
   private lastTimestamp = 0;
 
@@ -41,6 +51,14 @@ export class WebSocketClient {
           for (const handler of this.regionsOpenedHandlers) {
             handler(event);
           }
+        } else if (event.type === "indexer_status") {
+          for (const handler of this.indexerStatusHandlers) {
+            handler(event);
+          }
+        } else if (event.type === "season_started") {
+          for (const handler of this.seasonStartedHandlers) {
+            handler(event);
+          }
         }
       } catch {
         // ignore parse errors
@@ -80,6 +98,20 @@ export class WebSocketClient {
     };
   }
 
+  onIndexerStatus(handler: IndexerStatusHandler) {
+    this.indexerStatusHandlers.push(handler);
+    return () => {
+      this.indexerStatusHandlers = this.indexerStatusHandlers.filter((h) => h !== handler);
+    };
+  }
+
+  onSeasonStarted(handler: SeasonStartedHandler) {
+    this.seasonStartedHandlers.push(handler);
+    return () => {
+      this.seasonStartedHandlers = this.seasonStartedHandlers.filter((h) => h !== handler);
+    };
+  }
+
   disconnect() {
     if (this.reconnectTimer) {
       clearTimeout(this.reconnectTimer);